@@ -0,0 +1,15 @@
+#![no_main]
+use std::io::Write;
+
+use libfuzzer_sys::fuzz_target;
+use tempfile::NamedTempFile;
+
+use axiv::testing::hotels_reader_for;
+
+// Hotels that can't be read have to be reported as errors, never as panics, both in JSON lines and YAML
+fuzz_target!(|data: &[u8]| {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(data).unwrap();
+    let _ = hotels_reader_for("hotels.json")(file.path());
+    let _ = hotels_reader_for("hotels.yaml")(file.path());
+});
@@ -0,0 +1,27 @@
+#![no_main]
+use std::io::Write;
+
+use csv::ReaderBuilder;
+use libfuzzer_sys::fuzz_target;
+use tempfile::NamedTempFile;
+
+use axiv::testing::{csv_input, hotel, hotels, room, rooms, DataIntegrator};
+
+// Records of the input that can't be enriched have to be rejected, never panic
+fuzz_target!(|data: &[u8]| {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(data).unwrap();
+    let reader = match ReaderBuilder::new().delimiter(b'|').from_path(file.path()) {
+        Ok(reader) => reader,
+        Err(_) => return,
+    };
+    let (headers, input) = match csv_input(reader) {
+        Ok(input) => input,
+        Err(_) => return,
+    };
+    let rooms = rooms(vec![room().build(), room().source("GTA").build()]);
+    let hotels = hotels(vec![hotel().build()]);
+    DataIntegrator::new(rooms, hotels, headers, input)
+        .with_fuzzy_rooms(0.5)
+        .for_each(drop);
+});
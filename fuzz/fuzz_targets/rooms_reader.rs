@@ -0,0 +1,14 @@
+#![no_main]
+use std::io::Write;
+
+use libfuzzer_sys::fuzz_target;
+use tempfile::NamedTempFile;
+
+use axiv::testing::rooms_reader;
+
+// Rooms that can't be read have to be reported as errors, never as panics
+fuzz_target!(|data: &[u8]| {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(data).unwrap();
+    let _ = rooms_reader(file.path());
+});
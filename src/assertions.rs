@@ -0,0 +1,142 @@
+use std::collections::BTreeSet;
+
+use anyhow::{anyhow, Result};
+
+use crate::data::Output;
+use crate::settings::Settings;
+
+/// What the run has seen, used to check whether the output makes sense.
+#[derive(Debug, Default)]
+pub struct Tally {
+    /// Records read from the input, including the rejected ones
+    pub inputs: usize,
+    /// Enriched records passed to the output
+    pub outputs: usize,
+    /// Sources of the enriched records
+    pub sources: BTreeSet<String>,
+}
+
+impl Tally {
+    pub fn add(&mut self, output: &Output) {
+        self.outputs += 1;
+        if !self.sources.contains(&output.source) {
+            self.sources.insert(output.source.clone());
+        }
+    }
+}
+
+/// Checks of the output evaluated once the run is finished, they guard us against truncated input files.
+#[derive(Debug, Default, Clone)]
+pub struct Assertions {
+    pub min_records: Option<usize>,
+    /// Bounds of the ratio of the output records to the input records
+    pub min_ratio: Option<f64>,
+    pub max_ratio: Option<f64>,
+    /// Sources that have to be present in the output
+    pub sources: Vec<String>,
+}
+
+impl Assertions {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            min_records: settings.checks.min_records,
+            min_ratio: settings.checks.min_output_ratio,
+            max_ratio: settings.checks.max_output_ratio,
+            sources: settings.checks.require_sources.clone(),
+        }
+    }
+
+    /// It throws an error which lists all of the violated assertions.
+    pub fn check(&self, tally: &Tally) -> Result<()> {
+        let mut violations = Vec::new();
+        if let Some(min_records) = self.min_records {
+            if tally.outputs < min_records {
+                violations.push(format!(
+                    "{} record(s) in the output, at least {} expected",
+                    tally.outputs, min_records
+                ));
+            }
+        }
+        let ratio = if tally.inputs == 0 {
+            0.0
+        } else {
+            tally.outputs as f64 / tally.inputs as f64
+        };
+        if let Some(min_ratio) = self.min_ratio {
+            if ratio < min_ratio {
+                violations.push(format!(
+                    "output/input ratio {:.2}, at least {} expected",
+                    ratio, min_ratio
+                ));
+            }
+        }
+        if let Some(max_ratio) = self.max_ratio {
+            if ratio > max_ratio {
+                violations.push(format!(
+                    "output/input ratio {:.2}, at most {} expected",
+                    ratio, max_ratio
+                ));
+            }
+        }
+        let missing = self
+            .sources
+            .iter()
+            .filter(|source| !tally.sources.contains(*source))
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            violations.push(format!("missing source(s) {}", missing.join(", ")));
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "The output doesn't look right: {}!",
+                violations.join("; ")
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tally(inputs: usize, outputs: usize, sources: &[&str]) -> Tally {
+        Tally {
+            inputs,
+            outputs,
+            sources: sources.iter().map(|source| source.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn pass_without_assertions() -> Result<()> {
+        Assertions::default().check(&tally(0, 0, &[]))
+    }
+
+    #[test]
+    fn list_all_violations() -> Result<()> {
+        let assertions = Assertions {
+            min_records: Some(5),
+            min_ratio: Some(0.9),
+            max_ratio: Some(1.0),
+            sources: vec![
+                String::from("IHG"),
+                String::from("GTA"),
+                String::from("MARR"),
+            ],
+        };
+        assertions.check(&tally(6, 6, &["GTA", "IHG", "MARR"]))?;
+        assert_eq!(
+            assertions
+                .check(&tally(10, 3, &["IHG"]))
+                .expect_err("This should fail")
+                .to_string(),
+            "The output doesn't look right: 3 record(s) in the output, at least 5 expected; \
+             output/input ratio 0.30, at least 0.9 expected; missing source(s) GTA, MARR!"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,303 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::data::Output;
+use crate::lock;
+use crate::Outputs;
+
+/// How many records are saved in a single block of the file.
+const BLOCK_SIZE: usize = 1000;
+
+/// Prices are decimals with this many digits after the point.
+const PRICE_SCALE: u32 = 2;
+
+/// Schema of the records, the same columns as the ones of the SQLite output.
+/// Dates have the `date` logical type (days since 1970-01-01) and prices the `decimal` logical type.
+pub fn schema() -> Value {
+    let string = |name: &str| json!({"name": name, "type": "string"});
+    let int = |name: &str| json!({"name": name, "type": "int"});
+    let date = |name: &str| json!({"name": name, "type": {"type": "int", "logicalType": "date"}});
+    let optional =
+        |name: &str, kind: &str| json!({"name": name, "type": ["null", kind], "default": null});
+    json!({
+        "type": "record",
+        "name": "Output",
+        "namespace": "axiv",
+        "fields": [
+            string("room_type_meal"),
+            string("room_code"),
+            string("source"),
+            string("hotel_name"),
+            string("city_name"),
+            string("city_code"),
+            {"name": "hotel_category", "type": "float"},
+            int("pax"),
+            int("adults"),
+            int("children"),
+            string("room_name"),
+            date("checkin"),
+            date("checkout"),
+            {
+                "name": "price",
+                "type": {"type": "bytes", "logicalType": "decimal", "precision": 18, "scale": PRICE_SCALE}
+            },
+            optional("latitude", "double"),
+            optional("longitude", "double"),
+            string("hotel_code"),
+            string("meal"),
+            optional("rank", "int"),
+            optional("offers", "int"),
+            optional("winning_source", "string"),
+        ]
+    })
+}
+
+/// Avro object container file the enriched records are saved in, with the schema embedded in its header,
+/// so it can be read without the schema registry, e.g by Kafka Connect. The blocks are not compressed.
+pub struct AvroWriter<W: Write> {
+    inner: W,
+    sync: [u8; 16],
+}
+
+impl AvroWriter<BufWriter<File>> {
+    /// Create the file at the given path, with the sync marker unique to it.
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = lock::lock_for_writing(path)?;
+        let mut hasher = Sha256::new();
+        hasher.update(path.to_string_lossy().as_bytes());
+        if let Ok(now) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+            hasher.update(now.as_nanos().to_le_bytes());
+        }
+        let mut sync = [0; 16];
+        sync.copy_from_slice(&hasher.finalize()[..16]);
+        Self::new(BufWriter::new(file), sync)
+    }
+}
+
+impl<W: Write> AvroWriter<W> {
+    /// Start the file with its header, the sync marker separates the blocks of the file.
+    pub fn new(mut inner: W, sync: [u8; 16]) -> Result<Self> {
+        let mut header = b"Obj\x01".to_vec();
+        let schema = schema().to_string();
+        long(&mut header, 2);
+        bytes(&mut header, b"avro.schema");
+        bytes(&mut header, schema.as_bytes());
+        bytes(&mut header, b"avro.codec");
+        bytes(&mut header, b"null");
+        long(&mut header, 0);
+        header.extend_from_slice(&sync);
+        inner
+            .write_all(&header)
+            .with_context(|| "Couldn't save the header of the Avro output!")?;
+        Ok(Self { inner, sync })
+    }
+
+    /// Save the enriched records in blocks, returns how many of them were saved.
+    pub fn write(&mut self, outputs: Outputs) -> Result<usize> {
+        let mut outputs = outputs.peekable();
+        let mut records = 0;
+        while outputs.peek().is_some() {
+            let mut block = Vec::new();
+            let mut count = 0;
+            for output_res in outputs.by_ref().take(BLOCK_SIZE) {
+                encode(&mut block, &output_res?);
+                count += 1;
+            }
+            let mut framed = Vec::with_capacity(block.len() + 32);
+            long(&mut framed, count);
+            long(&mut framed, block.len() as i64);
+            framed.extend_from_slice(&block);
+            framed.extend_from_slice(&self.sync);
+            self.inner
+                .write_all(&framed)
+                .with_context(|| "Couldn't save the block of the Avro output!")?;
+            records += count as usize;
+        }
+        self.inner
+            .flush()
+            .with_context(|| "Couldn't save the Avro output!")?;
+        Ok(records)
+    }
+}
+
+/// Record in the binary encoding of Avro, in the order of the fields of the schema.
+fn encode(buffer: &mut Vec<u8>, output: &Output) {
+    let ranking = output.ranking.as_ref();
+    string(buffer, &output.room_type_meal);
+    string(buffer, &output.room_code);
+    string(buffer, &output.source);
+    string(buffer, &output.hotel_name);
+    string(buffer, &output.city_name);
+    string(buffer, &output.city_code);
+    buffer.extend_from_slice(&output.hotel_category.to_le_bytes());
+    long(buffer, output.pax.into());
+    long(buffer, output.adults.into());
+    long(buffer, output.children.into());
+    string(buffer, &output.room_name);
+    long(buffer, days(output.checkin));
+    long(buffer, days(output.checkout));
+    bytes(buffer, &decimal(output.price));
+    optional(buffer, output.latitude, |buffer, value| {
+        buffer.extend_from_slice(&value.to_le_bytes())
+    });
+    optional(buffer, output.longitude, |buffer, value| {
+        buffer.extend_from_slice(&value.to_le_bytes())
+    });
+    string(buffer, &output.hotel_code);
+    string(buffer, &output.meal);
+    optional(buffer, ranking.map(|ranking| ranking.rank as i64), long);
+    optional(buffer, ranking.map(|ranking| ranking.offers as i64), long);
+    optional(
+        buffer,
+        ranking.map(|ranking| &ranking.winning_source),
+        |buffer, value| string(buffer, value),
+    );
+}
+
+/// Ints and longs are zig-zag encoded variable-length numbers, so the small ones take a single byte.
+fn long(buffer: &mut Vec<u8>, value: i64) {
+    let mut zigzag = ((value << 1) ^ (value >> 63)) as u64;
+    while zigzag >= 0x80 {
+        buffer.push((zigzag & 0x7f) as u8 | 0x80);
+        zigzag >>= 7;
+    }
+    buffer.push(zigzag as u8);
+}
+
+fn bytes(buffer: &mut Vec<u8>, value: &[u8]) {
+    long(buffer, value.len() as i64);
+    buffer.extend_from_slice(value);
+}
+
+fn string(buffer: &mut Vec<u8>, value: &str) {
+    bytes(buffer, value.as_bytes());
+}
+
+/// Value of the `["null", type]` union, which is the index of the branch followed by the value.
+fn optional<T>(buffer: &mut Vec<u8>, value: Option<T>, encode: impl Fn(&mut Vec<u8>, T)) {
+    match value {
+        None => long(buffer, 0),
+        Some(value) => {
+            long(buffer, 1);
+            encode(buffer, value);
+        }
+    }
+}
+
+/// Date as the number of days since 1970-01-01.
+fn days(date: NaiveDate) -> i64 {
+    (date - NaiveDate::from_ymd(1970, 1, 1)).num_days()
+}
+
+/// Price as the shortest big-endian two's complement of its unscaled value, e.g 85.50 is 8550.
+fn decimal(price: f64) -> Vec<u8> {
+    let unscaled = (price * 10f64.powi(PRICE_SCALE as i32)).round() as i64;
+    let bytes = unscaled.to_be_bytes();
+    let mut start = 0;
+    // the leading bytes that only repeat the sign of the next one are not needed
+    while start < bytes.len() - 1
+        && ((bytes[start] == 0x00 && bytes[start + 1] & 0x80 == 0)
+            || (bytes[start] == 0xff && bytes[start + 1] & 0x80 != 0))
+    {
+        start += 1;
+    }
+    bytes[start..].to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    /// Reader of the binary encoding, just enough to check what was written.
+    struct Decoder<'a>(&'a [u8]);
+
+    impl Decoder<'_> {
+        fn long(&mut self) -> i64 {
+            let (mut value, mut shift) = (0u64, 0);
+            loop {
+                let byte = self.0[0];
+                self.0 = &self.0[1..];
+                value |= u64::from(byte & 0x7f) << shift;
+                shift += 7;
+                if byte & 0x80 == 0 {
+                    return (value >> 1) as i64 ^ -((value & 1) as i64);
+                }
+            }
+        }
+
+        fn bytes(&mut self) -> &[u8] {
+            let len = self.long() as usize;
+            let (value, rest) = self.0.split_at(len);
+            self.0 = rest;
+            value
+        }
+    }
+
+    #[test]
+    fn encode_numbers() {
+        let encoded = |value| {
+            let mut buffer = Vec::new();
+            long(&mut buffer, value);
+            buffer
+        };
+        assert_eq!(encoded(0), vec![0x00]);
+        assert_eq!(encoded(-1), vec![0x01]);
+        assert_eq!(encoded(1), vec![0x02]);
+        assert_eq!(encoded(64), vec![0x80, 0x01]);
+        assert_eq!(decimal(85.5), vec![0x21, 0x66]);
+        assert_eq!(decimal(1.27), vec![0x7f]);
+        assert_eq!(decimal(1.28), vec![0x00, 0x80]);
+        assert_eq!(decimal(-0.01), vec![0xff]);
+        assert_eq!(decimal(-1.29), vec![0xff, 0x7f]);
+    }
+
+    #[test]
+    fn save_container_file() -> Result<()> {
+        let sync = [7; 16];
+        let mut writer = AvroWriter::new(Vec::new(), sync)?;
+        let outputs: Outputs = Box::new((0..=BLOCK_SIZE).map(|_| Ok(testing::output().build())));
+        assert_eq!(writer.write(outputs)?, BLOCK_SIZE + 1);
+
+        let file = writer.inner;
+        assert_eq!(&file[..4], b"Obj\x01");
+        let mut decoder = Decoder(&file[4..]);
+        assert_eq!(decoder.long(), 2);
+        assert_eq!(decoder.bytes(), b"avro.schema");
+        let schema: Value = serde_json::from_slice(decoder.bytes())?;
+        assert_eq!(schema, super::schema());
+        assert_eq!(decoder.bytes(), b"avro.codec");
+        assert_eq!(decoder.bytes(), b"null");
+        assert_eq!(decoder.long(), 0);
+        assert_eq!(&decoder.0[..16], &sync);
+        decoder.0 = &decoder.0[16..];
+
+        // the records that don't fit in the first block go into the next one
+        assert_eq!(decoder.long(), BLOCK_SIZE as i64);
+        let size = decoder.long() as usize;
+        let mut record = Decoder(&decoder.0[..size]);
+        assert_eq!(record.bytes(), b"EZ F");
+        for _ in 0..5 {
+            record.bytes();
+        }
+        record.0 = &record.0[4..];
+        assert_eq!((record.long(), record.long(), record.long()), (1, 1, 0));
+        assert_eq!(record.bytes(), b"Einzelzimmer");
+        assert_eq!(record.long(), 17733);
+        assert_eq!(record.long(), 17734);
+        assert_eq!(record.bytes(), &[0x21, 0x66]);
+        assert_eq!((record.long(), record.long()), (0, 0));
+        decoder.0 = &decoder.0[size..];
+        assert_eq!(&decoder.0[..16], &sync);
+        decoder.0 = &decoder.0[16..];
+        assert_eq!(decoder.long(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+// only the PostgreSQL sink is slow enough to need it so far
+#![cfg_attr(not(feature = "postgres"), allow(dead_code))]
+
+use std::fmt;
+use std::io::{self, Write};
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread;
+
+use anyhow::{anyhow, Result};
+
+/// Size of the chunks of the output that are handed over to the sink.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// How full the queue in front of the slow sink was during the run.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct QueueStats {
+    /// How many chunks can wait for the sink at once
+    pub capacity: usize,
+    /// The most chunks that were waiting for the sink at once
+    pub max_depth: usize,
+    /// How many times the reading of the input had to wait for the sink, because the queue was full
+    pub waits: usize,
+}
+
+impl fmt::Display for QueueStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "at most {} of {} chunks queued, {} waits for the sink",
+            self.max_depth, self.capacity, self.waits
+        )
+    }
+}
+
+/// Writer of the chunks of the output into the queue in front of the sink.
+/// Once the queue is full, the writes block until the sink catches up, so a slow sink throttles the reading
+/// of the input instead of the output piling up in memory.
+pub struct QueueWriter {
+    sender: SyncSender<Vec<u8>>,
+    chunk: Vec<u8>,
+    depth: Arc<AtomicUsize>,
+    stats: QueueStats,
+}
+
+impl QueueWriter {
+    fn send(&mut self) -> io::Result<()> {
+        if self.chunk.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::replace(&mut self.chunk, Vec::with_capacity(CHUNK_SIZE));
+        // the depth is raised before the chunk is sent, so the sink never takes it below zero
+        let depth = self.depth.fetch_add(1, Ordering::SeqCst) + 1;
+        self.stats.max_depth = self.stats.max_depth.max(depth.min(self.stats.capacity));
+        let sent = match self.sender.try_send(chunk) {
+            Err(TrySendError::Full(chunk)) => {
+                self.stats.waits += 1;
+                self.sender.send(chunk).map_err(|_| ())
+            }
+            Err(TrySendError::Disconnected(_)) => Err(()),
+            Ok(()) => Ok(()),
+        };
+        sent.map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "The sink stopped!"))
+    }
+}
+
+impl Write for QueueWriter {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.chunk.extend_from_slice(bytes);
+        if self.chunk.len() >= CHUNK_SIZE {
+            self.send()?;
+        }
+        Ok(bytes.len())
+    }
+
+    /// The chunk is handed over to the sink, there's no waiting for the sink to write it.
+    fn flush(&mut self) -> io::Result<()> {
+        self.send()
+    }
+}
+
+/// Write everything the producer writes into the sink on another thread, with at most `capacity` chunks
+/// waiting for the sink at once. Returns the sink, together with the outcome of the producer and the stats of the queue.
+/// When the sink fails, its error is returned rather than the error of the producer it caused.
+pub fn write_bounded<W, T, F>(
+    sink: W,
+    capacity: NonZeroUsize,
+    produce: F,
+) -> Result<(W, T, QueueStats)>
+where
+    W: Write + Send,
+    F: FnOnce(&mut QueueWriter) -> Result<T>,
+{
+    let (sender, receiver) = sync_channel(capacity.get());
+    let depth = Arc::new(AtomicUsize::new(0));
+    thread::scope(|scope| {
+        let sink_depth = Arc::clone(&depth);
+        let sink = scope.spawn(move || drain(sink, receiver, &sink_depth));
+        let mut writer = QueueWriter {
+            sender,
+            chunk: Vec::with_capacity(CHUNK_SIZE),
+            depth,
+            stats: QueueStats {
+                capacity: capacity.get(),
+                ..QueueStats::default()
+            },
+        };
+        let produced = produce(&mut writer).and_then(|produced| {
+            writer.flush()?;
+            Ok(produced)
+        });
+        let stats = writer.stats;
+        // the sink is done once the queue is closed and empty
+        drop(writer);
+        let sink = sink.join().map_err(|_| anyhow!("The sink panicked!"))??;
+        Ok((sink, produced?, stats))
+    })
+}
+
+fn drain<W: Write>(mut sink: W, receiver: Receiver<Vec<u8>>, depth: &AtomicUsize) -> Result<W> {
+    for chunk in receiver {
+        depth.fetch_sub(1, Ordering::SeqCst);
+        sink.write_all(&chunk)?;
+    }
+    sink.flush()?;
+    Ok(sink)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Sink that takes its time with each of the chunks.
+    struct SlowSink(Vec<u8>);
+
+    impl Write for SlowSink {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            thread::sleep(Duration::from_millis(5));
+            self.0.extend_from_slice(bytes);
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn throttle_to_slow_sink() -> Result<()> {
+        let line = [b'x'; 1024];
+        let (sink, lines, stats) = write_bounded(
+            SlowSink(Vec::new()),
+            NonZeroUsize::new(2).unwrap(),
+            |writer| {
+                for _ in 0..1000 {
+                    writer.write_all(&line)?;
+                }
+                Ok(1000)
+            },
+        )?;
+        assert_eq!(lines, 1000);
+        assert_eq!(sink.0.len(), 1000 * 1024);
+        assert!(stats.max_depth <= 2);
+        assert!(stats.waits > 0);
+        Ok(())
+    }
+
+    /// Sink that fails right away.
+    struct BrokenSink;
+
+    impl Write for BrokenSink {
+        fn write(&mut self, _bytes: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("connection reset"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn report_failed_sink() {
+        let error = write_bounded(BrokenSink, NonZeroUsize::new(1).unwrap(), |writer| {
+            for _ in 0..1000 {
+                writer.write_all(&[b'x'; 1024])?;
+            }
+            Ok(())
+        })
+        .err()
+        .unwrap();
+        assert_eq!(error.to_string(), "connection reset");
+    }
+}
@@ -0,0 +1,188 @@
+use std::fs::read_to_string;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Settings;
+
+/// Jobs of the batch run, each of them is a run of its own with the options given before the `batch` command,
+/// e.g
+/// ```yaml
+/// jobs:
+///   - name: ihg
+///     input: ihg.csv
+///     output: ihg_enriched.csv
+///     profile: IHG
+///   - name: gta
+///     input: gta.csv
+///     output: gta_enriched.csv
+///     rooms: gta_room_names.csv
+/// ```
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Jobs {
+    pub jobs: Vec<Job>,
+}
+
+/// One of the jobs of the batch, everything that's not given falls back to the shared options.
+/// It's also what's sent to the daemon by `axiv submit`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Job {
+    /// Name of the job, it's shown in the summary of the batch
+    pub name: String,
+    pub input: String,
+    pub output: String,
+    /// Name of the profile from the config file which describes how the input is read
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub rooms: Option<String>,
+    #[serde(default)]
+    pub hotels: Option<String>,
+    #[serde(default)]
+    pub rejects: Option<String>,
+}
+
+impl Jobs {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let content = read_to_string(path).with_context(|| "Path to the jobs file is invalid!")?;
+        let jobs: Jobs = serde_yaml::from_str(&content)
+            .with_context(|| format!("Couldn't parse the jobs file {}!", path.display()))?;
+        for (index, job) in jobs.jobs.iter().enumerate() {
+            for other in &jobs.jobs[..index] {
+                if other.name == job.name {
+                    return Err(anyhow!(
+                        "There's more than one job {} in the jobs file!",
+                        job.name
+                    ));
+                }
+                if other.output == job.output {
+                    return Err(anyhow!(
+                        "Jobs {} and {} can't be saved at the same output {}!",
+                        other.name,
+                        job.name,
+                        job.output
+                    ));
+                }
+            }
+        }
+        Ok(jobs)
+    }
+}
+
+impl Job {
+    /// Settings of the run of the job, the checksums given for the shared files don't apply to its own files.
+    pub fn settings(&self, shared: &Settings) -> Settings {
+        let mut settings = shared.clone();
+        settings.command = None;
+        settings.input = self.input.clone();
+        settings.checksums.input_sha256 = None;
+        settings.output = self.output.clone();
+        if let Some(profile) = &self.profile {
+            settings.profile = Some(profile.clone());
+        }
+        if let Some(rooms) = &self.rooms {
+            settings.rooms = rooms.clone();
+            settings.checksums.rooms_sha256 = None;
+        }
+        if let Some(hotels) = &self.hotels {
+            settings.hotels = hotels.clone();
+            settings.checksums.hotels_sha256 = None;
+        }
+        if self.rejects.is_some() {
+            settings.rejects = self.rejects.clone();
+        }
+        settings
+    }
+}
+
+/// Run the task for each of the items, at most `parallel` of them at a time.
+/// The results are in the order of the items, no matter in which order they were done.
+pub fn run_parallel<T, R, F>(items: &[T], parallel: NonZeroUsize, task: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(items.len()));
+    thread::scope(|scope| {
+        for _ in 0..parallel.get().min(items.len()) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                match items.get(index) {
+                    Some(item) => {
+                        let result = task(item);
+                        results.lock().unwrap().push((index, result));
+                    }
+                    None => break,
+                }
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap();
+    results.sort_unstable_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn read_jobs() -> Result<()> {
+        let jobs: Jobs = serde_yaml::from_str(
+            r#"
+            jobs:
+              - name: ihg
+                input: ihg.csv
+                output: ihg_enriched.csv
+                profile: IHG
+              - name: gta
+                input: gta.csv
+                output: gta_enriched.csv
+                rooms: gta_room_names.csv
+            "#,
+        )?;
+        let shared = Settings::from_arguments(["axiv", "--rooms-sha256", "abc"]);
+        let ihg = jobs.jobs[0].settings(&shared);
+        assert_eq!(
+            (ihg.input.as_str(), ihg.output.as_str()),
+            ("ihg.csv", "ihg_enriched.csv")
+        );
+        assert_eq!(ihg.profile.as_deref(), Some("IHG"));
+        assert_eq!(ihg.rooms, "room_names.csv");
+        assert_eq!(ihg.checksums.rooms_sha256.as_deref(), Some("abc"));
+
+        let gta = jobs.jobs[1].settings(&shared);
+        assert_eq!(gta.profile, None);
+        assert_eq!(gta.rooms, "gta_room_names.csv");
+        assert_eq!(gta.checksums.rooms_sha256, None);
+        assert_eq!(gta.hotels, "hotels.json");
+        Ok(())
+    }
+
+    #[test]
+    fn run_in_order_with_bounded_parallelism() {
+        let running = AtomicUsize::new(0);
+        let most_running = AtomicUsize::new(0);
+        let items: Vec<u64> = (0..8).collect();
+        let results = run_parallel(&items, NonZeroUsize::new(3).unwrap(), |item| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            most_running.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(10 * (8 - item)));
+            running.fetch_sub(1, Ordering::SeqCst);
+            item * 2
+        });
+        assert_eq!(results, vec![0, 2, 4, 6, 8, 10, 12, 14]);
+        assert!(most_running.load(Ordering::SeqCst) <= 3);
+    }
+}
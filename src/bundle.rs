@@ -0,0 +1,99 @@
+#[cfg(feature = "bundle")]
+use std::fs::File;
+#[cfg(feature = "bundle")]
+use std::io::copy;
+use std::path::Path;
+
+#[cfg(feature = "bundle")]
+use anyhow::Context;
+use anyhow::Result;
+use tempfile::NamedTempFile;
+#[cfg(feature = "bundle")]
+use zip::ZipArchive;
+
+/// Separates the path to the archive from the name of its member, e.g `feed.zip#hotels.yaml`.
+const MEMBER_SEPARATOR: char = '#';
+
+/// Location of the given member of the bundle, e.g `feed.zip#input.csv`.
+pub fn member_location(bundle: &str, member: &str) -> String {
+    format!("{}{}{}", bundle, MEMBER_SEPARATOR, member)
+}
+
+/// Split the location into the ZIP archive and the name of its member, if it points into one.
+/// Locations like `feed.zip` point to the conventional member, e.g `hotels.json` for the hotels.
+pub fn split<'a>(location: &'a str, conventional: &'a str) -> Option<(&'a str, &'a str)> {
+    match location.rsplit_once(MEMBER_SEPARATOR) {
+        Some((archive, member)) if archive.to_lowercase().ends_with(".zip") => {
+            Some((archive, member))
+        }
+        _ if location.to_lowercase().ends_with(".zip") => Some((location, conventional)),
+        _ => None,
+    }
+}
+
+/// Copy the member of the archive to a temporary file which is removed as soon as it's dropped,
+/// so it can be handed over to the readers just like any other file.
+#[cfg(feature = "bundle")]
+pub fn extract(archive: &Path, member: &str) -> Result<NamedTempFile> {
+    let file = File::open(archive)
+        .with_context(|| format!("Path to the bundle {} is invalid!", archive.display()))?;
+    let mut archive = ZipArchive::new(file)
+        .with_context(|| format!("{} is not a valid ZIP archive!", archive.display()))?;
+    let mut entry = archive
+        .by_name(member)
+        .with_context(|| format!("There's no {} in the bundle!", member))?;
+    let mut extracted =
+        NamedTempFile::new().with_context(|| "Couldn't create a temporary file!")?;
+    copy(&mut entry, &mut extracted)
+        .with_context(|| format!("Couldn't extract {} from the bundle!", member))?;
+    Ok(extracted)
+}
+
+#[cfg(not(feature = "bundle"))]
+pub fn extract(archive: &Path, member: &str) -> Result<NamedTempFile> {
+    Err(anyhow::anyhow!(
+        "axiv was built without the bundle feature, so {} can't be read from {}!",
+        member,
+        archive.display()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "bundle")]
+    use std::fs::read_to_string;
+
+    use super::*;
+
+    #[test]
+    fn split_locations() {
+        assert_eq!(
+            split("feed.zip", "hotels.json"),
+            Some(("feed.zip", "hotels.json"))
+        );
+        assert_eq!(
+            split("data/FEED.ZIP#hotels.yaml", "hotels.json"),
+            Some(("data/FEED.ZIP", "hotels.yaml"))
+        );
+        assert_eq!(split("hotels.json", "hotels.json"), None);
+        assert_eq!(split("data#1/hotels.json", "hotels.json"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "bundle")]
+    fn extract_members() -> Result<()> {
+        let archive = Path::new("test_data/feed.zip");
+        let hotels = extract(archive, "hotels.json")?;
+        assert_eq!(
+            read_to_string(hotels.path())?,
+            read_to_string("test_data/hotels.json")?
+        );
+        assert_eq!(
+            extract(archive, "coordinates.csv")
+                .expect_err("This should fail")
+                .to_string(),
+            "There's no coordinates.csv in the bundle!"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use std::fs::{read_to_string, File};
+use std::io::copy;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use sha2::{Digest, Sha256};
+
+use crate::remote::is_remote;
+
+/// Calculate SHA-256 checksum of the file at given path and return it as a lowercase hex string.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path).with_context(|| {
+        format!(
+            "Couldn't open {} to calculate its checksum!",
+            path.display()
+        )
+    })?;
+    let mut hasher = Sha256::new();
+    copy(&mut file, &mut hasher).with_context(|| {
+        format!(
+            "Couldn't read {} to calculate its checksum!",
+            path.display()
+        )
+    })?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// Sidecar file is expected to be placed right next to the checked file, e.g input.csv.sha256.
+/// Its content might be either a bare checksum or a line produced by the `sha256sum` tool.
+fn sidecar_checksum(location: &str) -> Result<Option<String>> {
+    if is_remote(location) {
+        return Ok(None);
+    }
+    let sidecar = PathBuf::from(format!("{}.sha256", location));
+    if !sidecar.exists() {
+        return Ok(None);
+    }
+    let content = read_to_string(&sidecar)
+        .with_context(|| format!("Couldn't read checksum file {}!", sidecar.display()))?;
+    Ok(content.split_whitespace().next().map(String::from))
+}
+
+/// Verify that the file has the expected checksum. The checksum given explicitly takes precedence
+/// over the one found in the sidecar file. If there's neither of them, the file is not verified.
+/// `location` is the place where the file came from, as given in the settings, and `file` is where it's available now.
+pub fn verify(location: &str, file: &Path, expected: Option<&str>) -> Result<()> {
+    let expected = match expected {
+        Some(expected) => expected.to_string(),
+        None => match sidecar_checksum(location)? {
+            Some(expected) => expected,
+            None => return Ok(()),
+        },
+    };
+    let actual = sha256_file(file)?;
+    if actual.eq_ignore_ascii_case(expected.trim()) {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Checksum of {} doesn't match! Expected {}, but got {}",
+            location,
+            expected.trim(),
+            actual
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    // echo -n "some data" | sha256sum
+    const SOME_DATA_SHA256: &str =
+        "1307990e6ba5ca145eb35e99182a9bec46531bc54ddf656a602c780fa0240dee";
+
+    #[test]
+    fn calculate_checksum() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("data.csv");
+        write(&path, "some data")?;
+        assert_eq!(sha256_file(&path)?, SOME_DATA_SHA256);
+        Ok(())
+    }
+
+    #[test]
+    fn verify_explicit_checksum() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("data.csv");
+        write(&path, "some data")?;
+        let location = path.to_str().unwrap();
+
+        verify(location, &path, Some(SOME_DATA_SHA256))?;
+        verify(location, &path, Some(&SOME_DATA_SHA256.to_uppercase()))?;
+        assert_eq!(
+            verify(location, &path, Some("abc"))
+                .expect_err("This should fail")
+                .to_string(),
+            format!(
+                "Checksum of {} doesn't match! Expected abc, but got {}",
+                location, SOME_DATA_SHA256
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn verify_sidecar_checksum() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("data.csv");
+        write(&path, "some data")?;
+        let location = path.to_str().unwrap();
+
+        // no sidecar, nothing to verify
+        verify(location, &path, None)?;
+
+        write(
+            dir.path().join("data.csv.sha256"),
+            format!("{}  data.csv\n", SOME_DATA_SHA256),
+        )?;
+        verify(location, &path, None)?;
+
+        write(dir.path().join("data.csv.sha256"), "abc")?;
+        verify(location, &path, None).expect_err("This should fail");
+        Ok(())
+    }
+}
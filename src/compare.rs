@@ -0,0 +1,269 @@
+//! Comparison of two outputs by their records rather than by their bytes, so the acceptance tests don't break
+//! on the changes that don't change the data: the order of the rows and columns and the formatting of the numbers
+//! (`4`, `4.0` and `4.00` are the same value).
+//!
+//! ```
+//! use std::path::Path;
+//!
+//! let differences = axiv::compare::compare(Path::new("expected.csv"), Path::new("expected.csv")).unwrap();
+//! assert!(differences.is_empty(), "{}", differences);
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use csv::ReaderBuilder;
+
+/// Rows of the two outputs that have no counterpart in the other one, and the columns only one of them has.
+/// Rows are compared only by the columns both of the outputs have.
+#[derive(Debug, Default, PartialEq)]
+pub struct Differences {
+    pub missing_columns: Vec<String>,
+    pub unexpected_columns: Vec<String>,
+    /// Rows of the expected output that are not in the actual one
+    pub missing_rows: Vec<Row>,
+    /// Rows of the actual output that are not in the expected one
+    pub unexpected_rows: Vec<Row>,
+}
+
+/// Row of one of the compared outputs, with its values by the names of the columns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    /// Line of the file the row starts at, the header is the first one
+    pub line: u64,
+    pub values: BTreeMap<String, String>,
+}
+
+/// Comparison of the outputs saved with the given delimiter, in which the numbers are equal
+/// when they are the same once rounded to the given number of decimals.
+#[derive(Debug, Clone, Copy)]
+pub struct Comparison {
+    delimiter: u8,
+    decimals: usize,
+}
+
+/// Compare the CSV outputs saved by axiv with the default settings.
+pub fn compare(expected: &Path, actual: &Path) -> Result<Differences> {
+    Comparison::new().run(expected, actual)
+}
+
+impl Default for Comparison {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Comparison {
+    pub fn new() -> Self {
+        Self {
+            delimiter: b';',
+            decimals: 6,
+        }
+    }
+
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn with_decimals(mut self, decimals: usize) -> Self {
+        self.decimals = decimals;
+        self
+    }
+
+    pub fn run(&self, expected: &Path, actual: &Path) -> Result<Differences> {
+        let (expected_columns, expected_rows) = self.read(expected)?;
+        let (actual_columns, actual_rows) = self.read(actual)?;
+        let only_in = |columns: &[String], others: &[String]| -> Vec<String> {
+            columns
+                .iter()
+                .filter(|column| !others.contains(column))
+                .cloned()
+                .collect()
+        };
+        let mut common: Vec<String> = expected_columns
+            .iter()
+            .filter(|column| actual_columns.contains(column))
+            .cloned()
+            .collect();
+        common.sort_unstable();
+
+        let mut unmatched: HashMap<Vec<String>, Vec<Row>> = HashMap::new();
+        for row in expected_rows {
+            unmatched
+                .entry(self.key(&row, &common))
+                .or_default()
+                .push(row);
+        }
+        let mut unexpected_rows = Vec::new();
+        for row in actual_rows {
+            let matched = unmatched
+                .get_mut(&self.key(&row, &common))
+                .and_then(|rows| rows.pop());
+            if matched.is_none() {
+                unexpected_rows.push(row);
+            }
+        }
+        let mut missing_rows: Vec<Row> = unmatched.into_values().flatten().collect();
+        missing_rows.sort_unstable_by_key(|row| row.line);
+        Ok(Differences {
+            missing_columns: only_in(&expected_columns, &actual_columns),
+            unexpected_columns: only_in(&actual_columns, &expected_columns),
+            missing_rows,
+            unexpected_rows,
+        })
+    }
+
+    fn read(&self, path: &Path) -> Result<(Vec<String>, Vec<Row>)> {
+        let mut reader = ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .from_path(path)
+            .with_context(|| format!("Couldn't open the output {}!", path.display()))?;
+        let columns: Vec<String> = reader
+            .headers()
+            .with_context(|| format!("Couldn't read the header of {}!", path.display()))?
+            .iter()
+            .map(String::from)
+            .collect();
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            let record =
+                record.with_context(|| format!("Couldn't read the row of {}!", path.display()))?;
+            rows.push(Row {
+                line: record.position().map_or(0, |position| position.line()),
+                values: columns
+                    .iter()
+                    .cloned()
+                    .zip(record.iter().map(String::from))
+                    .collect(),
+            });
+        }
+        Ok((columns, rows))
+    }
+
+    /// Values of the row in the given columns, with the numbers in the same format.
+    fn key(&self, row: &Row, columns: &[String]) -> Vec<String> {
+        columns
+            .iter()
+            .map(|column| {
+                let value = row.values.get(column).map_or("", String::as_str);
+                match value.trim().parse::<f64>() {
+                    Ok(number) if number.is_finite() => {
+                        let rounded = format!("{:.*}", self.decimals, number);
+                        // -0.000 is the same as 0.000
+                        match rounded.strip_prefix('-') {
+                            Some(positive) if positive.bytes().all(|b| b == b'0' || b == b'.') => {
+                                positive.to_string()
+                            }
+                            _ => rounded,
+                        }
+                    }
+                    _ => value.to_string(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Differences {
+    /// Whether the outputs have the same columns and rows.
+    pub fn is_empty(&self) -> bool {
+        self.missing_columns.is_empty()
+            && self.unexpected_columns.is_empty()
+            && self.missing_rows.is_empty()
+            && self.unexpected_rows.is_empty()
+    }
+}
+
+impl fmt::Display for Differences {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "The outputs are the same");
+        }
+        if !self.missing_columns.is_empty() {
+            writeln!(f, "Missing columns: {}", self.missing_columns.join(", "))?;
+        }
+        if !self.unexpected_columns.is_empty() {
+            writeln!(
+                f,
+                "Unexpected columns: {}",
+                self.unexpected_columns.join(", ")
+            )?;
+        }
+        for (sign, rows) in [("-", &self.missing_rows), ("+", &self.unexpected_rows)] {
+            for row in rows {
+                let values: Vec<String> = row
+                    .values
+                    .iter()
+                    .map(|(column, value)| format!("{}={}", column, value))
+                    .collect();
+                writeln!(f, "{} line {}: {}", sign, row.line, values.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+
+    use super::*;
+
+    #[test]
+    fn compare_records() -> Result<()> {
+        let expected = std::env::temp_dir().join("axiv-compare-expected.csv");
+        let actual = std::env::temp_dir().join("axiv-compare-actual.csv");
+        write(
+            &expected,
+            "room_code;price;meal\nBER898;85.50;F\nBER898;78.00;F\nBER900;-0.00;N\n",
+        )?;
+        // other order of the rows and columns, other formatting of the numbers
+        write(
+            &actual,
+            "price;meal;room_code\n0;N;BER900\n78;F;BER898\n85.5;F;BER898\n",
+        )?;
+        assert_eq!(compare(&expected, &actual)?, Differences::default());
+
+        write(
+            &actual,
+            "price;room_code;city_code\n85.5;BER898;BER\n85.5;BER898;BER\n0;BER900;BER\n",
+        )?;
+        let differences = compare(&expected, &actual)?;
+        assert_eq!(differences.missing_columns, vec![String::from("meal")]);
+        assert_eq!(
+            differences.unexpected_columns,
+            vec![String::from("city_code")]
+        );
+        assert_eq!(
+            differences
+                .missing_rows
+                .iter()
+                .map(|row| row.line)
+                .collect::<Vec<_>>(),
+            vec![3]
+        );
+        assert_eq!(differences.unexpected_rows.len(), 1);
+        assert_eq!(
+            differences.to_string(),
+            "Missing columns: meal\n\
+             Unexpected columns: city_code\n\
+             - line 3: meal=F, price=78.00, room_code=BER898\n\
+             + line 3: city_code=BER, price=85.5, room_code=BER898\n"
+        );
+
+        // the numbers are compared with the given precision
+        write(
+            &actual,
+            "room_code;price;meal\nBER898;85.501;F\nBER898;78;F\nBER900;0;N\n",
+        )?;
+        assert!(!compare(&expected, &actual)?.is_empty());
+        assert!(Comparison::new()
+            .with_decimals(2)
+            .run(&expected, &actual)?
+            .is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use std::io::Write;
+
+use clap::IntoApp;
+use clap_generate::generate;
+use clap_generate::generators::{Bash, Fish, PowerShell, Zsh};
+
+use crate::settings::Settings;
+
+/// Shells for which the completion scripts can be generated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+}
+
+impl Shell {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            "powershell" => Ok(Shell::PowerShell),
+            _ => Err(format!(
+                "expected one of: bash, zsh, fish, powershell, got '{}'",
+                name
+            )),
+        }
+    }
+}
+
+/// Write the script that completes all of the options and commands of axiv in the given shell.
+pub fn completions(shell: Shell, buf: &mut dyn Write) {
+    let mut app = Settings::into_app();
+    match shell {
+        Shell::Bash => generate::<Bash, _>(&mut app, "axiv", buf),
+        Shell::Zsh => generate::<Zsh, _>(&mut app, "axiv", buf),
+        Shell::Fish => generate::<Fish, _>(&mut app, "axiv", buf),
+        Shell::PowerShell => generate::<PowerShell, _>(&mut app, "axiv", buf),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complete_options_and_commands() {
+        for shell in ["bash", "zsh", "fish", "powershell"] {
+            let mut script = Vec::new();
+            completions(Shell::from_name(shell).unwrap(), &mut script);
+            let script = String::from_utf8(script).unwrap();
+            assert!(script.contains("number-locale"), "{}", shell);
+            assert!(script.contains("stats"), "{}", shell);
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+
+#[cfg(feature = "compression")]
+use anyhow::Context;
+use anyhow::{anyhow, Result};
+use csv::Writer;
+#[cfg(feature = "compression")]
+use flate2::write::GzEncoder;
+
+/// Compression of the files of the output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    None,
+    #[cfg(feature = "compression")]
+    Gzip,
+    #[cfg(feature = "compression")]
+    Zstd,
+}
+
+impl Compression {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "none" => Ok(Compression::None),
+            #[cfg(feature = "compression")]
+            "gzip" => Ok(Compression::Gzip),
+            #[cfg(feature = "compression")]
+            "zstd" => Ok(Compression::Zstd),
+            #[cfg(not(feature = "compression"))]
+            "gzip" | "zstd" => Err(String::from(
+                "axiv was built without the compression feature",
+            )),
+            _ => Err(format!("expected one of: none, gzip, zstd, got '{}'", name)),
+        }
+    }
+
+    /// Level of the compression, the usual trade-off between the size and the speed is used when none is given.
+    /// It throws an error if the level is out of the range of the compression.
+    pub fn level(self, level: Option<i32>) -> Result<i32> {
+        let (default, levels) = match self.levels() {
+            Some(levels) => levels,
+            None => return Ok(0),
+        };
+        let level = level.unwrap_or(default);
+        if !levels.contains(&level) {
+            return Err(anyhow!(
+                "Level of the {} compression has to be between {} and {}, got {}!",
+                self.name(),
+                levels.start(),
+                levels.end(),
+                level
+            ));
+        }
+        Ok(level)
+    }
+
+    /// Default level of the compression with the range of its levels, there's none without the compression.
+    fn levels(self) -> Option<(i32, RangeInclusive<i32>)> {
+        match self {
+            Compression::None => None,
+            #[cfg(feature = "compression")]
+            Compression::Gzip => Some((6, 0..=9)),
+            #[cfg(feature = "compression")]
+            Compression::Zstd => Some((3, zstd::compression_level_range())),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            #[cfg(feature = "compression")]
+            Compression::Gzip => "gzip",
+            #[cfg(feature = "compression")]
+            Compression::Zstd => "zstd",
+        }
+    }
+}
+
+/// File of the output that's compressed as it's written.
+/// It has to be finished once everything is written, so the end of the compressed stream is saved.
+pub enum OutputFile {
+    Plain(File),
+    #[cfg(feature = "compression")]
+    Gzip(GzEncoder<File>),
+    #[cfg(feature = "compression")]
+    Zstd(zstd::Encoder<'static, File>),
+}
+
+impl OutputFile {
+    pub fn new(file: File, compression: Compression, level: Option<i32>) -> Result<Self> {
+        match (compression, compression.level(level)?) {
+            (Compression::None, _) => Ok(OutputFile::Plain(file)),
+            #[cfg(feature = "compression")]
+            (Compression::Gzip, level) => Ok(OutputFile::Gzip(GzEncoder::new(
+                file,
+                flate2::Compression::new(level as u32),
+            ))),
+            #[cfg(feature = "compression")]
+            (Compression::Zstd, level) => zstd::Encoder::new(file, level)
+                .map(OutputFile::Zstd)
+                .with_context(|| "Couldn't start the zstd compression of the output!"),
+        }
+    }
+
+    /// Save the end of the compressed stream.
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            OutputFile::Plain(mut file) => file.flush(),
+            #[cfg(feature = "compression")]
+            OutputFile::Gzip(encoder) => encoder.finish().map(drop),
+            #[cfg(feature = "compression")]
+            OutputFile::Zstd(encoder) => encoder.finish().map(drop),
+        }
+    }
+}
+
+/// Save whatever the CSV writer still buffers and the end of the compressed stream.
+pub fn finish_writer(writer: Writer<OutputFile>) -> Result<()> {
+    writer
+        .into_inner()
+        .map_err(|e| anyhow!("{}", e.error()))?
+        .finish()
+        .map_err(Into::into)
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            OutputFile::Plain(file) => file.write(buf),
+            #[cfg(feature = "compression")]
+            OutputFile::Gzip(encoder) => encoder.write(buf),
+            #[cfg(feature = "compression")]
+            OutputFile::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            OutputFile::Plain(file) => file.flush(),
+            #[cfg(feature = "compression")]
+            OutputFile::Gzip(encoder) => encoder.flush(),
+            #[cfg(feature = "compression")]
+            OutputFile::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "compression"))]
+mod tests {
+    use std::fs::{read, read_to_string};
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn write(path: &std::path::Path, compression: Compression, level: Option<i32>) -> Result<()> {
+        let mut file = OutputFile::new(File::create(path)?, compression, level)?;
+        file.write_all(b"room_code;price\nBER898;85.50\n")?;
+        Ok(file.finish()?)
+    }
+
+    #[test]
+    fn compress_output() -> Result<()> {
+        let dir = tempdir()?;
+
+        let plain = dir.path().join("output.csv");
+        write(&plain, Compression::None, None)?;
+        assert_eq!(read_to_string(&plain)?, "room_code;price\nBER898;85.50\n");
+
+        let gzip = dir.path().join("output.csv.gz");
+        write(&gzip, Compression::Gzip, Some(9))?;
+        let mut decompressed = String::new();
+        GzDecoder::new(File::open(&gzip)?).read_to_string(&mut decompressed)?;
+        assert_eq!(decompressed, "room_code;price\nBER898;85.50\n");
+
+        let zstd = dir.path().join("output.csv.zst");
+        write(&zstd, Compression::Zstd, None)?;
+        assert_eq!(
+            zstd::decode_all(read(&zstd)?.as_slice())?,
+            b"room_code;price\nBER898;85.50\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn check_level() -> Result<()> {
+        assert_eq!(Compression::Zstd.level(None)?, 3);
+        assert_eq!(Compression::Gzip.level(Some(9))?, 9);
+        assert_eq!(
+            Compression::Gzip
+                .level(Some(10))
+                .expect_err("This should fail")
+                .to_string(),
+            "Level of the gzip compression has to be between 0 and 9, got 10!"
+        );
+        Ok(())
+    }
+}
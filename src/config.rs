@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+
+use crate::data::{KeyCase, Limits, Mask};
+
+/// Configuration file shared by all of the feeds, e.g
+/// ```toml
+/// [profiles.DOTW]
+/// delimiter = ","
+/// date_format = "%d.%m.%Y"
+/// key_case = "upper"
+///
+/// [masks.partners]
+/// hash = ["hotel_name"]
+/// price_bucket = 50
+///
+/// [limits.legacy]
+/// room_name = { max_length = 100 }
+/// ```
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+    /// Anonymizations of the output for the extracts shared outside, by their names
+    #[serde(default)]
+    pub masks: HashMap<String, Mask>,
+    /// Limits of the saved values for the downstream databases, by their names
+    #[serde(default)]
+    pub limits: HashMap<String, Limits>,
+}
+
+/// The way in which the input of one of the suppliers is read.
+/// Everything that's not given in the profile falls back to the defaults.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// Character that separates fields of the input, `|` by default
+    pub delimiter: Option<char>,
+    /// Format of the checkin and the checkout, %Y%m%d by default
+    pub date_format: Option<String>,
+    /// How the codes of the input are matched with the rooms and hotels
+    pub key_case: KeyCase,
+}
+
+impl Config {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let content =
+            read_to_string(path).with_context(|| "Path to the config file is invalid!")?;
+        toml::from_str(&content)
+            .with_context(|| format!("Couldn't parse the config file {}!", path.display()))
+    }
+
+    pub fn profile(&self, name: &str) -> Result<&Profile> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| anyhow!("There's no profile {} in the config file!", name))
+    }
+
+    pub fn mask(&self, name: &str) -> Result<&Mask> {
+        let mask = self
+            .masks
+            .get(name)
+            .ok_or_else(|| anyhow!("There's no mask {} in the config file!", name))?;
+        mask.validate()
+            .with_context(|| format!("Mask {} in the config file is invalid!", name))?;
+        Ok(mask)
+    }
+
+    pub fn limits(&self, name: &str) -> Result<&Limits> {
+        let limits = self
+            .limits
+            .get(name)
+            .ok_or_else(|| anyhow!("There's no limits {} in the config file!", name))?;
+        limits
+            .validate()
+            .with_context(|| format!("Limits {} in the config file are invalid!", name))?;
+        Ok(limits)
+    }
+}
+
+impl Profile {
+    /// Delimiter of the input as expected by the csv crate.
+    pub fn delimiter(&self) -> Result<u8> {
+        match self.delimiter {
+            None => Ok(b'|'),
+            Some(delimiter) if delimiter.is_ascii() => Ok(delimiter as u8),
+            Some(delimiter) => Err(anyhow!(
+                "Delimiter of the input has to be an ASCII character, got '{}'!",
+                delimiter
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_profiles_masks_and_limits() -> Result<()> {
+        let config: Config = toml::from_str(
+            r#"
+            [profiles.MARR]
+
+            [profiles.DOTW]
+            delimiter = ","
+            date_format = "%d.%m.%Y"
+            key_case = "upper"
+
+            [masks.partners]
+            hash = ["hotel_name"]
+            price_bucket = 50
+            drop = ["room_name"]
+
+            [masks.broken]
+            price_bucket = -1
+
+            [limits.legacy]
+            room_name = { max_length = 100, transliterate = true }
+
+            [limits.broken]
+            room_name = { max_length = 0 }
+            "#,
+        )?;
+        assert_eq!(config.profile("MARR")?, &Profile::default());
+        assert_eq!(config.profile("MARR")?.delimiter()?, b'|');
+
+        let dotw = config.profile("DOTW")?;
+        assert_eq!(dotw.delimiter()?, b',');
+        assert_eq!(dotw.date_format.as_deref(), Some("%d.%m.%Y"));
+        assert_eq!(dotw.key_case, KeyCase::Upper);
+
+        assert_eq!(
+            config.profile("GTA").unwrap_err().to_string(),
+            "There's no profile GTA in the config file!"
+        );
+
+        let partners = config.mask("partners")?;
+        assert_eq!(partners.hash, vec![String::from("hotel_name")]);
+        assert_eq!(partners.price_bucket, Some(50.0));
+        assert_eq!(partners.drop, vec![String::from("room_name")]);
+        assert_eq!(
+            config.mask("broken").unwrap_err().to_string(),
+            "Mask broken in the config file is invalid!"
+        );
+
+        assert!(config.limits("legacy").is_ok());
+        assert_eq!(
+            config.limits("broken").unwrap_err().to_string(),
+            "Limits broken in the config file are invalid!"
+        );
+        Ok(())
+    }
+}
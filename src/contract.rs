@@ -0,0 +1,307 @@
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDate;
+use csv::{ReaderBuilder, StringRecord, WriterBuilder};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::data::{custom_date, NumberFormat, Output, OutputFormat, OutputRecord};
+
+/// What happens with the record that violates the contract.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OnViolation {
+    /// The whole run fails
+    #[default]
+    Fail,
+    /// The record is still saved, the violations are counted in the summary
+    Warn,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnType {
+    String,
+    Integer,
+    Number,
+    /// Date in the format of the output, e.g 2018-07-21
+    Date,
+}
+
+/// Expectations of the values of a single column of the output, e.g
+/// `price: {type: number, min: 0, max: 10000}` or `hotel_code: {pattern: "^[A-Z]{3}[0-9]{5}$"}`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnContract {
+    #[serde(rename = "type")]
+    pub column_type: Option<ColumnType>,
+    /// Whether the value can be empty
+    #[serde(default)]
+    pub nullable: bool,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// Regular expression the whole value has to match
+    pub pattern: Option<String>,
+}
+
+/// Contract of the output declared in a YAML file, every record of the output is validated against it
+/// exactly as it's serialized, so the loaders further down the pipeline don't get any surprises.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Contract {
+    #[serde(default)]
+    pub on_violation: OnViolation,
+    pub columns: BTreeMap<String, ColumnContract>,
+}
+
+impl Contract {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let content = read_to_string(path)
+            .with_context(|| format!("Path to the contract {} is invalid!", path.display()))?;
+        serde_yaml::from_str(&content)
+            .with_context(|| format!("Contract at {} is invalid!", path.display()))
+    }
+}
+
+struct Column {
+    name: String,
+    index: usize,
+    contract: ColumnContract,
+    pattern: Option<Regex>,
+}
+
+/// Validator of the records of the output in the given format against the contract.
+pub struct ContractValidator {
+    on_violation: OnViolation,
+    columns: Vec<Column>,
+    format: OutputFormat,
+    /// How many violations there were in each of the columns
+    violations: BTreeMap<String, usize>,
+}
+
+impl ContractValidator {
+    /// It throws an error when the contract expects a column that's not in the output at all.
+    pub fn new(contract: Contract, format: OutputFormat) -> Result<Self> {
+        let output_columns = format.columns();
+        let columns = contract
+            .columns
+            .into_iter()
+            .map(|(name, contract)| {
+                let index = output_columns
+                    .iter()
+                    .position(|column| *column == name)
+                    .ok_or_else(|| {
+                        anyhow!("Column {} of the contract is not in the output!", name)
+                    })?;
+                let pattern = match &contract.pattern {
+                    Some(pattern) => {
+                        Some(Regex::new(&format!("^(?:{})$", pattern)).with_context(|| {
+                            format!("Pattern of the column {} of the contract is invalid!", name)
+                        })?)
+                    }
+                    None => None,
+                };
+                Ok(Column {
+                    name,
+                    index,
+                    contract,
+                    pattern,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            on_violation: contract.on_violation,
+            columns,
+            format,
+            violations: BTreeMap::new(),
+        })
+    }
+
+    /// Validate the record, it throws an error which lists all of its violations when they are not just counted.
+    pub fn check(&mut self, output: &Output) -> Result<()> {
+        let record = serialize(output, &self.format)?;
+        let mut violations = Vec::new();
+        for column in &self.columns {
+            let value = record.get(column.index).unwrap_or("");
+            if let Some(violation) = violation(column, value, &self.format.number) {
+                *self.violations.entry(column.name.clone()).or_insert(0) += 1;
+                violations.push(format!("column {}: {}", column.name, violation));
+            }
+        }
+        if violations.is_empty() || self.on_violation == OnViolation::Warn {
+            return Ok(());
+        }
+        Err(anyhow!(
+            "Record of the room {} of the hotel {} from {} violates the output contract: {}!",
+            output.room_code,
+            output.hotel_code,
+            output.source,
+            violations.join("; ")
+        ))
+    }
+
+    /// How many violations there were in each of the columns.
+    pub fn finish(self) -> BTreeMap<String, usize> {
+        self.violations
+    }
+}
+
+/// Values of the record exactly as they are saved in the output.
+fn serialize(output: &Output, format: &OutputFormat) -> Result<StringRecord> {
+    let mut writer = WriterBuilder::new().has_headers(false).from_writer(vec![]);
+    writer
+        .serialize(OutputRecord::new(output, format))
+        .with_context(|| format!("Couldn't serialize {:#?}", output))?;
+    let serialized = writer
+        .into_inner()
+        .map_err(|e| anyhow!("Couldn't serialize {:#?}: {}", output, e))?;
+    ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(serialized.as_slice())
+        .records()
+        .next()
+        .unwrap_or_else(|| Ok(StringRecord::new()))
+        .with_context(|| format!("Couldn't serialize {:#?}", output))
+}
+
+/// Numbers formatted in the locale of the output, e.g 1.085,50, are understood as well.
+fn parse_number(value: &str, number_format: &NumberFormat) -> Option<f64> {
+    value.parse().ok().or_else(|| {
+        let mut plain: String = value
+            .chars()
+            .filter(|c| Some(*c) != number_format.thousands_separator)
+            .collect();
+        plain = plain.replace(number_format.decimal_separator, ".");
+        plain.parse().ok()
+    })
+}
+
+/// What's wrong with the value of the column, if there's anything.
+fn violation(column: &Column, value: &str, number_format: &NumberFormat) -> Option<String> {
+    let contract = &column.contract;
+    if value.is_empty() {
+        return if contract.nullable {
+            None
+        } else {
+            Some(String::from("value is missing"))
+        };
+    }
+    let number = match contract.column_type {
+        Some(ColumnType::Integer) => match value.parse::<i64>() {
+            Ok(number) => Some(number as f64),
+            Err(_) => return Some(format!("expected an integer, got '{}'", value)),
+        },
+        Some(ColumnType::Number) => match parse_number(value, number_format) {
+            Some(number) => Some(number),
+            None => return Some(format!("expected a number, got '{}'", value)),
+        },
+        Some(ColumnType::Date) => {
+            match NaiveDate::parse_from_str(value, custom_date::OUTPUT_FORMAT) {
+                Ok(_) => None,
+                Err(_) => return Some(format!("expected a date, got '{}'", value)),
+            }
+        }
+        Some(ColumnType::String) | None => parse_number(value, number_format),
+    };
+    if let (Some(min), Some(number)) = (contract.min, number) {
+        if number < min {
+            return Some(format!("{} is below the minimum {}", value, min));
+        }
+    }
+    if let (Some(max), Some(number)) = (contract.max, number) {
+        if number > max {
+            return Some(format!("{} is above the maximum {}", value, max));
+        }
+    }
+    match &column.pattern {
+        Some(pattern) if !pattern.is_match(value) => Some(format!(
+            "'{}' doesn't match the pattern {}",
+            value,
+            contract.pattern.as_deref().unwrap_or_default()
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::CategoryFormat;
+    use crate::testing;
+
+    fn format(locale: &str, coordinates: bool) -> OutputFormat {
+        OutputFormat {
+            number: NumberFormat::from_locale(locale).unwrap(),
+            category: CategoryFormat::Raw,
+            coordinates,
+            chains: false,
+            ranking: false,
+            run_date: None,
+            seasons: None,
+            match_status: false,
+            match_confidence: false,
+            candidate_room_names: None,
+            derived_metrics: false,
+            stamp: None,
+            dropped: Vec::new(),
+        }
+    }
+
+    fn contract(on_violation: &str) -> Contract {
+        serde_yaml::from_str(&format!(
+            "on_violation: {}
+columns:
+  price: {{type: number, min: 0, max: 1000}}
+  checkin: {{type: date}}
+  pax: {{type: integer, min: 1}}
+  latitude: {{type: number, nullable: true}}
+  city_code: {{pattern: '[A-Z]{{3}}'}}",
+            on_violation
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn fail_on_violations() -> Result<()> {
+        let mut validator = ContractValidator::new(contract("fail"), format("de", true))?;
+        validator.check(&testing::output().build())?;
+        let mut invalid = testing::output().price(1085.5).build();
+        invalid.city_code = String::from("Berlin");
+        assert_eq!(
+            validator
+                .check(&invalid)
+                .expect_err("This should fail")
+                .to_string(),
+            "Record of the room BER898 of the hotel BER00002 from IHG violates the output contract: \
+             column city_code: 'Berlin' doesn't match the pattern [A-Z]{3}; \
+             column price: 1.085,50 is above the maximum 1000!"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn count_violations() -> Result<()> {
+        let mut validator = ContractValidator::new(contract("warn"), format("plain", true))?;
+        validator.check(&testing::output().price(-1.0).build())?;
+        validator.check(&testing::output().price(2000.0).build())?;
+        validator.check(&testing::output().build())?;
+        let violations = validator.finish();
+        assert_eq!(violations.get("price"), Some(&2));
+        assert_eq!(violations.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn require_columns_of_output() {
+        assert_eq!(
+            ContractValidator::new(contract("fail"), format("plain", false))
+                .err()
+                .expect("This should fail")
+                .to_string(),
+            "Column latitude of the contract is not in the output!"
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use std::fs::remove_file;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::Shutdown;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::batch::Job;
+use crate::shutdown;
+
+/// How often the daemon checks whether it was asked to stop while it waits for the jobs.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Reply of the daemon to the submitted job, it's sent back as a single line of JSON once the job is done.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Reply {
+    Done { summary: String, terminated: bool },
+    Failed { error: String },
+}
+
+/// Listen on the Unix socket at the path until the daemon is asked to stop, each of the submitted jobs is run
+/// on a thread of its own. The jobs that are running when it's asked to stop end early, as the runs do.
+/// `listening` is called once the jobs can be submitted. Returns how many jobs were submitted.
+pub fn serve<F>(path: &Path, listening: impl FnOnce(), run: F) -> Result<usize>
+where
+    F: Fn(Job) -> Reply + Sync,
+{
+    let listener = bind(path)?;
+    listener
+        .set_nonblocking(true)
+        .with_context(|| "Couldn't set up the socket of the daemon!")?;
+    listening();
+    let submitted = AtomicUsize::new(0);
+    let (run, submitted_ref) = (&run, &submitted);
+    let served = thread::scope(|scope| loop {
+        if shutdown::is_requested() {
+            return Ok(());
+        }
+        match listener.accept() {
+            Ok((stream, _)) => {
+                submitted_ref.fetch_add(1, Ordering::Relaxed);
+                scope.spawn(move || {
+                    if let Err(e) = handle(stream, run) {
+                        eprintln!("Couldn't handle the job: {:#}", e);
+                    }
+                });
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+            Err(e) => return Err(e).with_context(|| "Couldn't accept the job!"),
+        }
+    });
+    let _ = remove_file(path);
+    served.map(|_| submitted.into_inner())
+}
+
+/// Socket at the path, the one left behind by a daemon that's no longer running is replaced.
+fn bind(path: &Path) -> Result<UnixListener> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(anyhow!(
+                "Another daemon is already listening on {}!",
+                path.display()
+            ));
+        }
+        remove_file(path)
+            .with_context(|| format!("Couldn't remove the stale socket {}!", path.display()))?;
+    }
+    UnixListener::bind(path)
+        .with_context(|| format!("Couldn't listen on the socket {}!", path.display()))
+}
+
+/// Read the job sent through the stream, run it and send back the reply.
+fn handle(stream: UnixStream, run: &impl Fn(Job) -> Reply) -> Result<()> {
+    stream.set_nonblocking(false)?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let reply = match serde_json::from_str::<Job>(&line) {
+        Ok(job) => run(job),
+        Err(e) => Reply::Failed {
+            error: format!("Couldn't parse the submitted job: {}", e),
+        },
+    };
+    let mut stream = stream;
+    writeln!(stream, "{}", serde_json::to_string(&reply)?)?;
+    Ok(())
+}
+
+/// Send the job to the daemon listening on the socket at the path and wait until it's done.
+pub fn submit(path: &Path, job: &Job) -> Result<Reply> {
+    let stream = UnixStream::connect(path).with_context(|| {
+        format!(
+            "Couldn't connect to the daemon at {}, is it running?",
+            path.display()
+        )
+    })?;
+    exchange(stream, job)
+}
+
+fn exchange(mut stream: UnixStream, job: &Job) -> Result<Reply> {
+    writeln!(stream, "{}", serde_json::to_string(job)?)
+        .with_context(|| "Couldn't send the job to the daemon!")?;
+    stream.shutdown(Shutdown::Write)?;
+    let mut line = String::new();
+    BufReader::new(&stream)
+        .read_line(&mut line)
+        .with_context(|| "Couldn't read the reply of the daemon!")?;
+    serde_json::from_str(&line).with_context(|| "The daemon stopped before the job was done!")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job() -> Job {
+        Job {
+            name: String::from("ihg"),
+            input: String::from("ihg.csv"),
+            output: String::from("ihg_enriched.csv"),
+            profile: Some(String::from("IHG")),
+            rooms: None,
+            hotels: None,
+            rejects: None,
+        }
+    }
+
+    #[test]
+    fn exchange_job_and_reply() -> Result<()> {
+        let (client, server) = UnixStream::pair()?;
+        let daemon = thread::spawn(move || {
+            handle(server, &|job: Job| Reply::Done {
+                summary: format!("{} saved at {}", job.input, job.output),
+                terminated: false,
+            })
+        });
+        assert_eq!(
+            exchange(client, &job())?,
+            Reply::Done {
+                summary: String::from("ihg.csv saved at ihg_enriched.csv"),
+                terminated: false
+            }
+        );
+        daemon.join().unwrap()?;
+
+        // the job that can't be parsed fails without being run
+        let (mut client, server) = UnixStream::pair()?;
+        let daemon = thread::spawn(move || handle(server, &|_| unreachable!()));
+        writeln!(client, "{{\"name\": \"ihg\"}}")?;
+        let mut reply = String::new();
+        BufReader::new(&client).read_line(&mut reply)?;
+        daemon.join().unwrap()?;
+        assert!(matches!(
+            serde_json::from_str(&reply)?,
+            Reply::Failed { error } if error.starts_with("Couldn't parse the submitted job")
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn replace_stale_socket() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("axiv-daemon-{}.sock", std::process::id()));
+        let _ = remove_file(&path);
+        let listener = bind(&path)?;
+        assert!(bind(&path)
+            .unwrap_err()
+            .to_string()
+            .starts_with("Another daemon is already listening"));
+
+        // the socket is left behind when the daemon is gone
+        drop(listener);
+        assert!(path.exists());
+        drop(bind(&path)?);
+        remove_file(&path)?;
+        Ok(())
+    }
+}
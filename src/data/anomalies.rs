@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::data::entities::Price;
+use crate::data::{group_sorted, sort_spilling, Output};
+use crate::Outputs;
+
+/// Thresholds above which the price per person is suspicious, compared with the median of the prices
+/// of the same room of the same hotel in the current run.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct AnomalyRules {
+    /// Maximal number of the standard deviations from the median
+    pub max_deviations: Option<f64>,
+    /// Maximal ratio of the price to the median (or of the median to the price), e.g 3.0
+    pub max_factor: Option<f64>,
+}
+
+impl AnomalyRules {
+    pub fn is_set(&self) -> bool {
+        self.max_deviations.is_some() || self.max_factor.is_some()
+    }
+}
+
+/// Record whose price is suspicious, it's saved in the review file instead of the output, e.g
+/// {"output":{...},"median":85.5,"deviations":4.2,"factor":3.1}
+#[derive(Serialize, Debug)]
+pub struct Anomaly {
+    pub output: Output,
+    /// Median of the prices per person of the same room
+    pub median: Price,
+    /// How many standard deviations the price is away from the median, there's none when all of the prices are the same
+    pub deviations: Option<f64>,
+    /// Ratio of the price to the median, or of the median to the price, whichever is bigger
+    pub factor: f64,
+}
+
+fn median(prices: &mut [Price]) -> Price {
+    prices.sort_by(|a, b| a.total_cmp(b));
+    let middle = prices.len() / 2;
+    if prices.len().is_multiple_of(2) {
+        (prices[middle - 1] + prices[middle]) / 2.0
+    } else {
+        prices[middle]
+    }
+}
+
+fn standard_deviation(prices: &[Price]) -> f64 {
+    let mean = prices.iter().sum::<Price>() / prices.len() as Price;
+    let variance = prices
+        .iter()
+        .map(|price| (price - mean).powi(2))
+        .sum::<Price>()
+        / prices.len() as Price;
+    variance.sqrt()
+}
+
+/// Split the records into the regular ones and the ones with suspicious prices, order of the records is kept.
+pub fn find_anomalies(outputs: Vec<Output>, rules: &AnomalyRules) -> (Vec<Output>, Vec<Anomaly>) {
+    let mut prices: HashMap<(String, String), Vec<Price>> = HashMap::new();
+    for output in &outputs {
+        prices
+            .entry(room_key(output))
+            .or_default()
+            .push(output.price);
+    }
+    let statistics: HashMap<(String, String), (Price, f64)> = prices
+        .into_iter()
+        .filter_map(|(key, mut prices)| statistics(&mut prices).map(|statistics| (key, statistics)))
+        .collect();
+
+    let mut regular = Vec::new();
+    let mut anomalies = Vec::new();
+    for output in outputs {
+        let statistics = statistics.get(&room_key(&output)).copied();
+        match review(output, statistics, rules) {
+            Reviewed::Regular(output) => regular.push(output),
+            Reviewed::Anomaly(anomaly) => anomalies.push(anomaly),
+        }
+    }
+    (regular, anomalies)
+}
+
+/// Find the anomalies just like `find_anomalies`, with at most about `budget` bytes of the records in memory.
+/// The records are sorted by their rooms and then back into their order, in runs on the disk when they don't fit
+/// into the budget. Only the anomalies are kept in memory.
+pub fn find_anomalies_spilling<'a>(
+    outputs: impl Iterator<Item = Output>,
+    rules: &AnomalyRules,
+    budget: usize,
+) -> Result<(Outputs<'a>, Vec<Anomaly>)> {
+    let by_room = sort_spilling(
+        outputs.enumerate().map(Ok),
+        |(index, output)| (room_key(output), *index),
+        budget,
+    )?;
+    let mut anomalies = Vec::new();
+    let reviewed = group_sorted(by_room, |(_, output)| room_key(output)).flat_map(
+        |room_res| -> Vec<Result<(usize, Output)>> {
+            let room = match room_res {
+                Ok(room) => room,
+                Err(e) => return vec![Err(e)],
+            };
+            let mut prices: Vec<Price> = room.iter().map(|(_, output)| output.price).collect();
+            let statistics = statistics(&mut prices);
+            let mut regular = Vec::new();
+            for (index, output) in room {
+                match review(output, statistics, rules) {
+                    Reviewed::Regular(output) => regular.push(Ok((index, output))),
+                    Reviewed::Anomaly(anomaly) => anomalies.push((index, anomaly)),
+                }
+            }
+            regular
+        },
+    );
+    let regular = sort_spilling(reviewed, |(index, _)| *index, budget)?;
+    anomalies.sort_unstable_by_key(|(index, _)| *index);
+    Ok((
+        Box::new(regular.map(|record| record.map(|(_, output)| output))),
+        anomalies.into_iter().map(|(_, anomaly)| anomaly).collect(),
+    ))
+}
+
+fn room_key(output: &Output) -> (String, String) {
+    (output.hotel_code.clone(), output.room_code.clone())
+}
+
+/// Median and standard deviation of the prices of the room, there are none when there's only one price.
+fn statistics(prices: &mut [Price]) -> Option<(Price, f64)> {
+    if prices.len() < 2 {
+        return None;
+    }
+    let deviation = standard_deviation(prices);
+    Some((median(prices), deviation))
+}
+
+enum Reviewed {
+    Regular(Output),
+    Anomaly(Anomaly),
+}
+
+/// Compare the price of the record with the statistics of its room.
+fn review(output: Output, statistics: Option<(Price, f64)>, rules: &AnomalyRules) -> Reviewed {
+    let (median, standard_deviation) = match statistics {
+        Some(statistics) => statistics,
+        None => return Reviewed::Regular(output),
+    };
+    let deviations = if standard_deviation > 0.0 {
+        Some((output.price - median).abs() / standard_deviation)
+    } else {
+        None
+    };
+    let factor = if output.price > 0.0 && median > 0.0 {
+        (output.price / median).max(median / output.price)
+    } else {
+        1.0
+    };
+    let too_many_deviations = match (deviations, rules.max_deviations) {
+        (Some(deviations), Some(max_deviations)) => deviations > max_deviations,
+        _ => false,
+    };
+    let too_big_factor = rules
+        .max_factor
+        .is_some_and(|max_factor| factor > max_factor);
+    if too_many_deviations || too_big_factor {
+        Reviewed::Anomaly(Anomaly {
+            output,
+            median,
+            deviations,
+            factor,
+        })
+    } else {
+        Reviewed::Regular(output)
+    }
+}
+
+/// Save the records with suspicious prices for the review, one JSON object per line.
+pub fn save_anomalies(anomalies: &[Anomaly], mut file: impl Write) -> Result<()> {
+    for anomaly in anomalies {
+        serde_json::to_writer(&mut file, anomaly).with_context(|| "Couldn't save the anomaly!")?;
+        writeln!(file).with_context(|| "Couldn't save the anomaly!")?;
+    }
+    file.flush().with_context(|| "Couldn't save the anomalies!")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::ApproximateSize;
+    use crate::testing;
+
+    fn prices(outputs: &[Output]) -> Vec<Price> {
+        outputs.iter().map(|output| output.price).collect()
+    }
+
+    fn outputs() -> Vec<Output> {
+        vec![
+            testing::output().price(80.0).build(),
+            testing::output().price(85.0).build(),
+            testing::output().price(90.0).build(),
+            testing::output().price(85.0).build(),
+            testing::output().price(850.0).build(),
+            // the only offer of the room can't be compared with anything
+            testing::output().room_code("BER899").price(1.0).build(),
+        ]
+    }
+
+    #[test]
+    fn flag_prices_far_from_median() {
+        let rules = AnomalyRules {
+            max_deviations: None,
+            max_factor: Some(3.0),
+        };
+        let (regular, anomalies) = find_anomalies(outputs(), &rules);
+        assert_eq!(prices(&regular), vec![80.0, 85.0, 90.0, 85.0, 1.0]);
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].output.price, 850.0);
+        assert_eq!(anomalies[0].median, 85.0);
+        assert_eq!(anomalies[0].factor, 10.0);
+
+        let rules = AnomalyRules {
+            max_deviations: Some(1.5),
+            max_factor: None,
+        };
+        let (regular, anomalies) = find_anomalies(outputs(), &rules);
+        assert_eq!(regular.len(), 5);
+        assert_eq!(
+            prices(&anomalies.into_iter().map(|a| a.output).collect::<Vec<_>>()),
+            vec![850.0]
+        );
+    }
+
+    #[test]
+    fn keep_same_prices() {
+        let rules = AnomalyRules {
+            max_deviations: Some(0.0),
+            max_factor: None,
+        };
+        let same = vec![
+            testing::output().price(85.0).build(),
+            testing::output().price(85.0).build(),
+        ];
+        let (regular, anomalies) = find_anomalies(same, &rules);
+        assert_eq!(regular.len(), 2);
+        assert!(anomalies.is_empty());
+    }
+
+    #[test]
+    fn find_anomalies_in_runs() -> Result<()> {
+        let rules = AnomalyRules {
+            max_deviations: None,
+            max_factor: Some(3.0),
+        };
+        let mut outputs = outputs();
+        outputs.extend(outputs.clone());
+        let budget = 2 * outputs[0].approximate_size();
+        let (regular, anomalies) =
+            find_anomalies_spilling(outputs.clone().into_iter(), &rules, budget)?;
+        let regular = regular.collect::<Result<Vec<_>>>()?;
+        let (expected_regular, expected_anomalies) = find_anomalies(outputs, &rules);
+        assert_eq!(prices(&regular), prices(&expected_regular));
+        assert_eq!(
+            anomalies.iter().map(|a| a.output.price).collect::<Vec<_>>(),
+            expected_anomalies
+                .iter()
+                .map(|a| a.output.price)
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+use std::io::Write;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::data::{Reject, RejectReason};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditStatus {
+    Enriched,
+    Rejected,
+}
+
+/// What was done with a single record of the input, e.g
+/// {"line":2,"status":"enriched","reason":null,"room_key":"BER00002-BER898-IHG","hotel":"BER00002","transforms":["coordinates"]}
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    /// Line of the input file where the record begins
+    pub line: u64,
+    pub status: AuditStatus,
+    pub reason: Option<RejectReason>,
+    /// Key by which the room was looked up, if the record got that far
+    pub room_key: Option<String>,
+    /// Key by which the hotel was looked up, if the record got that far
+    pub hotel: Option<String>,
+    /// Transformations applied to the record, e.g date_format, key_case, translation, room_name, coordinates
+    pub transforms: Vec<&'static str>,
+}
+
+impl AuditEntry {
+    pub fn new(line: u64) -> Self {
+        Self {
+            line,
+            status: AuditStatus::Enriched,
+            reason: None,
+            room_key: None,
+            hotel: None,
+            transforms: Vec::new(),
+        }
+    }
+
+    /// Final status of the record, it's rejected when there's a reject.
+    pub fn finish(mut self, reject: Option<&Reject>) -> Self {
+        if let Some(reject) = reject {
+            self.status = AuditStatus::Rejected;
+            self.reason = Some(reject.reason);
+        }
+        self
+    }
+}
+
+/// Audit trail of the run, one JSON object per line for each of the records of the input.
+pub struct Audit {
+    output: Box<dyn Write>,
+}
+
+impl Audit {
+    pub fn new(output: Box<dyn Write>) -> Self {
+        Self { output }
+    }
+
+    pub fn write(&mut self, entry: AuditEntry) -> Result<()> {
+        serde_json::to_writer(&mut self.output, &entry)
+            .with_context(|| "Couldn't save the audit trail!")?;
+        writeln!(self.output).with_context(|| "Couldn't save the audit trail!")
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.output
+            .flush()
+            .with_context(|| "Couldn't save the audit trail!")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use csv::StringRecord;
+
+    use super::*;
+
+    #[test]
+    fn rejected_entry() {
+        let mut entry = AuditEntry::new(3);
+        entry.room_key = Some(String::from("BER00009-BER898-IHG"));
+        let reject = Reject::new(
+            RejectReason::MissingRoom,
+            String::from("Input links to a non existent room"),
+            Some(3),
+            &StringRecord::new(),
+            None,
+        );
+        assert_eq!(
+            serde_json::to_string(&entry.finish(Some(&reject))).unwrap(),
+            r#"{"line":3,"status":"rejected","reason":"missing_room","room_key":"BER00009-BER898-IHG","hotel":null,"transforms":[]}"#
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, NaiveDate, Weekday};
+use csv::ReaderBuilder;
+use serde::Deserialize;
+
+use crate::data::custom_date;
+
+/// Named range of the dates, e.g the summer season, both of the ends are included.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Season {
+    pub name: String,
+    #[serde(with = "custom_date")]
+    pub start: NaiveDate,
+    #[serde(with = "custom_date")]
+    pub end: NaiveDate,
+}
+
+/// Function used to read the seasons from a CSV file with name, start and end columns, e.g `summer|20200601|20200831`.
+/// It throws an error if the file doesn't exist at specified path or if
+/// it encounters data that isn't in the format of the Season.
+pub fn seasons_reader(path: &Path) -> Result<Vec<Season>> {
+    let mut csv_reader = ReaderBuilder::new()
+        .has_headers(false)
+        .delimiter(b'|')
+        .from_path(path)
+        .with_context(|| "Path to the seasons data is invalid!")?;
+
+    csv_reader
+        .deserialize::<Season>()
+        .map(|res| {
+            res.with_context(|| "Encountered unparsable entity during parsing seasons data.")
+        })
+        .collect()
+}
+
+/// Label of the first season the date falls into, if there's any.
+pub fn season(seasons: &[Season], date: NaiveDate) -> Option<&str> {
+    seasons
+        .iter()
+        .find(|season| season.start <= date && date <= season.end)
+        .map(|season| season.name.as_str())
+}
+
+/// ISO 8601 week of the date, e.g 2018-W29.
+pub fn iso_week(date: NaiveDate) -> String {
+    let week = date.iso_week();
+    format!("{}-W{:02}", week.year(), week.week())
+}
+
+pub fn weekday_name(date: NaiveDate) -> &'static str {
+    match date.weekday() {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday",
+    }
+}
+
+/// How many days ahead of the checkin the offer is, it's negative for the checkins in the past.
+pub fn lead_time(checkin: NaiveDate, run_date: NaiveDate) -> i64 {
+    (checkin - run_date).num_days()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derive_calendar_values() {
+        let checkin = NaiveDate::from_ymd(2018, 7, 21);
+        assert_eq!(iso_week(checkin), "2018-W29");
+        // the first days of January might belong to the last week of the previous year
+        assert_eq!(iso_week(NaiveDate::from_ymd(2021, 1, 2)), "2020-W53");
+        assert_eq!(weekday_name(checkin), "Saturday");
+        assert_eq!(lead_time(checkin, NaiveDate::from_ymd(2018, 7, 1)), 20);
+        assert_eq!(lead_time(checkin, NaiveDate::from_ymd(2018, 7, 22)), -1);
+    }
+
+    #[test]
+    fn read_seasons() -> Result<()> {
+        let seasons = seasons_reader(Path::new("test_data/seasons.csv"))?;
+        assert_eq!(seasons.len(), 3);
+        assert_eq!(
+            season(&seasons, NaiveDate::from_ymd(2018, 7, 21)),
+            Some("summer")
+        );
+        assert_eq!(
+            season(&seasons, NaiveDate::from_ymd(2018, 8, 31)),
+            Some("summer")
+        );
+        assert_eq!(
+            season(&seasons, NaiveDate::from_ymd(2018, 12, 24)),
+            Some("christmas")
+        );
+        assert_eq!(season(&seasons, NaiveDate::from_ymd(2018, 3, 1)), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::NaiveDate;
+
+use crate::data::entities::Ranking;
+use crate::data::{group_sorted, sort_spilling, Output};
+use crate::Outputs;
+
+/// The ways in which offers of the same room from different sources are consolidated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Consolidation {
+    /// Only the cheapest offer of each group is kept.
+    Cheapest,
+    /// All of the offers are kept, ranked from the cheapest one.
+    Ranked,
+}
+
+impl Consolidation {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "cheapest" => Ok(Consolidation::Cheapest),
+            "ranked" => Ok(Consolidation::Ranked),
+            _ => Err(format!("expected one of: cheapest, ranked, got '{}'", name)),
+        }
+    }
+}
+
+type GroupKey = (String, String, NaiveDate, String);
+
+/// Offers are the same when they are for the same room in the same hotel, on the same day, with the same meal.
+fn group_key(output: &Output) -> GroupKey {
+    (
+        output.hotel_code.clone(),
+        output.room_code.clone(),
+        output.checkin,
+        output.meal.clone(),
+    )
+}
+
+/// Group the offers of different sources and rank them by the price per person.
+/// Groups are returned in the order in which they first appeared in the input. Offers with the same price
+/// keep their order, so the one that came first wins.
+pub fn consolidate(outputs: Vec<Output>, consolidation: Consolidation) -> Vec<Output> {
+    let mut groups: Vec<Vec<Output>> = Vec::new();
+    let mut group_indexes: HashMap<GroupKey, usize> = HashMap::new();
+    for output in outputs {
+        let key = group_key(&output);
+        match group_indexes.get(&key) {
+            Some(&index) => groups[index].push(output),
+            None => {
+                group_indexes.insert(key, groups.len());
+                groups.push(vec![output]);
+            }
+        }
+    }
+
+    groups
+        .into_iter()
+        .flat_map(|group| rank(group, consolidation))
+        .collect()
+}
+
+/// Consolidate the offers just like `consolidate`, with at most about `budget` bytes of them in memory.
+/// The offers are sorted by their groups and then back into the order of the groups, in runs on the disk
+/// when they don't fit into the budget.
+pub fn consolidate_spilling<'a>(
+    outputs: impl Iterator<Item = Output>,
+    consolidation: Consolidation,
+    budget: usize,
+) -> Result<Outputs<'a>> {
+    let by_group = sort_spilling(
+        outputs.enumerate().map(Ok),
+        |(index, output)| (group_key(output), *index),
+        budget,
+    )?;
+    // the group is known by its first offer, as that's where it appeared in the input
+    let ranked = group_sorted(by_group, |(_, output)| group_key(output)).flat_map(
+        move |group_res| -> Vec<Result<((usize, usize), Output)>> {
+            let group = match group_res {
+                Ok(group) => group,
+                Err(e) => return vec![Err(e)],
+            };
+            let first = group[0].0;
+            rank(
+                group.into_iter().map(|(_, output)| output).collect(),
+                consolidation,
+            )
+            .enumerate()
+            .map(|(position, output)| Ok(((first, position), output)))
+            .collect()
+        },
+    );
+    let sorted = sort_spilling(ranked, |(position, _)| *position, budget)?;
+    Ok(Box::new(
+        sorted.map(|offer| offer.map(|(_, output)| output)),
+    ))
+}
+
+/// Offers of the group ranked from the cheapest one, or just the cheapest one of them.
+fn rank(mut group: Vec<Output>, consolidation: Consolidation) -> impl Iterator<Item = Output> {
+    group.sort_by(|a, b| a.price.partial_cmp(&b.price).unwrap_or(Ordering::Equal));
+    let offers = group.len();
+    let winning_source = group[0].source.clone();
+    if consolidation == Consolidation::Cheapest {
+        group.truncate(1);
+    }
+    group
+        .into_iter()
+        .enumerate()
+        .map(move |(index, mut output)| {
+            output.ranking = Some(Ranking {
+                rank: index + 1,
+                offers,
+                winning_source: winning_source.clone(),
+            });
+            output
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Datelike;
+
+    use super::*;
+    use crate::data::ApproximateSize;
+    use crate::testing;
+
+    fn summarize(outputs: &[Output]) -> Vec<(&str, u32, usize, usize, &str)> {
+        outputs
+            .iter()
+            .map(|output| {
+                let ranking = output.ranking.as_ref().unwrap();
+                (
+                    output.source.as_str(),
+                    output.checkin.day(),
+                    ranking.rank,
+                    ranking.offers,
+                    ranking.winning_source.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    fn offers() -> Vec<Output> {
+        vec![
+            testing::output().build(),
+            testing::output().source("GTA").price(80.0).build(),
+            testing::output()
+                .checkin(NaiveDate::from_ymd(2018, 7, 22))
+                .price(78.0)
+                .build(),
+            testing::output().source("MARR").price(90.0).build(),
+            testing::output()
+                .source("GTA")
+                .checkin(NaiveDate::from_ymd(2018, 7, 22))
+                .price(78.0)
+                .build(),
+        ]
+    }
+
+    #[test]
+    fn keep_the_cheapest() {
+        assert_eq!(
+            summarize(&consolidate(offers(), Consolidation::Cheapest)),
+            vec![("GTA", 21, 1, 3, "GTA"), ("IHG", 22, 1, 2, "IHG")]
+        );
+    }
+
+    #[test]
+    fn rank_all() {
+        assert_eq!(
+            summarize(&consolidate(offers(), Consolidation::Ranked)),
+            vec![
+                ("GTA", 21, 1, 3, "GTA"),
+                ("IHG", 21, 2, 3, "GTA"),
+                ("MARR", 21, 3, 3, "GTA"),
+                ("IHG", 22, 1, 2, "IHG"),
+                ("GTA", 22, 2, 2, "IHG"),
+            ]
+        );
+    }
+
+    #[test]
+    fn different_rooms_are_not_grouped() {
+        let outputs = vec![
+            testing::output().build(),
+            testing::output().room_code("BER848").price(80.0).build(),
+        ];
+        assert_eq!(
+            summarize(&consolidate(outputs, Consolidation::Cheapest)),
+            vec![("IHG", 21, 1, 1, "IHG"), ("IHG", 21, 1, 1, "IHG")]
+        );
+    }
+
+    #[test]
+    fn consolidate_in_runs() -> Result<()> {
+        let offers: Vec<Output> = (0..60)
+            .map(|index| {
+                let room_code = format!("BER{}", 900 - index % 7);
+                testing::output()
+                    .room_code(&room_code)
+                    .source(["IHG", "GTA", "MARR"][index % 3])
+                    .checkin(NaiveDate::from_ymd(2018, 7, 21 + (index % 2) as u32))
+                    .price((index * 13 % 50) as f64)
+                    .build()
+            })
+            .collect();
+        let budget = 5 * offers[0].approximate_size();
+        for consolidation in [Consolidation::Cheapest, Consolidation::Ranked] {
+            let spilled = consolidate_spilling(offers.clone().into_iter(), consolidation, budget)?
+                .collect::<Result<Vec<_>>>()?;
+            let expected = consolidate(offers.clone(), consolidation);
+            assert_eq!(summarize(&spilled), summarize(&expected));
+            assert_eq!(
+                spilled
+                    .iter()
+                    .map(|output| &output.room_code)
+                    .collect::<Vec<_>>(),
+                expected
+                    .iter()
+                    .map(|output| &output.room_code)
+                    .collect::<Vec<_>>()
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+#[cfg(feature = "postgres")]
+use anyhow::Context;
+use anyhow::{anyhow, Result};
+
+use crate::data::entities::{Hotel, Room};
+
+/// Whether the location is a table of a PostgreSQL database, e.g `postgres://user@localhost/offers/rooms`.
+pub fn is_database(location: &str) -> bool {
+    location.starts_with("postgres://") || location.starts_with("postgresql://")
+}
+
+/// Split the location into the URL of the database and the name of its table, which is the last segment of the path.
+/// Parameters of the connection are kept in the URL, e.g `postgres://localhost/offers/rooms?connect_timeout=10`.
+pub fn split_table(location: &str) -> Result<(String, &str)> {
+    let (path, parameters) = match location.split_once('?') {
+        Some((path, parameters)) => (path, Some(parameters)),
+        None => (location, None),
+    };
+    let (url, table) = path
+        .rsplit_once('/')
+        .filter(|(url, _)| url.find("://").is_some_and(|scheme| url.len() > scheme + 3))
+        .ok_or_else(|| anyhow!("There's no table in {}!", location))?;
+    validate_table(table)?;
+    let url = match parameters {
+        Some(parameters) => format!("{}?{}", url, parameters),
+        None => url.to_string(),
+    };
+    Ok((url, table))
+}
+
+/// Name of the table is a part of the statements, so only plain names (optionally with the schema) are allowed.
+pub fn validate_table(table: &str) -> Result<()> {
+    let valid = !table.is_empty()
+        && table.split('.').all(|part| {
+            !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow!("Name of the table {} is invalid!", table))
+    }
+}
+
+#[cfg(feature = "postgres")]
+fn query(location: &str, columns: &str) -> Result<Vec<postgres::Row>> {
+    let (url, table) = split_table(location)?;
+    let mut client = postgres::Client::connect(&url, postgres::NoTls)
+        .with_context(|| "Couldn't connect to the PostgreSQL database!")?;
+    client
+        .query(format!("SELECT {} FROM {}", columns, table).as_str(), &[])
+        .with_context(|| format!("Couldn't read the table {}!", table))
+}
+
+/// Function used to read rooms data from a table with hotel_code, source, room_name and room_code columns.
+#[cfg(feature = "postgres")]
+pub fn rooms_table_reader(location: &str) -> Result<Vec<(String, Room)>> {
+    query(location, "hotel_code, source, room_name, room_code")?
+        .iter()
+        .map(|row| {
+            let room = Room {
+                hotel_code: row.try_get(0)?,
+                source: row.try_get(1)?,
+                room_name: row.try_get(2)?,
+                room_code: row.try_get(3)?,
+                valid_from: None,
+                valid_to: None,
+            };
+            Ok((room.key(), room))
+        })
+        .collect::<Result<_, postgres::Error>>()
+        .with_context(|| "Encountered unparsable entity during reading rooms data.")
+}
+
+/// Function used to read hotels data from a table with the columns named like the fields of the Hotel.
+#[cfg(feature = "postgres")]
+pub fn hotels_table_reader(location: &str) -> Result<Vec<(String, Hotel)>> {
+    query(
+        location,
+        "id, city_code, name, CAST(category AS REAL), country_code, city",
+    )?
+    .iter()
+    .map(|row| {
+        let hotel = Hotel {
+            id: row.try_get(0)?,
+            city_code: row.try_get(1)?,
+            name: row.try_get(2)?,
+            category: row.try_get(3)?,
+            country_code: row.try_get(4)?,
+            city: row.try_get(5)?,
+            valid_from: None,
+            valid_to: None,
+        };
+        Ok((hotel.id.clone(), hotel))
+    })
+    .collect::<Result<_, postgres::Error>>()
+    .with_context(|| "Encountered unparsable entity during reading hotels data.")
+}
+
+#[cfg(not(feature = "postgres"))]
+pub fn rooms_table_reader(location: &str) -> Result<Vec<(String, Room)>> {
+    Err(without_feature(location))
+}
+
+#[cfg(not(feature = "postgres"))]
+pub fn hotels_table_reader(location: &str) -> Result<Vec<(String, Hotel)>> {
+    Err(without_feature(location))
+}
+
+/// Locations of the tables are still checked, so they are reported the same way with and without the feature.
+#[cfg(not(feature = "postgres"))]
+fn without_feature(location: &str) -> anyhow::Error {
+    match split_table(location) {
+        Ok(_) => anyhow!(
+            "axiv was built without the postgres feature, so {} can't be read!",
+            location
+        ),
+        Err(e) => e,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_tables() -> Result<()> {
+        assert_eq!(
+            split_table("postgres://axiv@localhost:5433/offers/rooms")?,
+            (
+                String::from("postgres://axiv@localhost:5433/offers"),
+                "rooms"
+            )
+        );
+        assert_eq!(
+            split_table("postgresql://localhost/offers/reference.hotels?connect_timeout=10")?,
+            (
+                String::from("postgresql://localhost/offers?connect_timeout=10"),
+                "reference.hotels"
+            )
+        );
+        assert_eq!(
+            split_table("postgres://localhost")
+                .expect_err("This should fail")
+                .to_string(),
+            "There's no table in postgres://localhost!"
+        );
+        assert!(split_table("postgres://localhost/offers/rooms;DROP TABLE hotels").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn validate_table_names() {
+        assert!(validate_table("output").is_ok());
+        assert!(validate_table("offers.enriched_2020").is_ok());
+        assert_eq!(
+            validate_table("output; DROP TABLE hotels")
+                .expect_err("This should fail")
+                .to_string(),
+            "Name of the table output; DROP TABLE hotels is invalid!"
+        );
+        assert!(validate_table("offers.").is_err());
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+
+use anyhow::{Context, Result};
+use csv::StringRecord;
+use sha2::{Digest, Sha256};
+
+/// What happens with the records that are the same as one of the records before them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DuplicatePolicy {
+    /// Only the first of the same records is enriched, the others are dropped
+    KeepFirst,
+    /// All of them are enriched, the duplicates are just counted
+    KeepAll,
+    /// The duplicates are rejected
+    RejectDuplicates,
+}
+
+impl DuplicatePolicy {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "keep-first" => Ok(DuplicatePolicy::KeepFirst),
+            "keep-all" => Ok(DuplicatePolicy::KeepAll),
+            "reject-duplicates" => Ok(DuplicatePolicy::RejectDuplicates),
+            _ => Err(format!(
+                "expected one of: keep-first, keep-all, reject-duplicates, got '{}'",
+                name
+            )),
+        }
+    }
+}
+
+/// Size of each of the hashes in the spilled runs.
+const HASH_SIZE: u64 = 16;
+
+/// Sorted hashes saved in a temporary file, they are binary searched on the disk.
+struct Run {
+    file: File,
+    len: u64,
+}
+
+impl Run {
+    fn new(mut hashes: Vec<u128>) -> Result<Self> {
+        hashes.sort_unstable();
+        let file = tempfile::tempfile().with_context(|| "Couldn't spill the seen records!")?;
+        let mut writer = BufWriter::new(file);
+        for hash in &hashes {
+            writer.write_all(&hash.to_be_bytes())?;
+        }
+        Ok(Self {
+            file: writer
+                .into_inner()
+                .map_err(|e| e.into_error())
+                .with_context(|| "Couldn't spill the seen records!")?,
+            len: hashes.len() as u64,
+        })
+    }
+
+    fn contains(&mut self, hash: u128) -> Result<bool> {
+        let (mut low, mut high) = (0, self.len);
+        let mut bytes = [0; HASH_SIZE as usize];
+        while low < high {
+            let middle = (low + high) / 2;
+            self.file.seek(SeekFrom::Start(middle * HASH_SIZE))?;
+            self.file.read_exact(&mut bytes)?;
+            match u128::from_be_bytes(bytes).cmp(&hash) {
+                std::cmp::Ordering::Less => low = middle + 1,
+                std::cmp::Ordering::Greater => high = middle,
+                std::cmp::Ordering::Equal => return Ok(true),
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Hashes of the records seen so far. Once there are too many of them to keep in memory,
+/// they are spilled into a sorted run on the disk, so even the large inputs don't run out of memory.
+struct SeenRecords {
+    memory: HashSet<u128>,
+    max_in_memory: usize,
+    runs: Vec<Run>,
+}
+
+impl SeenRecords {
+    /// Remember the hash, it returns false when it was already seen.
+    fn insert(&mut self, hash: u128) -> Result<bool> {
+        if self.memory.contains(&hash) {
+            return Ok(false);
+        }
+        for run in &mut self.runs {
+            if run.contains(hash)? {
+                return Ok(false);
+            }
+        }
+        self.memory.insert(hash);
+        if self.memory.len() >= self.max_in_memory {
+            let hashes = self.memory.drain().collect();
+            self.runs.push(Run::new(hashes)?);
+        }
+        Ok(true)
+    }
+}
+
+/// Detection of the records of the input that were sent more than once, e.g the same offer resent by the supplier.
+/// Records are the same when they have the same values in the given columns, or in all of them when none are given.
+pub struct Duplicates {
+    pub policy: DuplicatePolicy,
+    columns: Vec<String>,
+    seen: SeenRecords,
+    count: usize,
+}
+
+impl Duplicates {
+    pub fn new(policy: DuplicatePolicy, columns: Vec<String>, max_in_memory: usize) -> Self {
+        Self {
+            policy,
+            columns,
+            seen: SeenRecords {
+                memory: HashSet::new(),
+                max_in_memory: max_in_memory.max(1),
+                runs: Vec::new(),
+            },
+            count: 0,
+        }
+    }
+
+    /// Hash of the values of the record that tell the duplicates apart.
+    fn hash(&self, headers: &StringRecord, record: &StringRecord) -> u128 {
+        let mut hasher = Sha256::new();
+        let mut add = |value: &str| {
+            // length of the value goes first, so the values can't run into each other
+            hasher.update((value.len() as u64).to_be_bytes());
+            hasher.update(value.as_bytes());
+        };
+        if self.columns.is_empty() {
+            record.iter().for_each(&mut add);
+        } else {
+            for column in &self.columns {
+                let value = headers
+                    .iter()
+                    .position(|header| header == column)
+                    .and_then(|index| record.get(index));
+                add(value.unwrap_or(""));
+            }
+        }
+        let mut bytes = [0; HASH_SIZE as usize];
+        bytes.copy_from_slice(&hasher.finalize()[..HASH_SIZE as usize]);
+        u128::from_be_bytes(bytes)
+    }
+
+    /// Whether the record is the same as one of the records checked before it.
+    pub fn check(&mut self, headers: &StringRecord, record: &StringRecord) -> Result<bool> {
+        let duplicate = !self.seen.insert(self.hash(headers, record))?;
+        if duplicate {
+            self.count += 1;
+        }
+        Ok(duplicate)
+    }
+
+    /// How many duplicates were found so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_duplicates() -> Result<()> {
+        let headers = StringRecord::from(vec!["hotel_code", "room_code", "price"]);
+        let record = |values: [&str; 3]| StringRecord::from(values.to_vec());
+        let mut duplicates = Duplicates::new(DuplicatePolicy::KeepFirst, Vec::new(), 100);
+        assert!(!duplicates.check(&headers, &record(["BER00002", "BER898", "85.50"]))?);
+        assert!(!duplicates.check(&headers, &record(["BER00002", "BER898", "78.00"]))?);
+        assert!(duplicates.check(&headers, &record(["BER00002", "BER898", "85.50"]))?);
+        // values can't run into each other
+        assert!(!duplicates.check(&headers, &record(["BER0000", "2BER898", "85.50"]))?);
+
+        let mut duplicates = Duplicates::new(
+            DuplicatePolicy::KeepFirst,
+            vec![String::from("hotel_code"), String::from("room_code")],
+            100,
+        );
+        assert!(!duplicates.check(&headers, &record(["BER00002", "BER898", "85.50"]))?);
+        assert!(duplicates.check(&headers, &record(["BER00002", "BER898", "78.00"]))?);
+        assert_eq!(duplicates.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn spill_seen_records() -> Result<()> {
+        let headers = StringRecord::from(vec!["id"]);
+        let mut duplicates = Duplicates::new(DuplicatePolicy::KeepAll, Vec::new(), 7);
+        for id in 0..100 {
+            let record = StringRecord::from(vec![id.to_string()]);
+            assert!(!duplicates.check(&headers, &record)?);
+        }
+        assert_eq!(duplicates.seen.runs.len(), 14);
+        for id in (0..100).rev() {
+            let record = StringRecord::from(vec![id.to_string()]);
+            assert!(duplicates.check(&headers, &record)?);
+        }
+        assert_eq!(duplicates.count(), 100);
+        Ok(())
+    }
+}
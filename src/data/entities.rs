@@ -1,12 +1,18 @@
+use std::num::NonZeroU16;
+
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
-use super::{custom_date, serialize_float};
+use super::custom_date;
+use super::placeholders::MatchStatus;
+use super::versions::validity_date;
+use super::warning::Warning;
 
 // I guess there are not many hotels, where you can find rooms for more than 256 people :D
 pub type PeopleAmount = u8;
 pub type HotelCategory = f32;
 pub type Price = f64;
+pub type Degrees = f64;
 
 /// It generates key for use in HashMap based on few properties of the room that are available in the input data,
 /// so we can distinguish rooms that have few of the same properties, but are not the same.
@@ -14,12 +20,26 @@ pub fn generate_room_key(hotel_code: &str, room_code: &str, source: &str) -> Str
     format!("{}-{}-{}", hotel_code, room_code, source)
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Room {
     pub hotel_code: String,
     pub source: String,
     pub room_name: String,
     pub room_code: String,
+    /// First day the item is valid on, it's valid since forever when it's not given
+    #[serde(
+        default,
+        with = "validity_date",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub valid_from: Option<NaiveDate>,
+    /// Last day the item is valid on, it's valid forever when it's not given
+    #[serde(
+        default,
+        with = "validity_date",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub valid_to: Option<NaiveDate>,
 }
 
 impl Room {
@@ -29,7 +49,12 @@ impl Room {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// It generates key for use in HashMap to find translation of the hotel data into the given language.
+pub fn generate_translation_key(hotel_id: &str, language: &str) -> String {
+    format!("{}-{}", hotel_id, language)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Hotel {
     pub id: String,
     pub city_code: String,
@@ -37,6 +62,52 @@ pub struct Hotel {
     pub category: HotelCategory,
     pub country_code: String,
     pub city: String,
+    /// First day the item is valid on, it's valid since forever when it's not given
+    #[serde(
+        default,
+        with = "validity_date",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub valid_from: Option<NaiveDate>,
+    /// Last day the item is valid on, it's valid forever when it's not given
+    #[serde(
+        default,
+        with = "validity_date",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub valid_to: Option<NaiveDate>,
+}
+
+/// Name of the hotel and its city in the given language.
+/// Empty values mean that there's no translation, so the default is used instead.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Translation {
+    pub hotel_id: String,
+    pub language: String,
+    pub name: String,
+    pub city: String,
+}
+
+impl Translation {
+    /// Key used in data sources to find the translation
+    pub fn key(&self) -> String {
+        generate_translation_key(&self.hotel_id, &self.language)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Coordinates {
+    pub hotel_id: String,
+    pub latitude: Degrees,
+    pub longitude: Degrees,
+}
+
+/// Chain and brand the hotel belongs to, e.g `BER00002|IHG|Crowne Plaza`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Chain {
+    pub hotel_id: String,
+    pub chain_code: String,
+    pub brand_name: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -52,11 +123,32 @@ pub struct Input {
     pub children: PeopleAmount,
     pub price: Price,
     pub source: String,
+    /// Length of the stay, it's a single night when the input has no such column
+    #[serde(default)]
+    pub nights: Option<NonZeroU16>,
+    /// Day of the checkout, when it's given it's used instead of the one after the nights
+    #[serde(
+        default,
+        deserialize_with = "custom_date::deserialize_optional",
+        serialize_with = "custom_date::serialize_optional"
+    )]
+    pub checkout: Option<NaiveDate>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+/// Position of the offer among the offers of the same room from different sources.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Ranking {
+    /// 1 for the cheapest offer
+    pub rank: usize,
+    /// How many offers there were for the room
+    pub offers: usize,
+    /// Source of the cheapest offer
+    pub winning_source: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Output {
-    #[serde(rename(serialize = "room_type meal"))]
+    #[serde(rename = "room_type meal")]
     pub room_type_meal: String,
     pub room_code: String,
     pub source: String,
@@ -68,12 +160,40 @@ pub struct Output {
     pub adults: PeopleAmount,
     pub children: PeopleAmount,
     pub room_name: String,
-    #[serde(with = "custom_date")]
+    #[serde(
+        serialize_with = "custom_date::serialize",
+        deserialize_with = "custom_date::deserialize_saved"
+    )]
     pub checkin: NaiveDate,
-    #[serde(with = "custom_date")]
+    #[serde(
+        serialize_with = "custom_date::serialize",
+        deserialize_with = "custom_date::deserialize_saved"
+    )]
     pub checkout: NaiveDate,
-    #[serde(serialize_with = "serialize_float")]
     pub price: Price,
+    pub latitude: Option<Degrees>,
+    pub longitude: Option<Degrees>,
+    pub hotel_code: String,
+    pub meal: String,
+    pub ranking: Option<Ranking>,
+    /// Whether the room and hotel were found, it's kept only when the placeholders are used for the missing ones
+    #[serde(default)]
+    pub match_status: Option<MatchStatus>,
+    /// Similarity of the key of the matched room to the one of the input, it's kept only when the rooms are matched fuzzily
+    #[serde(default)]
+    pub match_confidence: Option<f64>,
+    /// Names of the same room (the same hotel and room code) given by the other sources
+    #[serde(default)]
+    pub candidate_room_names: Vec<String>,
+    /// Chain of the hotel, it's kept only when the chains are given
+    #[serde(default)]
+    pub chain_code: Option<String>,
+    /// Brand of the hotel within its chain, it's kept only when the chains are given
+    #[serde(default)]
+    pub brand_name: Option<String>,
+    /// Suspicious things about the record, they are reported separately from the output
+    #[serde(skip)]
+    pub warnings: Vec<Warning>,
 }
 
 #[cfg(test)]
@@ -86,4 +206,10 @@ mod test {
         assert_eq!(generate_room_key("aaa", "bbb", "ccc"), "aaa-bbb-ccc");
         assert_eq!(generate_room_key("000", "111", "222"), "000-111-222");
     }
+
+    #[test]
+    fn translation_key() {
+        assert_eq!(generate_translation_key("HOTEL", "de"), "HOTEL-de");
+        assert_eq!(generate_translation_key("BER00002", "pl"), "BER00002-pl");
+    }
 }
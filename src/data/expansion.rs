@@ -0,0 +1,81 @@
+use chrono::Duration;
+
+use crate::data::entities::Price;
+use crate::data::Output;
+
+/// Split the stay into one record per night, each of them with its share of the price.
+/// Records of a single night are passed as they are.
+pub fn expand_nights(output: Output) -> Vec<Output> {
+    let nights = (output.checkout - output.checkin).num_days();
+    if nights <= 1 {
+        return vec![output];
+    }
+    let price = output.price / nights as Price;
+    (0..nights)
+        .map(|night| {
+            let checkin = output.checkin + Duration::days(night);
+            Output {
+                checkin,
+                checkout: checkin + Duration::days(1),
+                price,
+                ..output.clone()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn split_stay_into_nights() {
+        let nights = expand_nights(
+            testing::output()
+                .checkin(NaiveDate::from_ymd(2018, 7, 30))
+                .checkout(NaiveDate::from_ymd(2018, 8, 2))
+                .price(255.0)
+                .build(),
+        );
+        let nights = nights
+            .iter()
+            .map(|night| (night.checkin, night.checkout, night.price))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            nights,
+            vec![
+                (
+                    NaiveDate::from_ymd(2018, 7, 30),
+                    NaiveDate::from_ymd(2018, 7, 31),
+                    85.0
+                ),
+                (
+                    NaiveDate::from_ymd(2018, 7, 31),
+                    NaiveDate::from_ymd(2018, 8, 1),
+                    85.0
+                ),
+                (
+                    NaiveDate::from_ymd(2018, 8, 1),
+                    NaiveDate::from_ymd(2018, 8, 2),
+                    85.0
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn keep_single_nights() {
+        let checkin = NaiveDate::from_ymd(2018, 7, 30);
+        let nights = expand_nights(
+            testing::output()
+                .checkin(checkin)
+                .checkout(checkin + Duration::days(1))
+                .build(),
+        );
+        assert_eq!(nights.len(), 1);
+        assert_eq!(nights[0].price, 85.5);
+    }
+}
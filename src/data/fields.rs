@@ -0,0 +1,193 @@
+use std::fmt;
+
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+
+use crate::data::entities::{Hotel, HotelCategory, Input, PeopleAmount, Room};
+
+/// Highest category a hotel can have.
+const MAX_CATEGORY: HotelCategory = 7.0;
+
+/// Value of the field that's not in the expected format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldViolation {
+    pub field: &'static str,
+    pub value: String,
+    pub expected: String,
+}
+
+impl fmt::Display for FieldViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} '{}' is invalid, expected {}",
+            self.field, self.value, self.expected
+        )
+    }
+}
+
+/// Formats of the fields of the input and the reference data, checked when they are read,
+/// so the malformed values are reported right away instead of failing to match later on.
+/// Codes are checked by the optional patterns, which have to match the whole code, e.g `[A-Z]{3}\d{5}`.
+/// Category of the hotels has to be between 0 and 7, the price has to be a non-negative number
+/// and there can't be more people than the PeopleAmount holds.
+#[derive(Debug, Default, Clone)]
+pub struct FieldRules {
+    hotel_code: Option<Regex>,
+    room_code: Option<Regex>,
+    city_code: Option<Regex>,
+}
+
+impl FieldRules {
+    pub fn new(
+        hotel_code: Option<&str>,
+        room_code: Option<&str>,
+        city_code: Option<&str>,
+    ) -> Result<Self> {
+        let pattern = |field: &str, pattern: Option<&str>| -> Result<Option<Regex>> {
+            pattern
+                .map(|pattern| {
+                    Regex::new(&format!("^(?:{})$", pattern))
+                        .with_context(|| format!("Invalid pattern of the {}: {}!", field, pattern))
+                })
+                .transpose()
+        };
+        Ok(Self {
+            hotel_code: pattern("hotel_code", hotel_code)?,
+            room_code: pattern("room_code", room_code)?,
+            city_code: pattern("city_code", city_code)?,
+        })
+    }
+
+    fn code(
+        field: &'static str,
+        pattern: &Option<Regex>,
+        value: &str,
+    ) -> Result<(), FieldViolation> {
+        match pattern {
+            Some(pattern) if !pattern.is_match(value) => Err(FieldViolation {
+                field,
+                value: value.to_string(),
+                expected: format!("a code matching {}", pattern),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the record of the input, the first invalid field is reported.
+    pub fn check_input(&self, input: &Input) -> Result<(), FieldViolation> {
+        Self::code("hotel_code", &self.hotel_code, &input.hotel_code)?;
+        Self::code("room_code", &self.room_code, &input.room_code)?;
+        Self::code("city_code", &self.city_code, &input.city_code)?;
+        if input.adults.checked_add(input.children).is_none() {
+            return Err(FieldViolation {
+                field: "children",
+                value: input.children.to_string(),
+                expected: format!(
+                    "at most {} people together with the adults",
+                    PeopleAmount::MAX
+                ),
+            });
+        }
+        if !input.price.is_finite() || input.price < 0.0 {
+            return Err(FieldViolation {
+                field: "price",
+                value: input.price.to_string(),
+                expected: String::from("a finite, non-negative number"),
+            });
+        }
+        match input.checkout {
+            Some(checkout) if checkout <= input.checkin => Err(FieldViolation {
+                field: "checkout",
+                value: checkout.to_string(),
+                expected: format!("a date after the checkin {}", input.checkin),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_room(&self, room: &Room) -> Result<(), FieldViolation> {
+        Self::code("hotel_code", &self.hotel_code, &room.hotel_code)?;
+        Self::code("room_code", &self.room_code, &room.room_code)
+    }
+
+    pub fn check_hotel(&self, hotel: &Hotel) -> Result<(), FieldViolation> {
+        Self::code("hotel_code", &self.hotel_code, &hotel.id)?;
+        Self::code("city_code", &self.city_code, &hotel.city_code)?;
+        if !(0.0..=MAX_CATEGORY).contains(&hotel.category) {
+            return Err(FieldViolation {
+                field: "category",
+                value: hotel.category.to_string(),
+                expected: format!("a number between 0 and {}", MAX_CATEGORY),
+            });
+        }
+        Ok(())
+    }
+
+    /// Rooms read by one of the readers, it throws an error on the first invalid one.
+    pub fn rooms(&self, rooms: Vec<(String, Room)>) -> Result<Vec<(String, Room)>> {
+        for (key, room) in &rooms {
+            self.check_room(room)
+                .map_err(|violation| anyhow!("Room {} is invalid: {}!", key, violation))?;
+        }
+        Ok(rooms)
+    }
+
+    /// Hotels read by one of the readers, it throws an error on the first invalid one.
+    pub fn hotels(&self, hotels: Vec<(String, Hotel)>) -> Result<Vec<(String, Hotel)>> {
+        for (key, hotel) in &hotels {
+            self.check_hotel(hotel)
+                .map_err(|violation| anyhow!("Hotel {} is invalid: {}!", key, violation))?;
+        }
+        Ok(hotels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hotel(id: &str, category: HotelCategory) -> Hotel {
+        Hotel {
+            id: String::from(id),
+            city_code: String::from("BER"),
+            name: String::from("Hotel"),
+            category,
+            country_code: String::from("DE"),
+            city: String::from("Berlin"),
+            valid_from: None,
+            valid_to: None,
+        }
+    }
+
+    #[test]
+    fn check_hotels() -> Result<()> {
+        let rules = FieldRules::new(Some(r"[A-Z]{3}\d{5}"), None, Some("[A-Z]{3}"))?;
+        assert_eq!(rules.check_hotel(&hotel("BER00002", 4.5)), Ok(()));
+        assert_eq!(
+            rules
+                .check_hotel(&hotel("BER00002 ", 4.5))
+                .expect_err("This should fail")
+                .to_string(),
+            r"hotel_code 'BER00002 ' is invalid, expected a code matching ^(?:[A-Z]{3}\d{5})$"
+        );
+        assert_eq!(
+            rules
+                .hotels(vec![(String::from("BER00002"), hotel("BER00002", 8.0))])
+                .expect_err("This should fail")
+                .to_string(),
+            "Hotel BER00002 is invalid: category '8' is invalid, expected a number between 0 and 7!"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reject_invalid_pattern() {
+        assert_eq!(
+            FieldRules::new(None, Some("[A-Z"), None)
+                .expect_err("This should fail")
+                .to_string(),
+            "Invalid pattern of the room_code: [A-Z!"
+        );
+    }
+}
@@ -0,0 +1,576 @@
+use chrono::NaiveDate;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use super::custom_date;
+use crate::data::calendar::{iso_week, lead_time, season, weekday_name, Season};
+use crate::data::entities::{HotelCategory, Output, Price};
+use crate::stamp::Stamp;
+
+/// Configurable formatter of the numbers that end up in the output,
+/// e.g 1234.5 is presented as 1,234.50 in the `en` locale and as 1.234,50 in the `de` locale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NumberFormat {
+    pub decimal_separator: char,
+    pub thousands_separator: Option<char>,
+    pub decimals: usize,
+}
+
+impl NumberFormat {
+    /// Number format used by the given locale. `plain` locale formats numbers
+    /// with two decimal points and without any separators, e.g 1234.50
+    pub fn from_locale(locale: &str) -> Result<Self, String> {
+        let (decimal_separator, thousands_separator) = match locale {
+            "plain" => ('.', None),
+            "en" => ('.', Some(',')),
+            "de" | "pl" => (',', Some('.')),
+            "fr" => (',', Some(' ')),
+            "ch" => ('.', Some('\'')),
+            _ => {
+                return Err(format!(
+                    "expected one of: plain, en, de, pl, fr, ch, got '{}'",
+                    locale
+                ))
+            }
+        };
+        Ok(Self {
+            decimal_separator,
+            thousands_separator,
+            decimals: 2,
+        })
+    }
+
+    pub fn format(&self, num: Price) -> String {
+        if !num.is_finite() {
+            return num.to_string();
+        }
+        let formatted = format!("{:.*}", self.decimals, num.abs());
+        let (integer, fraction) = match formatted.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (formatted.as_str(), None),
+        };
+
+        let mut result = String::with_capacity(formatted.len() + integer.len() / 3 + 1);
+        // don't present negative zero e.g -0.001 as -0.00
+        if num.is_sign_negative() && formatted.bytes().any(|b| b.is_ascii_digit() && b != b'0') {
+            result.push('-');
+        }
+        for (i, digit) in integer.chars().enumerate() {
+            if i > 0 && (integer.len() - i) % 3 == 0 {
+                if let Some(separator) = self.thousands_separator {
+                    result.push(separator);
+                }
+            }
+            result.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            result.push(self.decimal_separator);
+            result.push_str(fraction);
+        }
+        result
+    }
+}
+
+/// The ways in which the category of a hotel can be presented in the output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CategoryFormat {
+    /// The category as it is, e.g 4.0 or 4.5
+    Raw,
+    /// Always with one decimal point, using the decimal separator of the number format, e.g 4.0 or 4,5
+    Decimal,
+    /// Number of stars with a suffix for half of the star, e.g 4 or 4½
+    Stars,
+    /// Textual description, e.g 4-star or 4.5-star
+    Text,
+}
+
+impl CategoryFormat {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "raw" => Ok(CategoryFormat::Raw),
+            "decimal" => Ok(CategoryFormat::Decimal),
+            "stars" => Ok(CategoryFormat::Stars),
+            "text" => Ok(CategoryFormat::Text),
+            _ => Err(format!(
+                "expected one of: raw, decimal, stars, text, got '{}'",
+                name
+            )),
+        }
+    }
+
+    /// Format the category, categories that are not a whole number of stars are rounded to the nearest half of the star.
+    /// Raw format is left to the serializer, so None is returned for it.
+    pub fn format(&self, category: HotelCategory, number: &NumberFormat) -> Option<String> {
+        let halves = (category * 2.0).round() as i64;
+        let stars = halves / 2;
+        let half = halves % 2 != 0;
+        match self {
+            CategoryFormat::Raw => None,
+            CategoryFormat::Decimal => Some(
+                NumberFormat {
+                    thousands_separator: None,
+                    decimals: 1,
+                    ..*number
+                }
+                .format(category as Price),
+            ),
+            CategoryFormat::Stars if half => Some(format!("{}½", stars)),
+            CategoryFormat::Stars => Some(stars.to_string()),
+            CategoryFormat::Text if half => Some(format!("{}.5-star", stars)),
+            CategoryFormat::Text => Some(format!("{}-star", stars)),
+        }
+    }
+}
+
+/// Everything that describes how the output records are presented.
+#[derive(Debug, Clone)]
+pub struct OutputFormat {
+    pub number: NumberFormat,
+    pub category: CategoryFormat,
+    /// Whether latitude and longitude columns are appended to the output.
+    pub coordinates: bool,
+    /// Whether chain_code and brand_name columns are appended to the output.
+    pub chains: bool,
+    /// Whether rank, offers and winning_source columns are appended to the output.
+    pub ranking: bool,
+    /// Date of the run, given when iso_week, weekday and lead_time_days columns are appended to the output.
+    pub run_date: Option<NaiveDate>,
+    /// Seasons of the dates, given when season column is appended to the output.
+    pub seasons: Option<Vec<Season>>,
+    /// Whether match_status column is appended to the output.
+    pub match_status: bool,
+    /// Whether match_confidence column is appended to the output.
+    pub match_confidence: bool,
+    /// Separator of the names, given when candidate_room_names column is appended to the output.
+    pub candidate_room_names: Option<String>,
+    /// Whether nights, room_nights and guest_nights columns are appended to the output.
+    pub derived_metrics: bool,
+    /// Where the output came from, given when axiv_version, settings_sha256 and reference_sha256 columns are appended.
+    pub stamp: Option<Stamp>,
+    /// Columns that are left out of the output by the mask.
+    pub dropped: Vec<String>,
+}
+
+impl Default for OutputFormat {
+    /// Plain numbers and raw categories, without any of the optional columns.
+    fn default() -> Self {
+        Self {
+            number: NumberFormat {
+                decimal_separator: '.',
+                thousands_separator: None,
+                decimals: 2,
+            },
+            category: CategoryFormat::Raw,
+            coordinates: false,
+            chains: false,
+            ranking: false,
+            run_date: None,
+            seasons: None,
+            match_status: false,
+            match_confidence: false,
+            candidate_room_names: None,
+            derived_metrics: false,
+            stamp: None,
+            dropped: Vec::new(),
+        }
+    }
+}
+
+impl OutputFormat {
+    /// Columns of the output in this format, in the order in which they are serialized.
+    pub fn columns(&self) -> Vec<&'static str> {
+        let mut columns = vec![
+            "room_type meal",
+            "room_code",
+            "source",
+            "hotel_name",
+            "city_name",
+            "city_code",
+            "hotel_category",
+            "pax",
+            "adults",
+            "children",
+            "room_name",
+            "checkin",
+            "checkout",
+            "price",
+        ];
+        if self.coordinates {
+            columns.extend(["latitude", "longitude"]);
+        }
+        if self.chains {
+            columns.extend(["chain_code", "brand_name"]);
+        }
+        if self.ranking {
+            columns.extend(["rank", "offers", "winning_source"]);
+        }
+        if self.run_date.is_some() {
+            columns.extend(["iso_week", "weekday", "lead_time_days"]);
+        }
+        if self.seasons.is_some() {
+            columns.push("season");
+        }
+        if self.match_status {
+            columns.push("match_status");
+        }
+        if self.match_confidence {
+            columns.push("match_confidence");
+        }
+        if self.candidate_room_names.is_some() {
+            columns.push("candidate_room_names");
+        }
+        if self.derived_metrics {
+            columns.extend(["nights", "room_nights", "guest_nights"]);
+        }
+        if self.stamp.is_some() {
+            columns.extend(["axiv_version", "settings_sha256", "reference_sha256"]);
+        }
+        columns.retain(|column| !self.dropped.iter().any(|dropped| dropped == column));
+        columns
+    }
+}
+
+/// Length of the stay of the offer.
+fn nights(output: &Output) -> i64 {
+    (output.checkout - output.checkin).num_days()
+}
+
+/// Output together with the format in which it should be serialized.
+/// The records are serialized with a custom serializer, so the presentation of the data can be
+/// tweaked with the settings of the run, while the Output itself keeps the plain values.
+pub struct OutputRecord<'a> {
+    output: &'a Output,
+    format: &'a OutputFormat,
+}
+
+impl<'a> OutputRecord<'a> {
+    pub fn new(output: &'a Output, format: &'a OutputFormat) -> Self {
+        Self { output, format }
+    }
+}
+
+/// Date serialized with the custom_date serde.
+struct Date<'a>(&'a NaiveDate);
+
+impl Serialize for Date<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        custom_date::serialize(self.0, serializer)
+    }
+}
+
+/// Fields of the serialized record, without the ones dropped by the mask.
+struct Fields<'a, R> {
+    record: R,
+    dropped: &'a [String],
+}
+
+impl<R: SerializeStruct> Fields<'_, R> {
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), R::Error> {
+        if self.dropped.iter().any(|dropped| dropped == key) {
+            return Ok(());
+        }
+        self.record.serialize_field(key, value)
+    }
+
+    fn end(self) -> Result<R::Ok, R::Error> {
+        self.record.end()
+    }
+}
+
+impl Serialize for OutputRecord<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let output = self.output;
+        let mut record = Fields {
+            record: serializer.serialize_struct("Output", 14)?,
+            dropped: &self.format.dropped,
+        };
+        record.serialize_field("room_type meal", &output.room_type_meal)?;
+        record.serialize_field("room_code", &output.room_code)?;
+        record.serialize_field("source", &output.source)?;
+        record.serialize_field("hotel_name", &output.hotel_name)?;
+        record.serialize_field("city_name", &output.city_name)?;
+        record.serialize_field("city_code", &output.city_code)?;
+        match self
+            .format
+            .category
+            .format(output.hotel_category, &self.format.number)
+        {
+            Some(category) => record.serialize_field("hotel_category", &category)?,
+            None => record.serialize_field("hotel_category", &output.hotel_category)?,
+        }
+        record.serialize_field("pax", &output.pax)?;
+        record.serialize_field("adults", &output.adults)?;
+        record.serialize_field("children", &output.children)?;
+        record.serialize_field("room_name", &output.room_name)?;
+        record.serialize_field("checkin", &Date(&output.checkin))?;
+        record.serialize_field("checkout", &Date(&output.checkout))?;
+        record.serialize_field("price", &self.format.number.format(output.price))?;
+        if self.format.coordinates {
+            record.serialize_field("latitude", &output.latitude)?;
+            record.serialize_field("longitude", &output.longitude)?;
+        }
+        if self.format.chains {
+            record.serialize_field("chain_code", &output.chain_code)?;
+            record.serialize_field("brand_name", &output.brand_name)?;
+        }
+        if self.format.ranking {
+            let ranking = output.ranking.as_ref();
+            record.serialize_field("rank", &ranking.map(|ranking| ranking.rank))?;
+            record.serialize_field("offers", &ranking.map(|ranking| ranking.offers))?;
+            record.serialize_field(
+                "winning_source",
+                &ranking.map(|ranking| &ranking.winning_source),
+            )?;
+        }
+        if let Some(run_date) = self.format.run_date {
+            record.serialize_field("iso_week", &iso_week(output.checkin))?;
+            record.serialize_field("weekday", weekday_name(output.checkin))?;
+            record.serialize_field("lead_time_days", &lead_time(output.checkin, run_date))?;
+        }
+        if let Some(seasons) = &self.format.seasons {
+            record.serialize_field("season", &season(seasons, output.checkin))?;
+        }
+        if self.format.match_status {
+            record.serialize_field("match_status", &output.match_status)?;
+        }
+        if self.format.match_confidence {
+            let confidence = output.match_confidence.map(|confidence| {
+                NumberFormat {
+                    thousands_separator: None,
+                    ..self.format.number
+                }
+                .format(confidence)
+            });
+            record.serialize_field("match_confidence", &confidence)?;
+        }
+        if let Some(separator) = &self.format.candidate_room_names {
+            record.serialize_field(
+                "candidate_room_names",
+                &output.candidate_room_names.join(separator),
+            )?;
+        }
+        if self.format.derived_metrics {
+            let nights = nights(output);
+            record.serialize_field("nights", &nights)?;
+            // every offer is for a single room
+            record.serialize_field("room_nights", &nights)?;
+            record.serialize_field("guest_nights", &(nights * i64::from(output.pax)))?;
+        }
+        if let Some(stamp) = &self.format.stamp {
+            record.serialize_field("axiv_version", stamp.axiv_version)?;
+            record.serialize_field("settings_sha256", &stamp.settings_sha256)?;
+            record.serialize_field("reference_sha256", &stamp.reference_sha256)?;
+        }
+        record.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn format_plain() {
+        let format = NumberFormat::from_locale("plain").unwrap();
+        assert_eq!(format.format(8.5), "8.50");
+        assert_eq!(format.format(85.0), "85.00");
+        assert_eq!(format.format(1234567.891), "1234567.89");
+        assert_eq!(format.format(-8.5), "-8.50");
+        assert_eq!(format.format(-0.001), "0.00");
+    }
+
+    #[test]
+    fn format_with_separators() {
+        let en = NumberFormat::from_locale("en").unwrap();
+        assert_eq!(en.format(8.5), "8.50");
+        assert_eq!(en.format(999.0), "999.00");
+        assert_eq!(en.format(1000.0), "1,000.00");
+        assert_eq!(en.format(1234567.891), "1,234,567.89");
+        assert_eq!(en.format(-1234.5), "-1,234.50");
+
+        let de = NumberFormat::from_locale("de").unwrap();
+        assert_eq!(de.format(8.5), "8,50");
+        assert_eq!(de.format(1234567.891), "1.234.567,89");
+
+        let fr = NumberFormat::from_locale("fr").unwrap();
+        assert_eq!(fr.format(1234.5), "1 234,50");
+
+        let ch = NumberFormat::from_locale("ch").unwrap();
+        assert_eq!(ch.format(1234.5), "1'234.50");
+    }
+
+    #[test]
+    fn format_without_decimals() {
+        let format = NumberFormat {
+            decimal_separator: ',',
+            thousands_separator: Some('.'),
+            decimals: 0,
+        };
+        assert_eq!(format.format(1234.5), "1.234");
+        assert_eq!(format.format(1235.5), "1.236");
+    }
+
+    #[test]
+    fn format_category() {
+        let plain = NumberFormat::from_locale("plain").unwrap();
+        let de = NumberFormat::from_locale("de").unwrap();
+
+        assert_eq!(CategoryFormat::Raw.format(4.0, &plain), None);
+
+        assert_eq!(
+            CategoryFormat::Decimal.format(4.0, &plain),
+            Some(String::from("4.0"))
+        );
+        assert_eq!(
+            CategoryFormat::Decimal.format(4.5, &de),
+            Some(String::from("4,5"))
+        );
+
+        assert_eq!(
+            CategoryFormat::Stars.format(4.0, &plain),
+            Some(String::from("4"))
+        );
+        assert_eq!(
+            CategoryFormat::Stars.format(4.5, &plain),
+            Some(String::from("4½"))
+        );
+        assert_eq!(
+            CategoryFormat::Stars.format(3.7, &plain),
+            Some(String::from("3½"))
+        );
+
+        assert_eq!(
+            CategoryFormat::Text.format(5.0, &plain),
+            Some(String::from("5-star"))
+        );
+        assert_eq!(
+            CategoryFormat::Text.format(4.5, &plain),
+            Some(String::from("4.5-star"))
+        );
+    }
+
+    #[test]
+    fn record_serialization() {
+        let output = testing::output()
+            .hotel_name("Crowne Plaza Berlin City Centre")
+            .hotel_category(4.5)
+            .price(1085.5)
+            .latitude(52.50424)
+            .build();
+        let format = OutputFormat {
+            number: NumberFormat::from_locale("de").unwrap(),
+            category: CategoryFormat::Text,
+            coordinates: true,
+            ..OutputFormat::default()
+        };
+
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(b';')
+            .from_writer(vec![]);
+        writer
+            .serialize(OutputRecord::new(&output, &format))
+            .expect("Unable to serialize given record");
+        assert_eq!(
+            String::from_utf8(writer.into_inner().unwrap()).unwrap(),
+            "room_type meal;room_code;source;hotel_name;city_name;city_code;hotel_category;pax;adults;children;room_name;checkin;checkout;price;latitude;longitude\n\
+             EZ F;BER898;IHG;Crowne Plaza Berlin City Centre;Berlin;BER;4.5-star;1;1;0;Einzelzimmer;2018-07-21;2018-07-22;1.085,50;52.50424;\n"
+        );
+        // columns are the same as the header of the serialized records
+        assert_eq!(
+            format.columns().join(";"),
+            "room_type meal;room_code;source;hotel_name;city_name;city_code;hotel_category;pax;adults;children;room_name;checkin;checkout;price;latitude;longitude"
+        );
+
+        // the columns dropped by the mask are left out of both
+        let format = OutputFormat {
+            dropped: vec![String::from("room_name"), String::from("latitude")],
+            ..format
+        };
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(b';')
+            .from_writer(vec![]);
+        writer
+            .serialize(OutputRecord::new(&output, &format))
+            .expect("Unable to serialize given record");
+        let serialized = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        let lines: Vec<&str> = serialized.lines().collect();
+        assert_eq!(lines[0], format.columns().join(";"));
+        assert_eq!(
+            lines[1],
+            "EZ F;BER898;IHG;Crowne Plaza Berlin City Centre;Berlin;BER;4.5-star;1;1;0;2018-07-21;2018-07-22;1.085,50;"
+        );
+    }
+
+    #[test]
+    fn calendar_columns() {
+        let output = testing::output().hotel_category(4.5).build();
+        let format = OutputFormat {
+            run_date: Some(NaiveDate::from_ymd(2018, 7, 1)),
+            seasons: Some(vec![Season {
+                name: String::from("summer"),
+                start: NaiveDate::from_ymd(2018, 6, 1),
+                end: NaiveDate::from_ymd(2018, 8, 31),
+            }]),
+            ..OutputFormat::default()
+        };
+
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(b';')
+            .from_writer(vec![]);
+        writer
+            .serialize(OutputRecord::new(&output, &format))
+            .expect("Unable to serialize given record");
+        let serialized = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        let lines = serialized.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], format.columns().join(";"));
+        assert!(lines[0].ends_with(";price;iso_week;weekday;lead_time_days;season"));
+        assert!(lines[1].ends_with(";85.50;2018-W29;Saturday;20;summer"));
+    }
+
+    #[test]
+    fn derived_metrics() {
+        let output = testing::output()
+            .room_type("DZ")
+            .room_code("BER848")
+            .source("MARR")
+            .hotel_name("Berlin Marriott Hotel")
+            .hotel_category(5.0)
+            .adults(2)
+            .children(1)
+            .room_name("Doppelzimmer")
+            .checkout(NaiveDate::from_ymd(2018, 7, 25))
+            .hotel_code("BER00003")
+            .build();
+        let format = OutputFormat {
+            derived_metrics: true,
+            ..OutputFormat::default()
+        };
+
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(b';')
+            .from_writer(vec![]);
+        writer
+            .serialize(OutputRecord::new(&output, &format))
+            .expect("Unable to serialize given record");
+        let serialized = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        let lines = serialized.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], format.columns().join(";"));
+        assert!(lines[0].ends_with(";price;nights;room_nights;guest_nights"));
+        assert!(lines[1].ends_with(";85.50;4;4;12"));
+    }
+
+    #[test]
+    fn unknown_locale() {
+        assert_eq!(
+            NumberFormat::from_locale("xx"),
+            Err(String::from(
+                "expected one of: plain, en, de, pl, fr, ch, got 'xx'"
+            ))
+        );
+    }
+}
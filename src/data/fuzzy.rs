@@ -0,0 +1,68 @@
+use strsim::normalized_levenshtein;
+
+use crate::data::entities::{generate_room_key, Room};
+use crate::data::RoomDataSource;
+
+/// Room of the same hotel and source whose key is the most similar to the key of the missing room,
+/// together with the similarity of the keys (normalized Levenshtein, from 0 to 1).
+/// Rooms that are less similar than `min_similarity` are not matched, ties go to the lower key.
+/// Only the rooms in memory are compared, the remote ones can't be searched through.
+pub fn nearest_room<'a>(
+    rooms: &'a RoomDataSource,
+    hotel_code: &str,
+    room_code: &str,
+    source: &str,
+    min_similarity: f64,
+) -> Option<(f64, &'a Room)> {
+    let key = generate_room_key(hotel_code, room_code, source);
+    rooms
+        .iter()
+        .filter(|(_, room)| room.hotel_code == hotel_code && room.source == source)
+        .map(|(room_key, room)| (normalized_levenshtein(&key, room_key), room_key, room))
+        .filter(|(similarity, _, _)| *similarity >= min_similarity)
+        .max_by(|a, b| a.0.total_cmp(&b.0).then(b.1.cmp(a.1)))
+        .map(|(similarity, _, room)| (similarity, room))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::DataSource;
+
+    fn room(hotel_code: &str, source: &str, room_code: &str) -> Room {
+        Room {
+            hotel_code: String::from(hotel_code),
+            source: String::from(source),
+            room_name: format!("Room {}", room_code),
+            room_code: String::from(room_code),
+            valid_from: None,
+            valid_to: None,
+        }
+    }
+
+    fn rooms() -> RoomDataSource {
+        let mut rooms: RoomDataSource = DataSource::new();
+        for room in [
+            room("BER00002", "IHG", "BER898"),
+            room("BER00002", "IHG", "BER123"),
+            room("BER00002", "GTA", "BER899"),
+            room("BER00003", "IHG", "BER899"),
+        ] {
+            rooms.insert(room.key(), room);
+        }
+        rooms.freeze()
+    }
+
+    #[test]
+    fn match_nearest_room() {
+        let rooms = rooms();
+        let (similarity, room) = nearest_room(&rooms, "BER00002", "BER899", "IHG", 0.8)
+            .expect("There should be a match");
+        assert_eq!(room.room_code, "BER898");
+        // one character of the 19 of the key is different
+        assert!((similarity - 18.0 / 19.0).abs() < 1e-9);
+
+        assert!(nearest_room(&rooms, "BER00002", "XYZ000", "IHG", 0.8).is_none());
+        assert!(nearest_room(&rooms, "BER00004", "BER898", "IHG", 0.0).is_none());
+    }
+}
@@ -0,0 +1,471 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use csv::{Reader, StringRecord};
+use serde::Deserialize;
+
+use crate::data::custom_date::INPUT_FORMAT;
+
+/// Record of the input data as it was read, before it's deserialized into the Input.
+/// Keeping the raw values around allows us to tell exactly which record was rejected and why.
+#[derive(Debug, Clone)]
+pub struct RawInput {
+    /// Line of the input file where the record begins
+    pub line: u64,
+    pub record: StringRecord,
+}
+
+/// Records of the input data, records that can't be even read are reported as errors,
+/// e.g when they are not valid UTF-8 or have a different number of fields than the header.
+pub type RawInputs = Box<dyn Iterator<Item = csv::Result<RawInput>>>;
+
+/// Read the header of the CSV input and prepare the iterator over its records.
+pub fn csv_input(mut reader: Reader<File>) -> Result<(StringRecord, RawInputs)> {
+    let headers = reader
+        .headers()
+        .with_context(|| "Couldn't read the header of the input data!")?
+        .clone();
+    let records = reader.into_records().map(|res| {
+        res.map(|record| RawInput {
+            line: record.position().map_or(0, |position| position.line()),
+            record,
+        })
+    });
+    Ok((headers, Box::new(records)))
+}
+
+/// Columns of the input, in the order in which they come in the Input.
+pub const INPUT_COLUMNS: [&str; 10] = [
+    "city_code",
+    "hotel_code",
+    "room_type",
+    "room_code",
+    "meal",
+    "checkin",
+    "adults",
+    "children",
+    "price",
+    "source",
+];
+
+/// Columns of the second version of the input, which the suppliers are migrating to: the meal is called the board,
+/// the dates are in ISO 8601 (e.g 2018-07-21) and the currency of the price and the nights of the stay are added.
+pub const INPUT_COLUMNS_V2: [&str; 12] = [
+    "city_code",
+    "hotel_code",
+    "room_type",
+    "room_code",
+    "board",
+    "checkin",
+    "adults",
+    "children",
+    "price",
+    "currency",
+    "source",
+    "nights",
+];
+
+/// Format of the dates in the second version of the input.
+const DATE_FORMAT_V2: &str = "%Y-%m-%d";
+
+/// Versions of the columns of the input, each of them is normalized into the columns of the Input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputSchema {
+    /// Detected for each file by its header, or by the number of its columns when it has no header
+    Auto,
+    /// The original columns of INPUT_COLUMNS
+    V1,
+    /// The columns of INPUT_COLUMNS_V2
+    V2,
+}
+
+impl InputSchema {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "auto" => Ok(InputSchema::Auto),
+            "v1" => Ok(InputSchema::V1),
+            "v2" => Ok(InputSchema::V2),
+            _ => Err(format!("expected one of: auto, v1, v2, got '{}'", name)),
+        }
+    }
+
+    fn columns(self) -> &'static [&'static str] {
+        match self {
+            InputSchema::V2 => &INPUT_COLUMNS_V2,
+            InputSchema::Auto | InputSchema::V1 => &INPUT_COLUMNS,
+        }
+    }
+
+    /// Schema of the input with the given header. The header is the first record of the input when none of its
+    /// values is a known column, then the schema is the one with the same number of columns.
+    /// Otherwise it's the one with the fewest of its columns missing in the header, the older one on a tie.
+    fn detect(self, header: &StringRecord) -> Self {
+        if self != InputSchema::Auto {
+            return self;
+        }
+        let schemas = [InputSchema::V1, InputSchema::V2];
+        if !has_header(header) {
+            return schemas
+                .iter()
+                .copied()
+                .find(|schema| schema.columns().len() == header.len())
+                .unwrap_or(InputSchema::V1);
+        }
+        let missing = |schema: &InputSchema| {
+            schema
+                .columns()
+                .iter()
+                .filter(|column| !header.iter().any(|value| value == **column))
+                .count()
+        };
+        schemas
+            .iter()
+            .copied()
+            .min_by_key(missing)
+            .unwrap_or(InputSchema::V1)
+    }
+}
+
+/// Whether any of the values of the first line of the input is one of the columns of the schemas.
+fn has_header(header: &StringRecord) -> bool {
+    header
+        .iter()
+        .any(|value| INPUT_COLUMNS.contains(&value) || INPUT_COLUMNS_V2.contains(&value))
+}
+
+/// Input in the columns of the Input, whichever of the schemas it comes in, so the rest of the run
+/// (and the rejects) only ever sees those. The input without a header gets the columns of its schema
+/// and its first line is read as a record.
+pub fn normalize_input(
+    schema: InputSchema,
+    header: StringRecord,
+    records: RawInputs,
+) -> (StringRecord, RawInputs) {
+    let schema = schema.detect(&header);
+    let (header, records): (StringRecord, RawInputs) = if has_header(&header) {
+        (header, records)
+    } else {
+        let first = RawInput {
+            line: 1,
+            record: header,
+        };
+        (
+            StringRecord::from(schema.columns().to_vec()),
+            Box::new(std::iter::once(Ok(first)).chain(records)),
+        )
+    };
+    match schema {
+        InputSchema::V2 => {
+            let header: StringRecord = header
+                .iter()
+                .map(|column| if column == "board" { "meal" } else { column })
+                .collect();
+            let normalized = header.clone();
+            let records = records.map(move |raw| {
+                raw.map(|raw| RawInput {
+                    line: raw.line,
+                    record: reformat_dates(&normalized, &raw.record, DATE_FORMAT_V2),
+                })
+            });
+            (header, Box::new(records))
+        }
+        InputSchema::Auto | InputSchema::V1 => (header, records),
+    }
+}
+
+/// Formats in which the input might come.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputFormat {
+    /// Picked by the extension of the input file, CSV unless it's .xml
+    Auto,
+    Csv,
+    Xml,
+    /// Fixed-width columns described by the column spec
+    Fixed,
+}
+
+impl InputFormat {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "auto" => Ok(InputFormat::Auto),
+            "csv" => Ok(InputFormat::Csv),
+            "xml" => Ok(InputFormat::Xml),
+            "fixed" => Ok(InputFormat::Fixed),
+            _ => Err(format!(
+                "expected one of: auto, csv, xml, fixed, got '{}'",
+                name
+            )),
+        }
+    }
+
+    /// Format of the input at the given location.
+    pub fn resolve(self, location: &str) -> Self {
+        match self {
+            InputFormat::Auto if location.to_lowercase().ends_with(".xml") => InputFormat::Xml,
+            InputFormat::Auto => InputFormat::Csv,
+            format => format,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnType {
+    /// Padded with spaces on the right
+    Text,
+    /// Padded with spaces on both sides
+    Number,
+    /// Padded with spaces on both sides
+    Date,
+}
+
+/// Where one of the columns is in the lines of the fixed-width input.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ColumnSpec {
+    pub name: String,
+    /// Position of the first character of the column, starting at 1
+    pub start: usize,
+    pub length: usize,
+    #[serde(rename = "type")]
+    pub kind: ColumnType,
+}
+
+impl ColumnSpec {
+    fn value(&self, line: &str) -> String {
+        let value: String = line
+            .chars()
+            .skip(self.start.saturating_sub(1))
+            .take(self.length)
+            .collect();
+        match self.kind {
+            ColumnType::Text => value.trim_end().to_string(),
+            ColumnType::Number | ColumnType::Date => value.trim().to_string(),
+        }
+    }
+}
+
+/// Function used to read the spec of the fixed-width columns (`name|start|length|type`) from a CSV file.
+/// It throws an error if the file doesn't exist at specified path or if
+/// it encounters data that isn't in the format of the ColumnSpec.
+pub fn column_spec_reader(path: &Path) -> Result<Vec<ColumnSpec>> {
+    let mut csv_reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .delimiter(b'|')
+        .from_path(path)
+        .with_context(|| "Path to the column spec is invalid!")?;
+
+    csv_reader
+        .deserialize::<ColumnSpec>()
+        .map(|res| res.with_context(|| "Encountered unparsable entity during parsing column spec."))
+        .collect()
+}
+
+/// Read the records of the fixed-width input, each line is a record with the columns given by the spec.
+/// Empty lines are skipped.
+pub fn fixed_width_input(path: &Path, spec: Vec<ColumnSpec>) -> Result<(StringRecord, RawInputs)> {
+    let file = File::open(path).with_context(|| "Path to the input data is invalid!")?;
+    let headers = spec.iter().map(|column| column.name.as_str()).collect();
+    let records = BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(move |(index, line)| {
+            let line = line.map_err(csv::Error::from)?;
+            Ok(RawInput {
+                line: index as u64 + 1,
+                record: spec.iter().map(|column| column.value(&line)).collect(),
+            })
+        });
+    Ok((headers, Box::new(records)))
+}
+
+/// How the codes of the input are turned into the keys used to find the rooms and hotels.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyCase {
+    /// Codes are used as they are
+    #[default]
+    Exact,
+    /// Codes are uppercased, for suppliers that don't care about the case of the codes
+    Upper,
+}
+
+impl KeyCase {
+    pub fn apply(self, code: &str) -> String {
+        match self {
+            KeyCase::Exact => code.to_string(),
+            KeyCase::Upper => code.to_uppercase(),
+        }
+    }
+}
+
+/// Rewrite the checkin and the checkout of the record from the given format into the one expected by the Input.
+/// Date that doesn't match the format is left as it is, so the record is rejected as unparsable later on.
+pub fn reformat_dates(headers: &StringRecord, record: &StringRecord, format: &str) -> StringRecord {
+    let dates: Vec<_> = headers
+        .iter()
+        .map(|header| header == "checkin" || header == "checkout")
+        .collect();
+    record
+        .iter()
+        .enumerate()
+        .map(
+            |(index, value)| match NaiveDate::parse_from_str(value, format) {
+                Ok(date) if dates.get(index) == Some(&true) => {
+                    date.format(INPUT_FORMAT).to_string()
+                }
+                _ => value.to_string(),
+            },
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reformat_dates_only() {
+        let headers = StringRecord::from(vec!["hotel_code", "checkin", "checkout", "booked"]);
+        let record = StringRecord::from(vec!["BER00002", "21.07.2018", "22.07.2018", "01.07.2018"]);
+        assert_eq!(
+            reformat_dates(&headers, &record, "%d.%m.%Y"),
+            StringRecord::from(vec!["BER00002", "20180721", "20180722", "01.07.2018"])
+        );
+        assert_eq!(reformat_dates(&headers, &record, "%Y-%m-%d"), record);
+    }
+
+    #[test]
+    fn read_fixed_width_input() -> Result<()> {
+        let spec = column_spec_reader(Path::new("test_data/input_columns.csv"))?;
+        let (headers, records) = fixed_width_input(Path::new("test_data/input.txt"), spec)?;
+        assert_eq!(headers, StringRecord::from(INPUT_COLUMNS.to_vec()));
+        let records = records.collect::<csv::Result<Vec<_>>>()?;
+        assert_eq!(
+            records
+                .iter()
+                .map(|raw| (raw.line, raw.record.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    1,
+                    StringRecord::from(vec![
+                        "BER", "BER00002", "EZ", "BER898", "F", "20180721", "1", "0", "85.50",
+                        "IHG"
+                    ])
+                ),
+                (
+                    3,
+                    StringRecord::from(vec![
+                        "BER", "BER00003", "DZ", "BER848", "F", "20180721", "2", "0", "140.00",
+                        "MARR"
+                    ])
+                ),
+            ]
+        );
+        Ok(())
+    }
+
+    fn normalize(
+        schema: InputSchema,
+        lines: Vec<Vec<&str>>,
+    ) -> Result<(StringRecord, Vec<(u64, StringRecord)>)> {
+        let header = StringRecord::from(lines[0].clone());
+        let records: Vec<csv::Result<RawInput>> = lines[1..]
+            .iter()
+            .enumerate()
+            .map(|(index, values)| {
+                Ok(RawInput {
+                    line: index as u64 + 2,
+                    record: StringRecord::from(values.clone()),
+                })
+            })
+            .collect();
+        let (header, records) = normalize_input(schema, header, Box::new(records.into_iter()));
+        let records = records
+            .map(|raw| raw.map(|raw| (raw.line, raw.record)))
+            .collect::<csv::Result<_>>()?;
+        Ok((header, records))
+    }
+
+    #[test]
+    fn normalize_input_schemas() -> Result<()> {
+        let v1 = vec![
+            "BER", "BER00002", "EZ", "BER898", "F", "20180721", "1", "0", "85.50", "IHG",
+        ];
+        let v2 = vec![
+            "BER",
+            "BER00002",
+            "EZ",
+            "BER898",
+            "F",
+            "2018-07-21",
+            "1",
+            "0",
+            "85.50",
+            "EUR",
+            "IHG",
+            "2",
+        ];
+        let normalized_v2 = StringRecord::from(vec![
+            "BER", "BER00002", "EZ", "BER898", "F", "20180721", "1", "0", "85.50", "EUR", "IHG",
+            "2",
+        ]);
+        let header_v2 = StringRecord::from(
+            INPUT_COLUMNS_V2
+                .iter()
+                .map(|column| if *column == "board" { "meal" } else { column })
+                .collect::<Vec<_>>(),
+        );
+
+        // the original input is left as it is
+        let (header, records) =
+            normalize(InputSchema::Auto, vec![INPUT_COLUMNS.to_vec(), v1.clone()])?;
+        assert_eq!(header, StringRecord::from(INPUT_COLUMNS.to_vec()));
+        assert_eq!(records, vec![(2, StringRecord::from(v1.clone()))]);
+
+        // the columns of the second version can come in any order
+        let mut columns = INPUT_COLUMNS_V2.to_vec();
+        let mut values = v2.clone();
+        columns.rotate_left(1);
+        values.rotate_left(1);
+        let (header, records) = normalize(InputSchema::Auto, vec![columns, values])?;
+        assert_eq!(header.get(11), Some("city_code"));
+        assert_eq!(header.get(3), Some("meal"));
+        assert_eq!(records[0].1.get(4), Some("20180721"));
+
+        // the input without a header is told by the number of its columns
+        let (header, records) = normalize(InputSchema::Auto, vec![v2.clone(), v2.clone()])?;
+        assert_eq!(header, header_v2);
+        assert_eq!(
+            records,
+            vec![(1, normalized_v2.clone()), (2, normalized_v2.clone())]
+        );
+        let (header, records) = normalize(InputSchema::Auto, vec![v1.clone()])?;
+        assert_eq!(header, StringRecord::from(INPUT_COLUMNS.to_vec()));
+        assert_eq!(records, vec![(1, StringRecord::from(v1))]);
+
+        // the given schema is used regardless of the columns
+        let (header, _) = normalize(InputSchema::V2, vec![INPUT_COLUMNS.to_vec()])?;
+        assert_eq!(header, StringRecord::from(INPUT_COLUMNS.to_vec()));
+        let (header, records) = normalize(InputSchema::V2, vec![v2])?;
+        assert_eq!((header, records), (header_v2, vec![(1, normalized_v2)]));
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_input_format() {
+        assert_eq!(InputFormat::Auto.resolve("input.csv"), InputFormat::Csv);
+        assert_eq!(InputFormat::Auto.resolve("INPUT.XML"), InputFormat::Xml);
+        assert_eq!(InputFormat::Fixed.resolve("input.csv"), InputFormat::Fixed);
+    }
+
+    #[test]
+    fn upper_key_case() {
+        assert_eq!(KeyCase::Upper.apply("ber00002"), "BER00002");
+        assert_eq!(KeyCase::Exact.apply("ber00002"), "ber00002");
+    }
+}
@@ -1,31 +1,897 @@
-use std::fs::File;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use chrono::Duration;
-use csv::DeserializeRecordsIter;
+use csv::{DeserializeErrorKind, StringRecord};
 
-use crate::data::entities::{generate_room_key, Price};
-use crate::data::{HotelDataSource, Input, Output, RoomDataSource};
+use crate::data::audit::{Audit, AuditEntry};
+use crate::data::duplicates::{DuplicatePolicy, Duplicates};
+use crate::data::entities::{generate_room_key, generate_translation_key, Hotel, Price, Room};
+use crate::data::fields::{FieldRules, FieldViolation};
+use crate::data::fuzzy::nearest_room;
+use crate::data::input::{reformat_dates, KeyCase, RawInput, RawInputs};
+use crate::data::markets::Markets;
+use crate::data::missing::{Coverage, MissingKey, MissingKeys, MissingKind};
+use crate::data::placeholders::{MatchStatus, Placeholders};
+use crate::data::reject::{Reject, RejectReason, Snippet};
+use crate::data::sanitize::Sanitizer;
+use crate::data::versions::Versions;
+use crate::data::{
+    CacheStats, ChainDataSource, CoordinatesDataSource, HotelDataSource, Input, Output,
+    RoomDataSource, RoomNameNormalizer, TranslationDataSource, WarningRules,
+};
+use crate::timings::{Snapshot, Usage};
+
+/// Time spent in reading the input and in fetching the rooms and hotels it links to.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct IntegratorUsage {
+    pub read: Usage,
+    pub lookup: Usage,
+}
 
 /// Struct used to enrich input data with the additional data from the rooms and hotels data source
 /// It works as an iterator and lazily buffers the data from .csv and into .csv files, so it is able
 /// to work with larger amounts of data.
-pub struct DataIntegrator<'a> {
-    input: DeserializeRecordsIter<'a, File, Input>,
+pub struct DataIntegrator {
+    input: RawInputs,
+    enrichment: Enrichment,
+    audit: Option<Audit>,
+    usage: Option<IntegratorUsage>,
+    duplicates: Option<Duplicates>,
+    /// Most bytes the record of the input can take
+    max_record_size: Option<usize>,
+    /// Countries the records are limited to
+    markets: Option<Markets>,
+    /// Coverage of the input found by the first pass over it
+    coverage: Option<Coverage>,
+    /// Threads the records are enriched by and the order they are given in, when there's more than one
+    threads: Option<(NonZeroUsize, Order)>,
+    /// Records that were already enriched by the threads, with their trails, and wait to be given
+    enriched: VecDeque<Result<Enriched>>,
+}
+
+/// Everything the records of the input are enriched with, it's only read, so it's shared by the threads
+/// that enrich the records at once.
+struct Enrichment {
+    headers: StringRecord,
     rooms: RoomDataSource,
     hotels: HotelDataSource,
+    coordinates: Option<CoordinatesDataSource>,
+    chains: Option<ChainDataSource>,
+    /// Versions of the rooms and hotels that are valid only for some time
+    room_versions: Versions<Room>,
+    hotel_versions: Versions<Hotel>,
+    translations: Option<(TranslationDataSource, String)>,
+    room_names: Option<RoomNameNormalizer>,
+    date_format: Option<String>,
+    key_case: KeyCase,
+    sanitizer: Sanitizer,
+    field_rules: FieldRules,
+    delimiter: char,
+    warning_rules: Option<WarningRules>,
+    placeholders: Option<Placeholders>,
+    /// Minimal similarity of the room matched fuzzily
+    fuzzy_rooms: Option<f64>,
+    candidate_room_names: Option<RoomNames>,
+}
+
+/// Enriched record of the input, or its reject, with what was done with it on the way.
+type Enriched = (AuditEntry, Result<Output, Box<Reject>>);
+
+/// Order of the records enriched by many threads at once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Order {
+    /// The same as the order of the input, the records enriched early wait for the ones before them
+    Ordered,
+    /// The order the threads are done with the records in, nothing waits
+    Unordered,
 }
 
-impl<'a> DataIntegrator<'a> {
+/// How many records of the input each of the threads enriches at once, when there's more than one.
+const BATCH_PER_THREAD: usize = 64;
+
+/// How many characters of each value of the record that's too large are kept in its reject.
+const OVERSIZED_VALUE_CHARS: usize = 64;
+
+/// Names of the rooms by their hotel and room codes, and then by their sources.
+type RoomNames = HashMap<(String, String), BTreeMap<String, String>>;
+
+impl DataIntegrator {
+    /// The data sources are only read by the integrator, so they are frozen.
     pub fn new(
         rooms: RoomDataSource,
         hotels: HotelDataSource,
-        input: DeserializeRecordsIter<'a, File, Input>,
+        headers: StringRecord,
+        input: RawInputs,
     ) -> Self {
         Self {
-            rooms,
-            hotels,
             input,
+            enrichment: Enrichment {
+                rooms: rooms.freeze(),
+                hotels: hotels.freeze(),
+                headers,
+                coordinates: None,
+                chains: None,
+                room_versions: Versions::default(),
+                hotel_versions: Versions::default(),
+                translations: None,
+                room_names: None,
+                date_format: None,
+                key_case: KeyCase::Exact,
+                sanitizer: Sanitizer::default(),
+                field_rules: FieldRules::default(),
+                delimiter: '|',
+                warning_rules: None,
+                placeholders: None,
+                fuzzy_rooms: None,
+                candidate_room_names: None,
+            },
+            audit: None,
+            usage: None,
+            duplicates: None,
+            markets: None,
+            coverage: None,
+            max_record_size: None,
+            threads: None,
+            enriched: VecDeque::new(),
+        }
+    }
+
+    /// Enrich the records by the given number of threads at once and give them in the given order.
+    /// When the rooms or hotels are looked up remotely, the records are enriched one after another,
+    /// as the caches keep only the rooms and hotels of the records that are enriched next.
+    pub fn with_threads(mut self, threads: NonZeroUsize, order: Order) -> Self {
+        self.threads = Some((threads, order)).filter(|(threads, _)| threads.get() > 1);
+        self
+    }
+
+    /// Measure how much time is spent in reading the input and in fetching the rooms and hotels.
+    pub fn with_timings(mut self) -> Self {
+        self.usage = Some(IntegratorUsage::default());
+        self
+    }
+
+    /// Enrich the records that link to the missing rooms or hotels with the placeholders instead of rejecting them.
+    pub fn with_placeholders(mut self, placeholders: Placeholders) -> Self {
+        self.enrichment.placeholders = Some(placeholders);
+        self
+    }
+
+    /// Match the records whose room is missing with the most similar room of the same hotel and source,
+    /// as long as the similarity of their keys is at least the given one.
+    pub fn with_fuzzy_rooms(mut self, min_similarity: f64) -> Self {
+        self.enrichment.fuzzy_rooms = Some(min_similarity);
+        self
+    }
+
+    /// List the names that the other sources give to the same room (the same hotel and room code) with each record.
+    /// Only the rooms in memory are listed.
+    pub fn with_candidate_room_names(mut self) -> Self {
+        let mut names = RoomNames::new();
+        for (_, room) in self.enrichment.rooms.iter() {
+            names
+                .entry((room.hotel_code.clone(), room.room_code.clone()))
+                .or_default()
+                .insert(room.source.clone(), room.room_name.clone());
+        }
+        self.enrichment.candidate_room_names = Some(names);
+        self
+    }
+
+    /// Detect the records of the input that were sent more than once and handle them by the policy.
+    pub fn with_duplicates(mut self, duplicates: Duplicates) -> Self {
+        self.duplicates = Some(duplicates);
+        self
+    }
+
+    /// Reject the records of the input that take more than the given number of bytes, e.g with a huge quoted field,
+    /// before anything else is done with them.
+    pub fn with_max_record_size(mut self, max_record_size: usize) -> Self {
+        self.max_record_size = Some(max_record_size);
+        self
+    }
+
+    /// Skip the records of the input that link to the hotels removed by the markets, they are only counted.
+    pub fn with_markets(mut self, markets: Markets) -> Self {
+        self.markets = Some(markets);
+        self
+    }
+
+    /// Save what was done with each of the records of the input in the given audit trail.
+    pub fn with_audit(mut self, audit: Audit) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Check the enriched records by the given rules and attach warnings to the suspicious ones.
+    pub fn with_warning_rules(mut self, warning_rules: WarningRules) -> Self {
+        self.enrichment.warning_rules = Some(warning_rules);
+        self
+    }
+
+    /// Delimiter of the input, it's used to show the rejected records as they were in the input.
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.enrichment.delimiter = delimiter;
+        self
+    }
+
+    /// Read the checkin and the checkout of the input in the given format (e.g %d.%m.%Y) instead of the default one.
+    pub fn with_date_format(mut self, date_format: &str) -> Self {
+        self.enrichment.date_format = Some(date_format.to_string());
+        self
+    }
+
+    /// Change the codes of the input before the rooms and hotels are looked up by them.
+    pub fn with_key_case(mut self, key_case: KeyCase) -> Self {
+        self.enrichment.key_case = key_case;
+        self
+    }
+
+    /// Clean up the whitespace in the fields of the input before they are read and the rooms and hotels are looked up.
+    pub fn with_sanitizer(mut self, sanitizer: Sanitizer) -> Self {
+        self.enrichment.sanitizer = sanitizer;
+        self
+    }
+
+    /// Check the formats of the codes of the input by the given rules, records with the invalid ones are rejected.
+    pub fn with_field_rules(mut self, field_rules: FieldRules) -> Self {
+        self.enrichment.field_rules = field_rules;
+        self
+    }
+
+    /// Present the canonical names of the rooms instead of the ones used by each of the sources.
+    pub fn with_room_name_normalizer(mut self, room_names: RoomNameNormalizer) -> Self {
+        self.enrichment.room_names = Some(room_names);
+        self
+    }
+
+    /// Present the names of the hotels and cities in the given language.
+    /// When there's no translation for the hotel, its default name and city are used.
+    pub fn with_translations(
+        mut self,
+        translations: TranslationDataSource,
+        language: &str,
+    ) -> Self {
+        self.enrichment.translations = Some((translations.freeze(), language.to_string()));
+        self
+    }
+
+    /// Enrich the output with the coordinates of the hotels.
+    /// Hotels without known coordinates are not an error, their coordinates are just left empty.
+    pub fn with_coordinates(mut self, coordinates: CoordinatesDataSource) -> Self {
+        self.enrichment.coordinates = Some(coordinates.freeze());
+        self
+    }
+
+    /// Look up the rooms and hotels by the checkin of the offer in the given versions first,
+    /// the ones in the data sources are used when none of the versions is effective on that day.
+    pub fn with_versions(
+        mut self,
+        room_versions: Versions<Room>,
+        hotel_versions: Versions<Hotel>,
+    ) -> Self {
+        self.enrichment.room_versions = room_versions;
+        self.enrichment.hotel_versions = hotel_versions;
+        self
+    }
+
+    /// Enrich the output with the chains and brands of the hotels.
+    /// Hotels that don't belong to any known chain are not an error, their chain and brand are just left empty.
+    pub fn with_chains(mut self, chains: ChainDataSource) -> Self {
+        self.enrichment.chains = Some(chains.freeze());
+        self
+    }
+}
+
+impl DataIntegrator {
+    /// Next record of the input that has to be enriched, the records that are rejected or skipped
+    /// before they are enriched are handled on the way. There's none once the input is over.
+    fn next_raw(&mut self) -> Option<Result<RawInput>> {
+        let snapshot = self.usage.map(|_| Snapshot::now());
+        let raw = loop {
+            match self.input.next() {
+                Some(Ok(raw)) => {
+                    if let Some(reject) = self.oversized(&raw) {
+                        return Some(Err(reject.into()));
+                    }
+                    if self.skip_out_of_market(&raw) {
+                        continue;
+                    }
+                    match self.duplicate(&raw) {
+                        Ok(Some(DuplicatePolicy::KeepFirst)) => continue,
+                        Ok(Some(DuplicatePolicy::RejectDuplicates)) => {
+                            return Some(Err(Reject::new(
+                                RejectReason::Duplicate,
+                                String::from("Input contains the same record more than once!"),
+                                Some(raw.line),
+                                &self.enrichment.headers,
+                                Some(&raw.record),
+                            )
+                            .into()))
+                        }
+                        Ok(_) => break raw,
+                        Err(e) => return Some(Err(e)),
+                    }
+                }
+                Some(Err(e)) => {
+                    return Some(Err(Reject::new(
+                        RejectReason::Unparsable,
+                        String::from("Input contains data that can't be deserialized!"),
+                        e.position().map(|position| position.line()),
+                        &self.enrichment.headers,
+                        None,
+                    )
+                    .into()))
+                }
+                None => return None,
+            }
+        };
+        let snapshot = match (&mut self.usage, snapshot) {
+            (Some(usage), Some(snapshot)) => {
+                usage.read += snapshot.elapsed();
+                Some(Snapshot::now())
+            }
+            _ => None,
+        };
+        if let Err(e) = self.prefetch(&raw) {
+            return Some(Err(e));
+        }
+        if let (Some(usage), Some(snapshot)) = (&mut self.usage, snapshot) {
+            usage.lookup += snapshot.elapsed();
+        }
+        Some(Ok(raw))
+    }
+
+    /// Save what was done with the enriched record in the audit trail.
+    fn audited(
+        &mut self,
+        trail: AuditEntry,
+        result: Result<Output, Box<Reject>>,
+    ) -> Result<Output> {
+        if let Some(audit) = &mut self.audit {
+            audit.write(trail.finish(result.as_ref().err().map(|reject| &**reject)))?;
+        }
+        result.map_err(|reject| (*reject).into())
+    }
+
+    /// The input is over, make sure that the whole audit trail got saved.
+    fn finish(&mut self) -> Option<Result<Output>> {
+        match &mut self.audit {
+            Some(audit) => audit.flush().err().map(Err),
+            None => None,
+        }
+    }
+
+    /// The rooms or hotels are looked up remotely, their caches keep only the ones that were prefetched last.
+    fn is_remote(&self) -> bool {
+        self.enrichment.rooms.cache_stats().is_some()
+            || self.enrichment.hotels.cache_stats().is_some()
+    }
+
+    /// Read the next batch of the input and enrich its records by the threads at once.
+    fn enrich_batch(&mut self, threads: NonZeroUsize, order: Order) {
+        let batch: Vec<Result<RawInput>> = std::iter::from_fn(|| self.next_raw())
+            .take(threads.get() * BATCH_PER_THREAD)
+            .collect();
+        let next = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+        let (enrichment, batch_ref) = (&self.enrichment, &batch);
+        thread::scope(|scope| {
+            for _ in 0..threads.get().min(batch.len()) {
+                let (next, sender) = (&next, sender.clone());
+                scope.spawn(move || loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let raw = match batch_ref.get(index) {
+                        Some(Ok(raw)) => raw,
+                        Some(Err(_)) => continue,
+                        None => break,
+                    };
+                    let mut trail = AuditEntry::new(raw.line);
+                    let result = enrichment.integrate(raw, &mut trail);
+                    if sender.send((index, trail, result)).is_err() {
+                        break;
+                    }
+                });
+            }
+        });
+        drop(sender);
+        let mut done: Vec<_> = receiver.into_iter().collect();
+        if order == Order::Ordered {
+            done.sort_by_key(|(index, _, _)| *index);
+        }
+        let mut done = done.into_iter().peekable();
+        for (index, raw_res) in batch.into_iter().enumerate() {
+            // the records enriched early wait for the ones before them, unless the order doesn't matter
+            if order == Order::Ordered {
+                while let Some((_, trail, result)) = done.next_if(|(done, _, _)| *done < index) {
+                    self.enriched.push_back(Ok((trail, result)));
+                }
+            }
+            if let Err(e) = raw_res {
+                self.enriched.push_back(Err(e));
+            }
+        }
+        self.enriched
+            .extend(done.map(|(_, trail, result)| Ok((trail, result))));
+    }
+
+    /// Statistics of the caches of the rooms and hotels, when they are looked up remotely.
+    pub fn cache_stats(&self) -> BTreeMap<String, CacheStats> {
+        [
+            ("rooms", self.enrichment.rooms.cache_stats()),
+            ("hotels", self.enrichment.hotels.cache_stats()),
+        ]
+        .iter()
+        .filter_map(|(name, stats)| stats.map(|stats| (name.to_string(), stats)))
+        .collect()
+    }
+
+    /// How many duplicates of the records of the input were found, when they are detected.
+    pub fn duplicates(&self) -> Option<usize> {
+        self.duplicates.as_ref().map(Duplicates::count)
+    }
+
+    /// How many records of the input were skipped because their hotels are out of the markets.
+    pub fn out_of_market(&self) -> Option<usize> {
+        self.markets.as_ref().map(Markets::skipped)
+    }
+
+    /// Why the merge join of the rooms fell back to all of them in memory, when it did.
+    pub fn rooms_fallback(&self) -> Option<String> {
+        self.enrichment.rooms.fallback()
+    }
+
+    /// Coverage of the input found by [DataIntegrator::prevalidate], when it was run.
+    pub fn coverage(&self) -> Option<Coverage> {
+        self.coverage
+    }
+
+    /// Time spent in reading the input and in fetching the rooms and hotels, when it's measured.
+    pub fn usage(&self) -> Option<IntegratorUsage> {
+        self.usage
+    }
+
+    /// Save whatever is still buffered in the audit trail, when the input is not read until its end.
+    pub fn flush(&mut self) -> Result<()> {
+        match &mut self.audit {
+            Some(audit) => audit.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Raw value of the column of the record, in the case of the keys.
+    fn value(&self, raw: &RawInput, column: &str) -> Option<String> {
+        self.enrichment
+            .headers
+            .iter()
+            .position(|header| header == column)
+            .and_then(|index| raw.record.get(index))
+            .map(|value| {
+                self.enrichment
+                    .key_case
+                    .apply(&self.enrichment.sanitizer.apply(value))
+            })
+    }
+
+    /// Make sure that the room and hotel the record links to can be found, when they are looked up remotely.
+    fn prefetch(&mut self, raw: &RawInput) -> Result<()> {
+        let value = |column: &str| self.value(raw, column);
+        let hotel_code = value("hotel_code");
+        let room_key = match (&hotel_code, value("room_code"), value("source")) {
+            (Some(hotel_code), Some(room_code), Some(source)) => {
+                Some(generate_room_key(hotel_code, &room_code, &source))
+            }
+            _ => None,
+        };
+        if let Some(room_key) = room_key {
+            self.enrichment.rooms.prefetch(&room_key)?;
+        }
+        if let Some(hotel_code) = hotel_code {
+            self.enrichment.hotels.prefetch(&hotel_code)?;
+        }
+        Ok(())
+    }
+
+    /// Go through the whole input and collect the rooms and hotels it links to that are not in the data sources.
+    /// The records are not enriched, so the room is checked even when the hotel is missing and the other way around.
+    /// Records that can't be read at all are skipped.
+    pub fn missing_keys(mut self) -> Result<MissingKeys> {
+        let input = std::mem::replace(&mut self.input, Box::new(std::iter::empty()));
+        self.check_references(input).map(|(_, missing)| missing)
+    }
+
+    /// First pass over another reader of the same input, it finds out how much of it links to the rooms and hotels
+    /// in the data sources before any of it is enriched. The coverage is kept for the summary.
+    pub fn prevalidate(&mut self, input: RawInputs) -> Result<Coverage> {
+        let (coverage, _) = self.check_references(input)?;
+        self.coverage = Some(coverage);
+        Ok(coverage)
+    }
+
+    /// Check the rooms and hotels of each of the records, the ones of the hotels out of the markets are left out.
+    fn check_references(&mut self, input: RawInputs) -> Result<(Coverage, MissingKeys)> {
+        let mut coverage = Coverage::default();
+        let mut missing = MissingKeys::default();
+        for raw in input {
+            let raw = match raw {
+                Ok(raw) => raw,
+                Err(_) => continue,
+            };
+            let out_of_market = match (&self.markets, self.value(&raw, "hotel_code")) {
+                (Some(markets), Some(hotel_code)) => markets.is_out_of_market(&hotel_code),
+                _ => false,
+            };
+            if out_of_market {
+                continue;
+            }
+            coverage.records += 1;
+            self.prefetch(&raw)?;
+            let (hotel_code, room_code, source) = match (
+                self.value(&raw, "hotel_code"),
+                self.value(&raw, "room_code"),
+                self.value(&raw, "source"),
+            ) {
+                (Some(hotel_code), Some(room_code), Some(source)) => {
+                    (hotel_code, room_code, source)
+                }
+                _ => continue,
+            };
+            let room_key = generate_room_key(&hotel_code, &room_code, &source);
+            // the checkin is not read yet, so any of the versions will do
+            let room_found = self.enrichment.rooms.find(&room_key).is_some()
+                || self.enrichment.room_versions.contains(&room_key);
+            let hotel_found = self.enrichment.hotels.find(&hotel_code).is_some()
+                || self.enrichment.hotel_versions.contains(&hotel_code);
+            if room_found && hotel_found {
+                coverage.covered += 1;
+            }
+            if !room_found {
+                missing.add(MissingKey {
+                    kind: MissingKind::Room,
+                    hotel_code: hotel_code.clone(),
+                    room_code,
+                    source: source.clone(),
+                });
+            }
+            if !hotel_found {
+                missing.add(MissingKey {
+                    kind: MissingKind::Hotel,
+                    hotel_code,
+                    room_code: String::new(),
+                    source,
+                });
+            }
+        }
+        coverage.missing_keys = missing.len();
+        Ok((coverage, missing))
+    }
+
+    /// Reject of the record that's too large, its values are cut short so the rejects don't balloon as well.
+    fn oversized(&self, raw: &RawInput) -> Option<Reject> {
+        let size = raw.record.as_slice().len();
+        let max_record_size = self.max_record_size.filter(|max| size > *max)?;
+        let record: StringRecord = raw
+            .record
+            .iter()
+            .map(
+                |value| match value.char_indices().nth(OVERSIZED_VALUE_CHARS) {
+                    Some((end, _)) => format!("{}...", &value[..end]),
+                    None => value.to_string(),
+                },
+            )
+            .collect();
+        Some(Reject::new(
+            RejectReason::RecordTooLarge,
+            format!(
+                "Record is too large: {} bytes, at most {} are allowed!",
+                size, max_record_size
+            ),
+            Some(raw.line),
+            &self.enrichment.headers,
+            Some(&record),
+        ))
+    }
+
+    /// Whether the record links to a hotel of another market, so it's skipped.
+    fn skip_out_of_market(&mut self, raw: &RawInput) -> bool {
+        match (self.value(raw, "hotel_code"), &mut self.markets) {
+            (Some(hotel_code), Some(markets)) => markets.skip(&hotel_code),
+            _ => false,
+        }
+    }
+
+    /// Policy of the duplicates that applies to the record, there's none when it's not a duplicate.
+    fn duplicate(&mut self, raw: &RawInput) -> Result<Option<DuplicatePolicy>> {
+        let duplicates = match &mut self.duplicates {
+            Some(duplicates) => duplicates,
+            None => return Ok(None),
+        };
+        // records that differ only in the whitespace are the same once they are sanitized
+        let duplicate = if self.enrichment.sanitizer.is_enabled() {
+            duplicates.check(
+                &self.enrichment.headers,
+                &self.enrichment.sanitizer.record(&raw.record),
+            )?
+        } else {
+            duplicates.check(&self.enrichment.headers, &raw.record)?
+        };
+        Ok(Some(duplicates.policy).filter(|_| duplicate))
+    }
+}
+
+impl Enrichment {
+    /// Enrich the single record of the input, the trail keeps what was done with it on the way.
+    fn integrate(&self, raw: &RawInput, trail: &mut AuditEntry) -> Result<Output, Box<Reject>> {
+        let reject = |reason, message| {
+            Box::new(Reject::new(
+                reason,
+                message,
+                Some(raw.line),
+                &self.headers,
+                Some(&raw.record),
+            ))
+        };
+        let record = if self.sanitizer.is_enabled() {
+            trail.transforms.push("sanitize");
+            self.sanitizer.record(&raw.record)
+        } else {
+            raw.record.clone()
+        };
+        let record = match &self.date_format {
+            Some(date_format) => {
+                trail.transforms.push("date_format");
+                reformat_dates(&self.headers, &record, date_format)
+            }
+            None => record,
+        };
+        let item: Input = record
+            .deserialize(Some(&self.headers))
+            .map_err(|e| Box::new(self.unparsable(raw, e)))?;
+        if let Err(violation) = self.field_rules.check_input(&item) {
+            return Err(Box::new(self.invalid_field(raw, violation)));
+        }
+        if self.key_case != KeyCase::Exact {
+            trail.transforms.push("key_case");
+        }
+        let room_key = generate_room_key(
+            &self.key_case.apply(&item.hotel_code),
+            &self.key_case.apply(&item.room_code),
+            &self.key_case.apply(&item.source),
+        );
+        trail.room_key = Some(room_key.clone());
+        let mut match_confidence = self.fuzzy_rooms.map(|_| 1.0);
+        let version = self.room_versions.find(&room_key, item.checkin);
+        let found = version.or_else(|| self.rooms.find(&room_key));
+        let nearest = match (found, self.fuzzy_rooms) {
+            (None, Some(min_similarity)) => nearest_room(
+                &self.rooms,
+                &self.key_case.apply(&item.hotel_code),
+                &self.key_case.apply(&item.room_code),
+                &self.key_case.apply(&item.source),
+                min_similarity,
+            ),
+            _ => None,
+        };
+        if let Some((similarity, _)) = nearest {
+            trail.transforms.push("fuzzy_room");
+            match_confidence = Some(similarity);
+        }
+        if version.is_some() {
+            trail.transforms.push("room_version");
+        }
+        let room = match (found.or(nearest.map(|(_, room)| room)), &self.placeholders) {
+            (Some(room), _) => Cow::Borrowed(room),
+            (None, Some(placeholders)) => Cow::Owned(placeholders.room(&item)),
+            (None, None) => {
+                return Err(reject(
+                    RejectReason::MissingRoom,
+                    format!("Input links to a non existent room: {:#?}", item),
+                ))
+            }
+        };
+        // placeholder of the room isn't matched at all
+        if let Cow::Owned(_) = room {
+            match_confidence = None;
+        }
+        let hotel_key = self.key_case.apply(&item.hotel_code);
+        trail.hotel = Some(hotel_key.clone());
+        let version = self.hotel_versions.find(&hotel_key, item.checkin);
+        if version.is_some() {
+            trail.transforms.push("hotel_version");
+        }
+        let hotel = match (
+            version.or_else(|| self.hotels.find(&hotel_key)),
+            &self.placeholders,
+        ) {
+            (Some(hotel), _) => Cow::Borrowed(hotel),
+            (None, Some(placeholders)) => Cow::Owned(placeholders.hotel(&item)),
+            (None, None) => {
+                return Err(reject(
+                    RejectReason::MissingHotel,
+                    format!("Input links to a non existent hotel: {:#?}", item),
+                ))
+            }
+        };
+        let match_status = self.placeholders.as_ref().map(|_| {
+            MatchStatus::new(
+                matches!(room, Cow::Borrowed(_)),
+                matches!(hotel, Cow::Borrowed(_)),
+            )
+        });
+        if !matches!(match_status, None | Some(MatchStatus::Matched)) {
+            trail.transforms.push("placeholder");
+        }
+        let translation = self
+            .translations
+            .as_ref()
+            .and_then(|(translations, language)| {
+                translations.find(&generate_translation_key(&hotel_key, language))
+            });
+        if translation.is_some() {
+            trail.transforms.push("translation");
+        }
+        let hotel_name = match translation {
+            Some(translation) if !translation.name.is_empty() => translation.name.clone(),
+            _ => hotel.name.clone(),
+        };
+        let city_name = match translation {
+            Some(translation) if !translation.city.is_empty() => translation.city.clone(),
+            _ => hotel.city.clone(),
+        };
+        let candidate_room_names = self
+            .candidate_room_names
+            .as_ref()
+            .and_then(|names| names.get(&(room.hotel_code.clone(), room.room_code.clone())))
+            .map(|names| {
+                names
+                    .iter()
+                    .filter(|(source, _)| **source != room.source)
+                    .map(|(_, name)| name.clone())
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .collect()
+            })
+            .unwrap_or_default();
+        let room_name = match &self.room_names {
+            Some(room_names) => room_names.normalize(&item.source, &room.room_name),
+            None => room.room_name.clone(),
+        };
+        if room_name != room.room_name {
+            trail.transforms.push("room_name");
+        }
+        let coordinates = self
+            .coordinates
+            .as_ref()
+            .and_then(|coordinates| coordinates.find(&hotel_key));
+        if coordinates.is_some() {
+            trail.transforms.push("coordinates");
+        }
+        let chain = self
+            .chains
+            .as_ref()
+            .and_then(|chains| chains.find(&hotel_key));
+        if chain.is_some() {
+            trail.transforms.push("chain");
+        }
+        // number of adults and children combined, it's checked by the field rules that it doesn't overflow
+        let pax = item.adults + item.children;
+        // price per person
+        let price = item.price / pax as Price;
+        // the checkout of the input is used as it is, the conflict with its nights is just reported
+        let checkout = item.checkout.unwrap_or_else(|| {
+            item.checkin + Duration::days(item.nights.map_or(1, |nights| nights.get() as i64))
+        });
+        let input_warnings = self
+            .warning_rules
+            .as_ref()
+            .map(|warning_rules| warning_rules.check_input(&item, raw.line))
+            .unwrap_or_default();
+        // combine everything together
+        let mut output = Output {
+            room_type_meal: format!("{} {}", item.room_type, item.meal),
+            hotel_code: item.hotel_code,
+            meal: item.meal,
+            room_code: room.room_code.clone(),
+            source: item.source,
+            hotel_name,
+            city_name,
+            city_code: item.city_code,
+            hotel_category: hotel.category,
+            pax,
+            adults: item.adults,
+            children: item.children,
+            room_name,
+            checkin: item.checkin,
+            checkout,
+            price,
+            latitude: coordinates.map(|coordinates| coordinates.latitude),
+            longitude: coordinates.map(|coordinates| coordinates.longitude),
+            ranking: None,
+            match_status,
+            match_confidence,
+            candidate_room_names,
+            chain_code: chain.map(|chain| chain.chain_code.clone()),
+            brand_name: chain.map(|chain| chain.brand_name.clone()),
+            warnings: Vec::new(),
+        };
+        if let Some(warning_rules) = &self.warning_rules {
+            output.warnings = warning_rules.check(&output, raw.line);
+            output.warnings.extend(input_warnings);
+        }
+        Ok(output)
+    }
+
+    /// Reject of the record with a field in the invalid format, pointing out the offending value.
+    fn invalid_field(&self, raw: &RawInput, violation: FieldViolation) -> Reject {
+        let invalid = Reject::new(
+            RejectReason::InvalidField,
+            format!("Input contains a field in invalid format: {}!", violation),
+            Some(raw.line),
+            &self.headers,
+            Some(&raw.record),
+        );
+        match self
+            .headers
+            .iter()
+            .position(|header| header == violation.field)
+        {
+            Some(index) => invalid.with_snippet(Snippet::new(
+                &self.headers,
+                &raw.record,
+                index,
+                self.delimiter,
+                format!("expected {}", violation.expected),
+            )),
+            None => invalid,
+        }
+    }
+
+    /// Reject of the record that can't be deserialized into the Input, pointing out the offending value.
+    fn unparsable(&self, raw: &RawInput, e: csv::Error) -> Reject {
+        let unparsable = Reject::new(
+            RejectReason::Unparsable,
+            String::from("Input contains data that can't be deserialized!"),
+            Some(raw.line),
+            &self.headers,
+            Some(&raw.record),
+        );
+        let err = match e.kind() {
+            csv::ErrorKind::Deserialize { err, .. } => err,
+            _ => return unparsable,
+        };
+        // csv doesn't know which field failed with a custom error, e.g of the custom_date,
+        // but these errors always say what value they got
+        let index = err
+            .field()
+            .map(|index| index as usize)
+            .or_else(|| match err.kind() {
+                DeserializeErrorKind::Message(message) => raw
+                    .record
+                    .iter()
+                    .position(|value| message.ends_with(&format!("got '{}'", value))),
+                _ => None,
+            });
+        match index {
+            Some(index) => {
+                let value = raw.record.get(index).unwrap_or("");
+                let hint = match err.kind() {
+                    DeserializeErrorKind::Message(message) => message.clone(),
+                    kind => format!("{}, got '{}'", kind, value),
+                };
+                unparsable.with_snippet(Snippet::new(
+                    &self.headers,
+                    &raw.record,
+                    index,
+                    self.delimiter,
+                    hint,
+                ))
+            }
+            None => unparsable,
         }
     }
 }
@@ -34,57 +900,448 @@ impl<'a> DataIntegrator<'a> {
 /// In enriches the input data with additional information about room and hotel.
 /// It throws an error if there's no room or hotel found for the specified code for each of them in the input data.
 /// Then it calculates the sum of adults and children, date of the checkout, price per person and combines everything into final object.
-impl<'a> Iterator for DataIntegrator<'a> {
+impl Iterator for DataIntegrator {
     type Item = Result<Output>;
     fn next(&mut self) -> Option<Self::Item> {
-        match self.input.next().transpose() {
-            Ok(Some(item)) => {
-                let room_key = generate_room_key(&item.hotel_code, &item.room_code, &item.source);
-                let room = match self.rooms.find(&room_key) {
-                    Some(room) => room,
-                    None => {
-                        return Some(Err(anyhow!(format!(
-                            "Input links to a non existent room: {:#?}",
-                            item
-                        ))))
+        let (threads, order) = match self.threads {
+            Some(threads) if !self.is_remote() => threads,
+            _ => {
+                return match self.next_raw() {
+                    Some(Ok(raw)) => {
+                        let mut trail = AuditEntry::new(raw.line);
+                        let result = self.enrichment.integrate(&raw, &mut trail);
+                        Some(self.audited(trail, result))
                     }
-                };
-                let hotel = match self.hotels.find(&item.hotel_code) {
-                    Some(hotel) => hotel,
-                    None => {
-                        return Some(Err(anyhow!(format!(
-                            "Input links to a non existent hotel: {:#?}",
-                            item
-                        ))))
-                    }
-                };
-                // number of adults and children combined
-                let pax = item.adults + item.children;
-                // price per person
-                let price = item.price / pax as Price;
-                // combine everything together
-                let output = Output {
-                    room_type_meal: format!("{} {}", item.room_type, item.meal),
-                    room_code: room.room_code.clone(),
-                    source: item.source,
-                    hotel_name: hotel.name.clone(),
-                    city_name: hotel.city.clone(),
-                    city_code: item.city_code,
-                    hotel_category: hotel.category,
-                    pax,
-                    adults: item.adults,
-                    children: item.children,
-                    room_name: room.room_name.clone(),
-                    checkin: item.checkin,
-                    checkout: item.checkin + Duration::days(1),
-                    price,
-                };
-                Some(Ok(output))
+                    Some(Err(e)) => Some(Err(e)),
+                    None => self.finish(),
+                }
+            }
+        };
+        if self.enriched.is_empty() {
+            self.enrich_batch(threads, order);
+        }
+        // the records are audited once they are given, so the ones left behind by the shutdown aren't
+        match self.enriched.pop_front() {
+            Some(Ok((trail, result))) => Some(self.audited(trail, result)),
+            Some(Err(e)) => Some(Err(e)),
+            None => self.finish(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use chrono::NaiveDate;
+    use csv::ReaderBuilder;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::data::{csv_input, hotels_reader_for, rooms_reader, DataSource};
+
+    /// Integrator of the test input, with the room of the MARR offers and the hotel of the IHG offers missing.
+    fn partially_mapped() -> Result<DataIntegrator> {
+        let mut rooms: RoomDataSource = DataSource::new();
+        rooms.import_from(Path::new("test_data/room_names.csv"), rooms_reader)?;
+        rooms.remove(&generate_room_key("BER00003", "BER848", "MARR"));
+        let mut hotels: HotelDataSource = DataSource::new();
+        hotels.import_from(
+            Path::new("test_data/hotels.json"),
+            hotels_reader_for("hotels.json"),
+        )?;
+        hotels.remove(&String::from("BER00002"));
+        let (headers, input) = csv_input(
+            ReaderBuilder::new()
+                .delimiter(b'|')
+                .from_path("test_data/input.csv")?,
+        )?;
+        Ok(DataIntegrator::new(rooms, hotels, headers, input))
+    }
+
+    #[test]
+    fn collect_missing_keys() -> Result<()> {
+        let missing = partially_mapped()?.missing_keys()?;
+        let mut saved = Vec::new();
+        missing.save(&mut saved)?;
+        assert_eq!(
+            String::from_utf8(saved)?,
+            "kind,hotel_code,room_code,source,records\n\
+             room,BER00003,BER848,MARR,3\n\
+             hotel,BER00002,,IHG,3\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn prevalidate_coverage() -> Result<()> {
+        let mut integrator = partially_mapped()?;
+        let (_, input) = csv_input(
+            ReaderBuilder::new()
+                .delimiter(b'|')
+                .from_path("test_data/input.csv")?,
+        )?;
+        let coverage = integrator.prevalidate(input)?;
+        assert_eq!(
+            coverage,
+            Coverage {
+                records: 6,
+                covered: 0,
+                missing_keys: 2,
             }
-            Err(_) => Some(Err(anyhow!(
-                "Input contains data that can't be deserialized!"
-            ))),
-            Ok(None) => None,
+        );
+        assert_eq!(integrator.coverage(), Some(coverage));
+        assert_eq!(
+            coverage.to_string(),
+            "0.0% (0 of 6 records, 2 missing keys)"
+        );
+        // the input itself is still there to be enriched
+        assert_eq!(integrator.count(), 6);
+        Ok(())
+    }
+
+    #[test]
+    fn enrich_with_placeholders() -> Result<()> {
+        assert_eq!(partially_mapped()?.filter(Result::is_ok).count(), 0);
+
+        let outputs = partially_mapped()?
+            .with_placeholders(Placeholders {
+                room_name: String::from("UNKNOWN ROOM"),
+                hotel_name: String::from("UNKNOWN HOTEL"),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(outputs.len(), 6);
+        assert_eq!(outputs[0].hotel_name, "UNKNOWN HOTEL");
+        assert_eq!(outputs[0].room_name, "Einzelzimmer");
+        assert_eq!(outputs[0].match_status, Some(MatchStatus::MissingHotel));
+        assert_eq!(outputs[3].room_name, "UNKNOWN ROOM");
+        assert_eq!(outputs[3].room_code, "BER848");
+        assert_ne!(outputs[3].hotel_name, "UNKNOWN HOTEL");
+        assert_eq!(outputs[3].match_status, Some(MatchStatus::MissingRoom));
+        Ok(())
+    }
+
+    #[test]
+    fn list_candidate_room_names() -> Result<()> {
+        let outputs = integrator(Path::new("test_data/input.csv"))?
+            .with_candidate_room_names()
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(outputs[0].candidate_room_names, vec!["Standard"]);
+        assert_eq!(outputs[3].candidate_room_names, vec!["SINGLE DELUXE"]);
+        Ok(())
+    }
+
+    /// Integrator of the given input with all of the rooms and hotels.
+    fn integrator(input: &Path) -> Result<DataIntegrator> {
+        let mut rooms: RoomDataSource = DataSource::new();
+        rooms.import_from(Path::new("test_data/room_names.csv"), rooms_reader)?;
+        let mut hotels: HotelDataSource = DataSource::new();
+        hotels.import_from(
+            Path::new("test_data/hotels.json"),
+            hotels_reader_for("hotels.json"),
+        )?;
+        let (headers, input) = csv_input(ReaderBuilder::new().delimiter(b'|').from_path(input)?)?;
+        Ok(DataIntegrator::new(rooms, hotels, headers, input))
+    }
+
+    /// Input of the given records, saved in the given directory.
+    fn input(dir: &tempfile::TempDir, records: &str) -> Result<std::path::PathBuf> {
+        let path = dir.path().join("input.csv");
+        std::fs::write(
+            &path,
+            format!(
+                "city_code|hotel_code|room_type|room_code|meal|checkin|adults|children|price|source\n{}",
+                records
+            ),
+        )?;
+        Ok(path)
+    }
+
+    #[test]
+    fn sanitize_padded_codes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = input(
+            &dir,
+            "BER|BER00002 |EZ|\tBER898|F|20180721|1|0|85.50| IHG\n",
+        )?;
+        assert!(integrator(&path)?.all(|output| output.is_err()));
+
+        let outputs = integrator(&path)?
+            .with_sanitizer(Sanitizer::from_names("trim").unwrap())
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].hotel_code, "BER00002");
+        assert_eq!(outputs[0].room_code, "BER898");
+        Ok(())
+    }
+
+    #[test]
+    fn reject_invalid_fields() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = input(
+            &dir,
+            "BER|BER00002|EZ|BER898|F|20180721|1|0|-85.50|IHG\n\
+             BER|ber00002|EZ|BER898|F|20180721|1|0|85.50|IHG\n",
+        )?;
+        let rejects: Vec<Reject> = integrator(&path)?
+            .with_field_rules(FieldRules::new(Some(r"[A-Z]{3}\d{5}"), None, None)?)
+            .filter_map(|output| output.err())
+            .map(|e| e.downcast::<Reject>().expect("It should be a reject"))
+            .collect();
+        assert_eq!(rejects.len(), 2);
+        assert_eq!(rejects[0].reason, RejectReason::InvalidField);
+        assert_eq!(
+            rejects[0].message,
+            "Input contains a field in invalid format: \
+             price '-85.5' is invalid, expected a finite, non-negative number!"
+        );
+        let snippet = rejects[1]
+            .snippet
+            .as_ref()
+            .expect("There should be a snippet");
+        assert_eq!(snippet.column, "hotel_code");
+        assert_eq!((snippet.start, snippet.len), (4, 8));
+        Ok(())
+    }
+
+    #[test]
+    fn use_checkout_of_input() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("input.csv");
+        std::fs::write(
+            &path,
+            "city_code|hotel_code|room_type|room_code|meal|checkin|adults|children|price|source|nights|checkout\n\
+             BER|BER00002|EZ|BER898|F|20180721|1|0|85.50|IHG||20180724\n\
+             BER|BER00002|EZ|BER898|F|20180721|1|0|85.50|IHG|2|20180724\n\
+             BER|BER00002|EZ|BER898|F|20180721|1|0|85.50|IHG|2|\n\
+             BER|BER00002|EZ|BER898|F|20180721|1|0|85.50|IHG||20180721\n",
+        )?;
+        let outputs: Vec<_> = integrator(&path)?
+            .with_warning_rules(WarningRules {
+                today: NaiveDate::from_ymd(2018, 7, 1),
+                max_price: 500.0,
+            })
+            .collect();
+        let output = |index: usize| outputs[index].as_ref().expect("It should be enriched");
+        assert_eq!(output(0).checkout, NaiveDate::from_ymd(2018, 7, 24));
+        assert!(output(0).warnings.is_empty());
+        // the checkout is kept when it isn't as many nights after the checkin
+        assert_eq!(output(1).checkout, NaiveDate::from_ymd(2018, 7, 24));
+        assert_eq!(
+            output(1)
+                .warnings
+                .iter()
+                .map(|warning| warning.kind.code())
+                .collect::<Vec<_>>(),
+            vec!["checkout_conflict"]
+        );
+        assert_eq!(output(2).checkout, NaiveDate::from_ymd(2018, 7, 23));
+
+        let reject = outputs[3]
+            .as_ref()
+            .expect_err("This should fail")
+            .downcast_ref::<Reject>()
+            .expect("It should be a reject");
+        assert_eq!(reject.reason, RejectReason::InvalidField);
+        assert_eq!(
+            reject.message,
+            "Input contains a field in invalid format: \
+             checkout '2018-07-21' is invalid, expected a date after the checkin 2018-07-21!"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn handle_duplicates() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = input(
+            &dir,
+            "BER|BER00002|EZ|BER898|F|20180721|1|0|85.50|IHG\n\
+             BER|BER00002|EZ|BER898|F|20180721|1|0|85.50|IHG\n\
+             BER|BER00002|EZ|BER898|F|20180722|1|0|85.50|IHG\n",
+        )?;
+        let handle = |policy, columns: &[&str]| -> Result<(Vec<bool>, Option<usize>)> {
+            let columns = columns.iter().map(|column| column.to_string()).collect();
+            let mut integrator =
+                integrator(&path)?.with_duplicates(Duplicates::new(policy, columns, 1));
+            let outputs = integrator.by_ref().map(|output| output.is_ok()).collect();
+            Ok((outputs, integrator.duplicates()))
+        };
+        assert_eq!(
+            handle(DuplicatePolicy::KeepFirst, &[])?,
+            (vec![true, true], Some(1))
+        );
+        assert_eq!(
+            handle(DuplicatePolicy::KeepAll, &[])?,
+            (vec![true, true, true], Some(1))
+        );
+        assert_eq!(
+            handle(
+                DuplicatePolicy::RejectDuplicates,
+                &["hotel_code", "room_code"]
+            )?,
+            (vec![true, false, false], Some(2))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn pick_effective_versions() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = input(
+            &dir,
+            "BER|BER00002|EZ|BER898|F|20180721|1|0|85.50|IHG\n\
+             BER|BER00002|EZ|BER898|F|20180722|1|0|78.00|IHG\n",
+        )?;
+        let mut room_versions = Versions::default();
+        room_versions.split(vec![(
+            generate_room_key("BER00002", "BER898", "IHG"),
+            Room {
+                hotel_code: String::from("BER00002"),
+                source: String::from("IHG"),
+                room_name: String::from("Single Classic"),
+                room_code: String::from("BER898"),
+                valid_from: None,
+                valid_to: Some(NaiveDate::from_ymd(2018, 7, 21)),
+            },
+        )]);
+        let outputs = integrator(&path)?
+            .with_versions(room_versions, Versions::default())
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(outputs[0].room_name, "Single Classic");
+        // the room in the data source is used once the version is not effective
+        assert_eq!(outputs[1].room_name, "Einzelzimmer");
+        Ok(())
+    }
+
+    #[test]
+    fn reject_oversized_records() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = input(
+            &dir,
+            &format!(
+                "BER|BER00002|\"{}\"|BER898|F|20180721|1|0|85.50|IHG\n\
+                 BER|BER00002|EZ|BER898|F|20180722|1|0|85.50|IHG\n",
+                "EZ ".repeat(100)
+            ),
+        )?;
+        let outputs: Vec<_> = integrator(&path)?.with_max_record_size(256).collect();
+        assert!(outputs[1].is_ok());
+        let reject = outputs[0]
+            .as_ref()
+            .expect_err("This should fail")
+            .downcast_ref::<Reject>()
+            .expect("It should be a reject");
+        assert_eq!(reject.reason, RejectReason::RecordTooLarge);
+        assert_eq!(
+            reject.message,
+            "Record is too large: 336 bytes, at most 256 are allowed!"
+        );
+        assert_eq!(reject.value("room_type").len(), 67);
+        Ok(())
+    }
+
+    #[test]
+    fn skip_out_of_market_hotels() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = input(
+            &dir,
+            "BER|BER00002|EZ|BER898|F|20180721|1|0|85.50|IHG\n\
+             BER|BER00002|EZ|BER898|F|20180722|1|0|78.00|IHG\n\
+             BER|BER00009|EZ|BER898|F|20180722|1|0|78.00|IHG\n",
+        )?;
+        let mut hotels: HotelDataSource = DataSource::new();
+        hotels.import_from(
+            Path::new("test_data/hotels.json"),
+            hotels_reader_for("hotels.json"),
+        )?;
+        let mut markets = Markets::new(&[String::from("AT")]);
+        markets.filter_hotels(&mut hotels);
+        let mut integrator = integrator(&path)?.with_markets(markets);
+        let outputs: Vec<_> = integrator.by_ref().collect();
+        // the hotel that's missing altogether is still rejected
+        assert_eq!(outputs.len(), 1);
+        assert!(outputs[0].is_err());
+        assert_eq!(integrator.out_of_market(), Some(2));
+        Ok(())
+    }
+
+    #[test]
+    fn enrich_by_threads() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let records: String = (0..500)
+            .map(|index| match index % 50 {
+                // can't be read at all, so it's rejected before it's enriched
+                7 => String::from("BER|BER00002\n"),
+                13 => format!("BER|BER00002|EZ|BER000|F|20180721|1|0|{}|IHG\n", index),
+                _ => format!("BER|BER00002|EZ|BER898|F|20180721|1|0|{}|IHG\n", index),
+            })
+            .collect();
+        let path = input(&dir, &records)?;
+        let summarize = |integrator: DataIntegrator| -> Vec<Result<u32, Option<u64>>> {
+            integrator
+                .map(|output_res| match output_res {
+                    Ok(output) => Ok(output.price as u32),
+                    Err(e) => Err(e.downcast::<Reject>().expect("It should be a reject").line),
+                })
+                .collect()
+        };
+        let sequential = summarize(integrator(&path)?);
+        assert_eq!(sequential.len(), 500);
+        let threads = NonZeroUsize::new(4).unwrap();
+        assert_eq!(
+            summarize(integrator(&path)?.with_threads(threads, Order::Ordered)),
+            sequential
+        );
+        let mut unordered = summarize(integrator(&path)?.with_threads(threads, Order::Unordered));
+        let mut expected = sequential;
+        unordered.sort();
+        expected.sort();
+        assert_eq!(unordered, expected);
+        Ok(())
+    }
+
+    /// Record of the input that's most of the time valid, but any of its fields might be garbage.
+    fn record() -> impl Strategy<Value = Vec<String>> {
+        [
+            "BER",
+            "BER0000[0-4]",
+            "[A-Z]{2}",
+            "BER8[49][0-9]",
+            "[A-Z]",
+            "2018072[0-9]",
+            "[0-9]{1,3}",
+            "[0-9]{1,3}",
+            "-?[0-9]{1,4}\\.[0-9]{2}",
+            "IHG|MARR|DOTW|GTA",
+        ]
+        .iter()
+        .map(|valid| prop_oneof![4 => *valid, 1 => "[A-Z0-9\".|; -]{0,6}"])
+        .collect::<Vec<_>>()
+    }
+
+    proptest! {
+        // malformed records of the production feeds have to end up as rejects, never as panics
+        #[test]
+        fn integrate_arbitrary_input(records in vec(record(), 0..8)) {
+            let dir = tempfile::tempdir().unwrap();
+            let records: String = records
+                .iter()
+                .map(|record| format!("{}\n", record.join("|")))
+                .collect();
+            let path = input(&dir, &records).unwrap();
+            integrator(&path).unwrap().for_each(drop);
+            integrator(&path)
+                .unwrap()
+                .with_fuzzy_rooms(0.5)
+                .with_candidate_room_names()
+                .with_placeholders(Placeholders {
+                    room_name: String::from("UNKNOWN ROOM"),
+                    hotel_name: String::from("UNKNOWN HOTEL"),
+                })
+                .for_each(drop);
+            let _ = integrator(&path).unwrap().missing_keys();
         }
     }
 }
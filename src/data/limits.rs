@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::data::masking::{text_value, TEXT_COLUMNS};
+use crate::data::Output;
+
+/// What's done with the value that's longer than the limit of its column.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Overflow {
+    /// Characters past the limit are cut off
+    #[default]
+    Truncate,
+    /// The whole value is replaced with the redaction
+    Redact,
+}
+
+/// Limit of one of the text columns.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColumnLimit {
+    /// How many characters the value can have at most, there's no limit when it's not given
+    pub max_length: Option<usize>,
+    pub overflow: Overflow,
+    /// Text that replaces the redacted values, they are emptied by default
+    pub redaction: String,
+    /// Whether the non-ASCII characters are replaced with their ASCII look-alikes before the value is limited,
+    /// e.g `Zürich` becomes `Zurich`, the characters without any become `?`
+    pub transliterate: bool,
+}
+
+/// Limits of the saved values for the downstream databases with fixed-width columns, which fail the whole load
+/// on a single value that doesn't fit. They are given in the config file by the columns, e.g
+/// ```toml
+/// [limits.legacy]
+/// room_name = { max_length = 100, transliterate = true }
+/// hotel_name = { max_length = 60, overflow = "redact", redaction = "-" }
+/// ```
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct Limits {
+    columns: BTreeMap<String, ColumnLimit>,
+    /// How many values of each of the columns were truncated or redacted
+    #[serde(skip)]
+    cut: BTreeMap<String, usize>,
+}
+
+impl Limits {
+    pub fn validate(&self) -> Result<()> {
+        for (column, limit) in &self.columns {
+            if !TEXT_COLUMNS.contains(&column.as_str()) {
+                return Err(anyhow!(
+                    "Column {} can't be limited, expected one of: {}!",
+                    column,
+                    TEXT_COLUMNS.join(", ")
+                ));
+            }
+            match limit.max_length {
+                Some(0) => return Err(anyhow!("Max length of the {} has to be positive!", column)),
+                Some(max_length) if limit.redaction.chars().count() > max_length => {
+                    return Err(anyhow!(
+                        "Redaction of the {} is longer than its max length {}!",
+                        column,
+                        max_length
+                    ))
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Record with the values transliterated and cut to the limits of their columns.
+    pub fn apply(&mut self, mut output: Output) -> Output {
+        for (column, limit) in &self.columns {
+            let value = match text_value(&mut output, column) {
+                Some(value) => value,
+                None => continue,
+            };
+            if limit.transliterate && !value.is_ascii() {
+                *value = transliterated(value);
+            }
+            let max_length = match limit.max_length {
+                Some(max_length) => max_length,
+                None => continue,
+            };
+            if let Some((end, _)) = value.char_indices().nth(max_length) {
+                match limit.overflow {
+                    Overflow::Truncate => value.truncate(end),
+                    Overflow::Redact => *value = limit.redaction.clone(),
+                }
+                *self.cut.entry(column.clone()).or_default() += 1;
+            }
+        }
+        output
+    }
+
+    /// How many values of each of the columns were cut to their limits.
+    pub fn finish(self) -> BTreeMap<String, usize> {
+        self.cut
+    }
+}
+
+/// The value with the accents and ligatures spelled in ASCII.
+fn transliterated(value: &str) -> String {
+    let mut ascii = String::with_capacity(value.len());
+    for c in value.nfkd().filter(|c| !is_combining_mark(*c)) {
+        match c {
+            c if c.is_ascii() => ascii.push(c),
+            'ß' => ascii.push_str("ss"),
+            'Æ' => ascii.push_str("AE"),
+            'æ' => ascii.push_str("ae"),
+            'Œ' => ascii.push_str("OE"),
+            'œ' => ascii.push_str("oe"),
+            'Ø' => ascii.push('O'),
+            'ø' => ascii.push('o'),
+            'Ł' => ascii.push('L'),
+            'ł' => ascii.push('l'),
+            'Đ' => ascii.push('D'),
+            'đ' => ascii.push('d'),
+            'Þ' => ascii.push_str("TH"),
+            'þ' => ascii.push_str("th"),
+            _ => ascii.push('?'),
+        }
+    }
+    ascii
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn limits(toml: &str) -> Result<Limits> {
+        let limits: Limits = toml::from_str(toml)?;
+        limits.validate()?;
+        Ok(limits)
+    }
+
+    #[test]
+    fn limit_values() -> Result<()> {
+        let mut limits = limits(
+            r#"
+            room_name = { max_length = 12, transliterate = true }
+            hotel_name = { max_length = 10, overflow = "redact", redaction = "-" }
+            chain_code = { max_length = 2 }
+            "#,
+        )?;
+        let limited = limits.apply(
+            testing::output()
+                .room_name("Großes Doppelzimmer")
+                .hotel_name("Hôtel Œil")
+                .build(),
+        );
+        assert_eq!(limited.room_name, "Grosses Dopp");
+        // the names of the hotels fit and they aren't transliterated
+        assert_eq!(limited.hotel_name, "Hôtel Œil");
+        assert_eq!(limited.chain_code, None);
+
+        let limited = limits.apply(
+            testing::output()
+                .room_name("Suite")
+                .hotel_name("Crowne Plaza Berlin City Centre")
+                .build(),
+        );
+        assert_eq!(limited.room_name, "Suite");
+        assert_eq!(limited.hotel_name, "-");
+        assert_eq!(
+            limits.finish(),
+            vec![
+                (String::from("hotel_name"), 1),
+                (String::from("room_name"), 1)
+            ]
+            .into_iter()
+            .collect::<BTreeMap<_, _>>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn transliterate_values() {
+        assert_eq!(transliterated("Zürich Straße"), "Zurich Strasse");
+        assert_eq!(transliterated("Łódź Økern"), "Lodz Okern");
+        assert_eq!(transliterated("ﬁve ½"), "five 1?2");
+        assert_eq!(transliterated("東京"), "??");
+    }
+
+    #[test]
+    fn validate_limits() {
+        assert!(limits("price = { max_length = 5 }")
+            .unwrap_err()
+            .to_string()
+            .starts_with("Column price can't be limited"));
+        assert_eq!(
+            limits("room_name = { max_length = 0 }")
+                .unwrap_err()
+                .to_string(),
+            "Max length of the room_name has to be positive!"
+        );
+        assert_eq!(
+            limits(r#"room_name = { max_length = 2, overflow = "redact", redaction = "n/a" }"#)
+                .unwrap_err()
+                .to_string(),
+            "Redaction of the room_name is longer than its max length 2!"
+        );
+        assert!(limits("room_name = { length = 2 }").is_err());
+    }
+}
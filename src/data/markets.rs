@@ -0,0 +1,141 @@
+use std::collections::HashSet;
+
+use crate::data::entities::{Hotel, Room};
+use crate::data::versions::Versions;
+use crate::data::HotelDataSource;
+
+/// Countries the run is limited to, e.g `DE,AT` for a run of the DACH market.
+/// Hotels of the other countries are dropped right after they are loaded, with their rooms,
+/// and the records of the input that link to them are skipped instead of being rejected as missing.
+#[derive(Debug, Default, Clone)]
+pub struct Markets {
+    countries: HashSet<String>,
+    out_of_market: HashSet<String>,
+    skipped: usize,
+}
+
+impl Markets {
+    pub fn new(countries: &[String]) -> Self {
+        Self {
+            countries: countries
+                .iter()
+                .map(|country| country.trim().to_uppercase())
+                .collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Remove the hotels of the other countries from the data source, their ids are remembered.
+    pub fn filter_hotels(&mut self, hotels: &mut HotelDataSource) {
+        let out_of_market: Vec<String> = hotels
+            .iter()
+            .filter(|(_, hotel)| !self.countries.contains(&hotel.country_code.to_uppercase()))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &out_of_market {
+            hotels.remove(id);
+        }
+        self.out_of_market.extend(out_of_market);
+    }
+
+    /// Remove the dated versions of the hotels of the other countries, their ids are remembered as well.
+    pub fn filter_hotel_versions(&mut self, versions: &mut Versions<Hotel>) {
+        let (countries, out_of_market) = (&self.countries, &mut self.out_of_market);
+        versions.retain(|hotel| {
+            let keep = countries.contains(&hotel.country_code.to_uppercase());
+            if !keep {
+                out_of_market.insert(hotel.id.clone());
+            }
+            keep
+        });
+    }
+
+    /// Rooms read by one of the readers, without the ones of the hotels removed by [Markets::filter_hotels].
+    pub fn rooms(&self, rooms: Vec<(String, Room)>) -> Vec<(String, Room)> {
+        if self.out_of_market.is_empty() {
+            return rooms;
+        }
+        rooms
+            .into_iter()
+            .filter(|(_, room)| !self.out_of_market.contains(&room.hotel_code))
+            .collect()
+    }
+
+    /// Whether the hotel was removed by [Markets::filter_hotels].
+    pub fn is_out_of_market(&self, hotel_code: &str) -> bool {
+        self.out_of_market.contains(hotel_code)
+    }
+
+    /// Whether the record of the input with the hotel code should be skipped, it's counted when it is.
+    pub fn skip(&mut self, hotel_code: &str) -> bool {
+        let skip = self.is_out_of_market(hotel_code);
+        if skip {
+            self.skipped += 1;
+        }
+        skip
+    }
+
+    /// How many records of the input were skipped so far.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::entities::Hotel;
+    use crate::data::DataSource;
+
+    fn hotel(id: &str, country_code: &str) -> Hotel {
+        Hotel {
+            id: String::from(id),
+            city_code: String::from("BER"),
+            name: String::from("Hotel"),
+            category: 4.0,
+            country_code: String::from(country_code),
+            city: String::from("Berlin"),
+            valid_from: None,
+            valid_to: None,
+        }
+    }
+
+    fn room(hotel_code: &str) -> (String, Room) {
+        let room = Room {
+            hotel_code: String::from(hotel_code),
+            source: String::from("IHG"),
+            room_name: String::from("Double Room"),
+            room_code: String::from("BER848"),
+            valid_from: None,
+            valid_to: None,
+        };
+        (room.key(), room)
+    }
+
+    #[test]
+    fn filter_by_markets() {
+        let mut hotels: HotelDataSource = DataSource::new();
+        for hotel in [
+            hotel("BER00002", "DE"),
+            hotel("VIE00001", "at"),
+            hotel("PAR00001", "FR"),
+        ] {
+            hotels.insert(hotel.id.clone(), hotel);
+        }
+        let mut markets = Markets::new(&[String::from("de"), String::from("AT")]);
+        markets.filter_hotels(&mut hotels);
+        let mut ids: Vec<_> = hotels.iter().map(|(id, _)| id.as_str()).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec!["BER00002", "VIE00001"]);
+
+        let rooms = markets.rooms(vec![room("BER00002"), room("PAR00001")]);
+        assert_eq!(rooms.len(), 1);
+        assert_eq!(rooms[0].1.hotel_code, "BER00002");
+
+        assert!(markets.skip("PAR00001"));
+        // hotels that are missing altogether are left for the integrator to reject
+        assert!(!markets.skip("LON00001"));
+        assert!(!markets.skip("BER00002"));
+        assert_eq!(markets.skipped(), 1);
+    }
+}
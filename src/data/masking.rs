@@ -0,0 +1,176 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::data::Output;
+
+/// Text columns of the output, they can be replaced with their hashes or cut to the limits.
+pub(crate) const TEXT_COLUMNS: [&str; 11] = [
+    "room_code",
+    "source",
+    "hotel_name",
+    "city_name",
+    "city_code",
+    "room_name",
+    "hotel_code",
+    "meal",
+    "chain_code",
+    "brand_name",
+    "winning_source",
+];
+
+/// How many hex digits of the SHA-256 are kept in place of the hashed values.
+const HASH_LENGTH: usize = 16;
+
+/// Anonymization of the records for the extracts that are shared outside, e.g with the analytics partners.
+/// Everything it does is given in the config file, so it can be reviewed before any extract is made, e.g
+/// ```toml
+/// [masks.partners]
+/// hash = ["hotel_name", "hotel_code"]
+/// salt = "b8f1c2"
+/// price_bucket = 50
+/// drop = ["room_name", "latitude", "longitude"]
+/// ```
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Mask {
+    /// Columns whose values are replaced with their salted hashes, the same value always has the same hash,
+    /// so the records can still be grouped by them
+    pub hash: Vec<String>,
+    /// Secret mixed into the hashes, so they can't be reversed by hashing the known names
+    pub salt: String,
+    /// Width of the ranges the prices are rounded down to, e.g 50 makes 85.50 into 50.00
+    pub price_bucket: Option<f64>,
+    /// Columns that are left out of the output altogether
+    pub drop: Vec<String>,
+}
+
+impl Mask {
+    pub fn validate(&self) -> Result<()> {
+        if let Some(column) = self
+            .hash
+            .iter()
+            .find(|column| !TEXT_COLUMNS.contains(&column.as_str()))
+        {
+            return Err(anyhow!(
+                "Column {} can't be hashed, expected one of: {}!",
+                column,
+                TEXT_COLUMNS.join(", ")
+            ));
+        }
+        match self.price_bucket {
+            Some(bucket) if bucket <= 0.0 || bucket.is_nan() => Err(anyhow!(
+                "Price bucket of the mask has to be positive, got {}!",
+                bucket
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Record with the hashed values and bucketed price, the dropped columns are left out once it's serialized.
+    pub fn apply(&self, mut output: Output) -> Output {
+        for column in &self.hash {
+            if let Some(value) = text_value(&mut output, column) {
+                *value = self.hashed(value);
+            }
+        }
+        if let Some(bucket) = self.price_bucket {
+            output.price = (output.price / bucket).floor() * bucket;
+        }
+        output
+    }
+
+    fn hashed(&self, value: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.salt.as_bytes());
+        hasher.update(value.as_bytes());
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>()[..HASH_LENGTH]
+            .to_string()
+    }
+}
+
+/// Value of the text column of the record, there's none when the column is not one of the text columns
+/// or it's empty in the record, like the chain of an independent hotel.
+pub(crate) fn text_value<'a>(output: &'a mut Output, column: &str) -> Option<&'a mut String> {
+    match column {
+        "room_code" => Some(&mut output.room_code),
+        "source" => Some(&mut output.source),
+        "hotel_name" => Some(&mut output.hotel_name),
+        "city_name" => Some(&mut output.city_name),
+        "city_code" => Some(&mut output.city_code),
+        "room_name" => Some(&mut output.room_name),
+        "hotel_code" => Some(&mut output.hotel_code),
+        "meal" => Some(&mut output.meal),
+        "chain_code" => output.chain_code.as_mut(),
+        "brand_name" => output.brand_name.as_mut(),
+        "winning_source" => output
+            .ranking
+            .as_mut()
+            .map(|ranking| &mut ranking.winning_source),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn mask_record() -> Result<()> {
+        let mask = Mask {
+            hash: vec![String::from("hotel_name"), String::from("chain_code")],
+            salt: String::from("secret"),
+            price_bucket: Some(50.0),
+            drop: Vec::new(),
+        };
+        mask.validate()?;
+        let masked = mask.apply(testing::output().build());
+        assert_eq!(masked.hotel_name.len(), HASH_LENGTH);
+        assert_ne!(masked.hotel_name, testing::output().build().hotel_name);
+        assert_eq!(
+            masked.hotel_name,
+            mask.apply(testing::output().build()).hotel_name
+        );
+        assert_eq!(masked.chain_code, None);
+        assert_eq!(masked.hotel_code, testing::output().build().hotel_code);
+        assert_eq!(masked.price, 50.0);
+
+        // another salt gives other hashes
+        let other = Mask {
+            salt: String::from("other"),
+            ..mask.clone()
+        };
+        assert_ne!(
+            other.apply(testing::output().build()).hotel_name,
+            masked.hotel_name
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn validate_mask() {
+        let mask = Mask {
+            hash: vec![String::from("price")],
+            ..Mask::default()
+        };
+        assert!(mask
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .starts_with("Column price can't be hashed"));
+
+        let mask = Mask {
+            price_bucket: Some(0.0),
+            ..Mask::default()
+        };
+        assert_eq!(
+            mask.validate().unwrap_err().to_string(),
+            "Price bucket of the mask has to be positive, got 0!"
+        );
+    }
+}
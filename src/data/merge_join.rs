@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Seek, SeekFrom};
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use csv::{DeserializeRecordsIntoIter, ReaderBuilder};
+
+use crate::data::versions::Dated;
+use crate::data::{FieldRules, Lookup, Markets, Room, Sanitizer};
+
+/// How many of the joined rooms are cached in front of the merge join, only the rooms of the current hotel
+/// are at hand anyway.
+pub const MERGE_JOIN_CACHE_SIZE: usize = 1024;
+
+/// Rooms streamed from the file sorted by their hotels alongside the input sorted the same way, so only
+/// the rooms of the hotel that the input is at are kept in memory. The input moves the join forward with
+/// the keys of its rooms, each of them starts with the code of its hotel (`BER00002-...`).
+/// The rooms are checked when the join is opened, the input while it's joined. When either of them turns out
+/// not to be sorted, all of the rooms are loaded into a map and the rest of the input is enriched from it,
+/// the reason is kept for the summary.
+pub struct MergeJoin {
+    file: File,
+    rooms: DeserializeRecordsIntoIter<File, Room>,
+    sanitizer: Sanitizer,
+    field_rules: FieldRules,
+    markets: Markets,
+    /// First room of the next hotel, it was read to find out where the current one ends
+    pending: Option<(String, Room)>,
+    /// Prefix of the keys of the rooms at hand (e.g `BER00002-`) with the rooms, there's none past the last hotel
+    current: Option<(String, HashMap<String, Room>)>,
+    /// Prefix of the keys of the hotel whose rooms were dropped last, the sorted input can't come back to it
+    passed: Option<String>,
+    /// All of the rooms, once the join fell back to them
+    all: Option<HashMap<String, Room>>,
+    fallback: Option<String>,
+}
+
+impl MergeJoin {
+    /// Join the rooms of the file, cleaned up and checked the same way as the ones that are loaded.
+    pub fn open(
+        path: &Path,
+        sanitizer: Sanitizer,
+        field_rules: FieldRules,
+        markets: Markets,
+    ) -> Result<Self> {
+        let file = File::open(path).with_context(|| "Path to the rooms data is invalid!")?;
+        let mut join = Self {
+            rooms: reader(&file)?,
+            file,
+            sanitizer,
+            field_rules,
+            markets,
+            pending: None,
+            current: None,
+            passed: None,
+            all: None,
+            fallback: None,
+        };
+        // the rooms are gone through once up front, the misses of the join can only be trusted when they are sorted
+        match join.unsorted()? {
+            Some(reason) => join.fall_back(reason)?,
+            None => {
+                join.rewind()?;
+                join.current = join.next_hotel()?;
+            }
+        }
+        Ok(join)
+    }
+
+    /// Why the rooms of the file are not sorted by their hotels, there's no reason when they are.
+    fn unsorted(&mut self) -> Result<Option<String>> {
+        let mut previous = String::new();
+        while let Some((key, room)) = self.next_room()? {
+            if previous.is_empty() || !key.starts_with(&previous) {
+                let prefix = format!("{}-", room.hotel_code);
+                if prefix < previous {
+                    return Ok(Some(format!(
+                        "the rooms aren't sorted by hotel_code, {} came after {}",
+                        room.hotel_code,
+                        previous.trim_end_matches('-')
+                    )));
+                }
+                previous = prefix;
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read the rooms of the file from its beginning again.
+    fn rewind(&mut self) -> Result<()> {
+        self.file.seek(SeekFrom::Start(0))?;
+        self.rooms = reader(&self.file)?;
+        self.pending = None;
+        Ok(())
+    }
+
+    /// Next of the rooms of the file that's not out of the markets.
+    fn next_room(&mut self) -> Result<Option<(String, Room)>> {
+        for room in self.rooms.by_ref() {
+            let room =
+                room.with_context(|| "Encountered unparsable entity during parsing rooms data.")?;
+            if room.is_dated() {
+                return Err(anyhow!(
+                    "Room {} has a validity, the rooms with the validity can't be merge-joined!",
+                    room.key()
+                ));
+            }
+            let rooms = self.sanitizer.rooms(vec![(room.key(), room)]);
+            let rooms = self.field_rules.rooms(self.markets.rooms(rooms))?;
+            if let Some(room) = rooms.into_iter().next() {
+                return Ok(Some(room));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Rooms of the next hotel of the file, with the prefix of their keys.
+    fn next_hotel(&mut self) -> Result<Option<(String, HashMap<String, Room>)>> {
+        let (key, first) = match self.pending.take() {
+            Some(room) => room,
+            None => match self.next_room()? {
+                Some(room) => room,
+                None => return Ok(None),
+            },
+        };
+        let prefix = format!("{}-", first.hotel_code);
+        let mut rooms = HashMap::new();
+        rooms.insert(key, first);
+        while let Some((key, room)) = self.next_room()? {
+            if !key.starts_with(&prefix) {
+                self.pending = Some((key, room));
+                break;
+            }
+            rooms.insert(key, room);
+        }
+        Ok(Some((prefix, rooms)))
+    }
+
+    /// Load all of the rooms of the file into a map, the rest of the input is enriched from it.
+    fn fall_back(&mut self, reason: String) -> Result<()> {
+        self.rewind()?;
+        self.current = None;
+        let mut all = HashMap::new();
+        while let Some((key, room)) = self.next_room()? {
+            all.insert(key, room);
+        }
+        self.all = Some(all);
+        self.fallback = Some(reason);
+        Ok(())
+    }
+}
+
+fn reader(file: &File) -> Result<DeserializeRecordsIntoIter<File, Room>> {
+    Ok(ReaderBuilder::new()
+        .has_headers(false)
+        .delimiter(b'|')
+        .flexible(true)
+        .from_reader(file.try_clone()?)
+        .into_deserialize())
+}
+
+impl Lookup<String, Room> for MergeJoin {
+    fn fetch(&mut self, key: &String) -> Result<Option<Room>> {
+        loop {
+            if let Some(all) = &self.all {
+                return Ok(all.get(key).cloned());
+            }
+            if let Some(passed) = &self.passed {
+                if key.starts_with(passed.as_str()) || key < passed {
+                    let reason = format!(
+                        "the input isn't sorted by hotel_code, {} came after the rooms of the later hotels",
+                        key
+                    );
+                    self.fall_back(reason)?;
+                    continue;
+                }
+            }
+            let (prefix, rooms) = match &self.current {
+                Some(current) => current,
+                None => return Ok(None),
+            };
+            if key.starts_with(prefix.as_str()) {
+                return Ok(rooms.get(key).cloned());
+            }
+            // the hotel of the key is missing in the rooms, it's between the previous hotel and the current one
+            if key < prefix {
+                return Ok(None);
+            }
+            self.passed = self.current.take().map(|(prefix, _)| prefix);
+            self.current = self.next_hotel()?;
+        }
+    }
+
+    fn fallback(&self) -> Option<String> {
+        self.fallback.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn join(rooms: &[&str]) -> Result<MergeJoin> {
+        let mut file = tempfile::NamedTempFile::new()?;
+        for room in rooms {
+            writeln!(file, "{}", room)?;
+        }
+        MergeJoin::open(
+            file.path(),
+            Sanitizer::default(),
+            FieldRules::default(),
+            Markets::default(),
+        )
+    }
+
+    fn room_name(join: &mut MergeJoin, key: &str) -> Result<Option<String>> {
+        Ok(join.fetch(&String::from(key))?.map(|room| room.room_name))
+    }
+
+    const ROOMS: [&str; 4] = [
+        "BER00002|IHG|Single Classic|BER898",
+        "BER00002|IHG|Double Classic|BER848",
+        "BER00003|MARR|Double Deluxe|BER848",
+        "BER00005|IHG|Suite|BER101",
+    ];
+
+    #[test]
+    fn join_sorted_input() -> Result<()> {
+        let mut join = join(&ROOMS)?;
+        assert_eq!(
+            room_name(&mut join, "BER00002-BER848-IHG")?.as_deref(),
+            Some("Double Classic")
+        );
+        assert_eq!(
+            room_name(&mut join, "BER00002-BER898-IHG")?.as_deref(),
+            Some("Single Classic")
+        );
+        // the hotel that's missing in the rooms doesn't move the join past the next one
+        assert_eq!(room_name(&mut join, "BER00002A-BER848-IHG")?, None);
+        assert_eq!(
+            room_name(&mut join, "BER00003-BER848-MARR")?.as_deref(),
+            Some("Double Deluxe")
+        );
+        assert_eq!(room_name(&mut join, "BER00004-BER101-IHG")?, None);
+        assert_eq!(room_name(&mut join, "BER00004-BER101-IHG")?, None);
+        assert_eq!(
+            room_name(&mut join, "BER00005-BER101-IHG")?.as_deref(),
+            Some("Suite")
+        );
+        assert_eq!(room_name(&mut join, "BER00009-BER101-IHG")?, None);
+        assert!(join.all.is_none());
+        assert_eq!(join.fallback(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn fall_back_when_not_sorted() -> Result<()> {
+        let mut join = self::join(&ROOMS)?;
+        assert!(room_name(&mut join, "BER00003-BER848-MARR")?.is_some());
+        assert_eq!(
+            room_name(&mut join, "BER00002-BER898-IHG")?.as_deref(),
+            Some("Single Classic")
+        );
+        assert!(join
+            .fallback()
+            .unwrap()
+            .starts_with("the input isn't sorted by hotel_code"));
+
+        // the rooms are checked before anything is joined
+        let mut join = self::join(&[ROOMS[2], ROOMS[0], ROOMS[3]])?;
+        assert_eq!(
+            join.fallback().as_deref(),
+            Some("the rooms aren't sorted by hotel_code, BER00002 came after BER00003")
+        );
+        assert!(room_name(&mut join, "BER00002-BER898-IHG")?.is_some());
+        assert!(room_name(&mut join, "BER00003-BER848-MARR")?.is_some());
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+
+use anyhow::{Context, Result};
+use csv::Writer;
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingKind {
+    Room,
+    Hotel,
+}
+
+/// Room or hotel that the input links to, but that's not in the reference data.
+/// Hotels are listed for each of the sources that link to them, so every supplier gets its own list.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MissingKey {
+    pub kind: MissingKind,
+    pub hotel_code: String,
+    /// It's empty for the hotels
+    pub room_code: String,
+    pub source: String,
+}
+
+#[derive(Serialize)]
+struct MissingKeyRecord<'a> {
+    kind: MissingKind,
+    hotel_code: &'a str,
+    room_code: &'a str,
+    source: &'a str,
+    /// How many records of the input link to it
+    records: usize,
+}
+
+/// Distinct keys missing in the reference data, with the number of the records of the input that link to each of them.
+#[derive(Debug, Default)]
+pub struct MissingKeys {
+    keys: BTreeMap<MissingKey, usize>,
+}
+
+impl MissingKeys {
+    pub fn add(&mut self, key: MissingKey) {
+        *self.keys.entry(key).or_insert(0) += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Save the keys as CSV, the missing rooms go first, e.g `room,BER00003,BER848,MARR,3`.
+    pub fn save(&self, writer: impl Write) -> Result<()> {
+        let mut writer = Writer::from_writer(writer);
+        for (key, records) in &self.keys {
+            writer
+                .serialize(MissingKeyRecord {
+                    kind: key.kind,
+                    hotel_code: &key.hotel_code,
+                    room_code: &key.room_code,
+                    source: &key.source,
+                    records: *records,
+                })
+                .with_context(|| format!("Couldn't save the missing key {:?}!", key))?;
+        }
+        writer
+            .flush()
+            .with_context(|| "Couldn't save the missing keys!")
+    }
+}
+
+/// How much of the input links to the rooms and hotels that are in the reference data,
+/// found by going through the whole input before any of it is enriched.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Coverage {
+    /// How many records of the input were read
+    pub records: usize,
+    /// How many of them link to both a room and a hotel that are in the reference data
+    pub covered: usize,
+    /// How many distinct rooms and hotels are missing
+    pub missing_keys: usize,
+}
+
+impl Coverage {
+    /// Ratio of the covered records to all of them, the empty input is covered completely.
+    pub fn ratio(&self) -> f64 {
+        if self.records == 0 {
+            1.0
+        } else {
+            self.covered as f64 / self.records as f64
+        }
+    }
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.1}% ({} of {} records, {} missing keys)",
+            self.ratio() * 100.0,
+            self.covered,
+            self.records,
+            self.missing_keys
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(kind: MissingKind, hotel_code: &str, room_code: &str, source: &str) -> MissingKey {
+        MissingKey {
+            kind,
+            hotel_code: String::from(hotel_code),
+            room_code: String::from(room_code),
+            source: String::from(source),
+        }
+    }
+
+    #[test]
+    fn save_distinct_keys() -> Result<()> {
+        let mut missing = MissingKeys::default();
+        missing.add(key(MissingKind::Hotel, "BER00002", "", "IHG"));
+        missing.add(key(MissingKind::Room, "BER00003", "BER848", "MARR"));
+        missing.add(key(MissingKind::Hotel, "BER00002", "", "IHG"));
+        assert_eq!(missing.len(), 2);
+
+        let mut saved = Vec::new();
+        missing.save(&mut saved)?;
+        assert_eq!(
+            String::from_utf8(saved)?,
+            "kind,hotel_code,room_code,source,records\n\
+             room,BER00003,BER848,MARR,1\n\
+             hotel,BER00002,,IHG,2\n"
+        );
+        Ok(())
+    }
+}
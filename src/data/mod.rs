@@ -1,22 +1,93 @@
 use std::collections::HashMap;
 use std::hash::Hash;
+use std::num::NonZeroUsize;
 use std::path::Path;
+use std::sync::Arc;
 
 use anyhow::Result;
-use serde::Serializer;
-
-pub use entities::{Hotel, Input, Output, Room};
-pub use integrator::DataIntegrator;
-pub use readers::{hotels_reader, rooms_reader};
-
-use crate::data::entities::Price;
-
+use lru::LruCache;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+pub use anomalies::{find_anomalies, find_anomalies_spilling, save_anomalies, AnomalyRules};
+pub use audit::Audit;
+pub use calendar::seasons_reader;
+pub use consolidation::{consolidate, consolidate_spilling, Consolidation};
+#[cfg(feature = "postgres")]
+pub use database::validate_table;
+pub use database::{hotels_table_reader, is_database, rooms_table_reader};
+pub use duplicates::{DuplicatePolicy, Duplicates};
+pub use entities::{
+    generate_room_key, Chain, Coordinates, Hotel, Input, Output, Room, Translation,
+};
+pub use expansion::expand_nights;
+pub use fields::FieldRules;
+pub use format::{CategoryFormat, NumberFormat, OutputFormat, OutputRecord};
+pub use input::{
+    column_spec_reader, csv_input, fixed_width_input, normalize_input, InputFormat, InputSchema,
+    KeyCase, RawInput, RawInputs, INPUT_COLUMNS,
+};
+pub use integrator::{DataIntegrator, Order};
+pub use limits::Limits;
+pub use markets::Markets;
+pub use masking::Mask;
+pub use merge_join::{MergeJoin, MERGE_JOIN_CACHE_SIZE};
+pub use missing::Coverage;
+pub use normalization::RoomNameNormalizer;
+pub use overrides::{apply_overrides, overrides_reader, Action, Entry, Override};
+pub use placeholders::{OnMissing, Placeholders};
+pub use readers::{
+    chains_reader, coordinates_reader, hotels_reader_for, rooms_reader, translations_reader,
+};
+#[cfg(feature = "redis")]
+pub use redis_lookup::RedisLookup;
+pub use reject::{Reject, RejectReason};
+pub use sanitize::Sanitizer;
+pub use spill::{group_sorted, sort_spilling, ApproximateSize, DiskLookup, MemoryBudget};
+pub use stats::{GroupBy, Stats};
+pub use versions::Versions;
+pub use warning::{Warning, WarningKind, WarningRules};
+#[cfg(feature = "xml")]
+pub use xml::xml_input;
+
+mod anomalies;
+mod audit;
+mod calendar;
+mod consolidation;
+mod database;
+mod duplicates;
 mod entities;
+mod expansion;
+mod fields;
+mod format;
+mod fuzzy;
+mod input;
 mod integrator;
+mod limits;
+mod markets;
+mod masking;
+mod merge_join;
+mod missing;
+mod normalization;
+mod overrides;
+mod placeholders;
 mod readers;
+#[cfg(feature = "redis")]
+mod redis_lookup;
+mod reject;
+mod sanitize;
+mod spill;
+mod stats;
+mod versions;
+mod warning;
+#[cfg(feature = "xml")]
+mod xml;
 
 pub type RoomDataSource = DataSource<String, Room>;
 pub type HotelDataSource = DataSource<String, Hotel>;
+pub type CoordinatesDataSource = DataSource<String, Coordinates>;
+pub type ChainDataSource = DataSource<String, Chain>;
+pub type TranslationDataSource = DataSource<String, Translation>;
 
 /// Custom serde for dates that come in the input.
 /// It deserializes date from format %Y%m%d (e.g 20190730) to chrono::NaiveDate.
@@ -25,8 +96,8 @@ pub mod custom_date {
     use chrono::NaiveDate;
     use serde::{self, Deserialize, Deserializer, Serializer};
 
-    const INPUT_FORMAT: &str = "%Y%m%d";
-    const OUTPUT_FORMAT: &str = "%Y-%m-%d";
+    pub const INPUT_FORMAT: &str = "%Y%m%d";
+    pub const OUTPUT_FORMAT: &str = "%Y-%m-%d";
 
     pub fn serialize<S>(date: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -39,37 +110,233 @@ pub mod custom_date {
     where
         D: Deserializer<'de>,
     {
-        NaiveDate::parse_from_str(String::deserialize(deserializer)?.as_str(), INPUT_FORMAT)
-            .map_err(serde::de::Error::custom)
+        let value = String::deserialize(deserializer)?;
+        NaiveDate::parse_from_str(value.as_str(), INPUT_FORMAT).map_err(|_| {
+            serde::de::Error::custom(format!(
+                "expected date in {} format, got '{}'",
+                INPUT_FORMAT, value
+            ))
+        })
     }
-}
 
-/// Custom serde for float numbers to ensure that it is always serialized
-/// with two decimal points e.g 8.50 instead of 8.5
+    /// Optional date of the input, it's missing when its value is empty.
+    pub fn deserialize_optional<'de, D>(deserializer: D) -> Result<Option<NaiveDate>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        if value.is_empty() {
+            return Ok(None);
+        }
+        NaiveDate::parse_from_str(value.as_str(), INPUT_FORMAT)
+            .map(Some)
+            .map_err(|_| {
+                serde::de::Error::custom(format!(
+                    "expected date in {} format, got '{}'",
+                    INPUT_FORMAT, value
+                ))
+            })
+    }
+
+    pub fn serialize_optional<S>(date: &Option<NaiveDate>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match date {
+            Some(date) => serialize(date, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
 
-pub fn serialize_float<S>(num: &Price, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    serializer.serialize_str(format!("{:.2}", num).as_ref())
+    /// Date as it's saved in the output, e.g when the records spilled to the disk are read back.
+    pub fn deserialize_saved<'de, D>(deserializer: D) -> Result<NaiveDate, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        NaiveDate::parse_from_str(value.as_str(), OUTPUT_FORMAT).map_err(|_| {
+            serde::de::Error::custom(format!(
+                "expected date in {} format, got '{}'",
+                OUTPUT_FORMAT, value
+            ))
+        })
+    }
 }
 
 /// In-memory data source that keeps its data in a HashMap.
 /// The data can be imported from many different places and the read/deserialization
 /// process is supplied by the Reader which is just a plain function that reads data from the given path
 /// and returns it as a Vec<I>. This way we are not strictly tied to one source of data and one way of parsing it.
-
+/// The items are kept behind an Arc, so the handles made with `share` read the same map instead of copying it.
+/// Once everything is imported, the DataSource can be frozen into a read-only view of the items.
 pub struct DataSource<K: Eq + Hash, I> {
-    items: HashMap<K, I>,
+    items: Arc<Items<K, I>>,
+    remote: Option<Remote<K, I>>,
+}
+
+/// Items of the DataSource, they are kept in a map while they are imported and changed,
+/// and in a vector sorted by the keys once they are frozen, which takes much less memory than the map.
+#[derive(Clone)]
+enum Items<K, I> {
+    Building(HashMap<K, I>),
+    Frozen(Vec<(K, I)>),
+}
+
+impl<K: Ord + Hash, I> Items<K, I> {
+    fn get(&self, key: &K) -> Option<&I> {
+        match self {
+            Items::Building(items) => items.get(key),
+            Items::Frozen(items) => items
+                .binary_search_by(|(item_key, _)| item_key.cmp(key))
+                .ok()
+                .map(|index| &items[index].1),
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (&K, &I)> + '_> {
+        match self {
+            Items::Building(items) => Box::new(items.iter()),
+            Items::Frozen(items) => Box::new(items.iter().map(|(key, item)| (key, item))),
+        }
+    }
+}
+
+/// Source of the items that are not kept in memory, but looked up one by one, e.g in a Redis hash.
+/// It has to be safe to send to (and share with) other threads, just like the rest of the DataSource.
+pub trait Lookup<K, I>: Send + Sync {
+    fn fetch(&mut self, key: &K) -> Result<Option<I>>;
+
+    /// Why the items are no longer looked up the way they were meant to be, e.g the merge join of the input
+    /// that wasn't sorted.
+    fn fallback(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Items that are looked up remotely, the recently used ones (and the ones that are missing) are cached.
+struct Remote<K: Eq + Hash, I> {
+    lookup: Box<dyn Lookup<K, I>>,
+    cache: LruCache<K, Option<I>>,
+    stats: CacheStats,
+}
+
+/// How many of the remote items were found in the cache and how many had to be looked up.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl CacheStats {
+    /// Percent of the items that were found in the cache.
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 * 100.0 / total as f64,
+        }
+    }
 }
 
-impl<I, K: Eq + Hash> DataSource<K, I> {
+impl<I, K: Ord + Hash> Default for DataSource<K, I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I, K: Ord + Hash> DataSource<K, I> {
     pub fn new() -> Self {
         Self {
-            items: HashMap::new(),
+            items: Arc::new(Items::Building(HashMap::new())),
+            remote: None,
+        }
+    }
+
+    /// Look up the items that are not in memory with the given lookup, keeping at most `cache_size` of them at hand.
+    /// The remote items have to be prefetched before they can be found.
+    pub fn with_lookup(mut self, lookup: Box<dyn Lookup<K, I>>, cache_size: NonZeroUsize) -> Self {
+        self.remote = Some(Remote {
+            lookup,
+            cache: LruCache::new(cache_size),
+            stats: CacheStats::default(),
+        });
+        self
+    }
+
+    /// Find data in the DataSource by the given key.
+    pub fn find(&self, key: &K) -> Option<&I> {
+        self.items.get(key).or_else(|| {
+            self.remote
+                .as_ref()
+                .and_then(|remote| remote.cache.peek(key))
+                .and_then(Option::as_ref)
+        })
+    }
+
+    /// Iterate over all of the data in the DataSource, in no particular order (in the order of the keys once frozen).
+    /// Remote items are not a part of it.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &I)> {
+        self.items.iter()
+    }
+
+    /// Whether the items in memory are shared with other handles.
+    pub fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.items) > 1
+    }
+
+    /// Whether the items in memory are the same ones as those of the other handle.
+    #[cfg(test)]
+    pub(crate) fn shares_items_with(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.items, &other.items)
+    }
+
+    /// Another handle to the items in memory, they are shared with it instead of being copied.
+    /// Remote items are not a part of it, the lookup and its cache stay with this handle.
+    pub fn share(&self) -> Self {
+        Self {
+            items: Arc::clone(&self.items),
+            remote: None,
+        }
+    }
+}
+
+/// Changes of the items are applied in place, unless they are shared with other handles,
+/// then the handle gets its own copy of them first and the other handles see no change.
+/// Frozen items go back into a map when they are changed.
+impl<I: Clone, K: Ord + Hash + Clone> DataSource<K, I> {
+    /// Items that can be changed.
+    fn building(&mut self) -> &mut HashMap<K, I> {
+        let items = Arc::make_mut(&mut self.items);
+        if let Items::Frozen(frozen) = items {
+            *items = Items::Building(frozen.drain(..).collect());
+        }
+        match items {
+            Items::Building(items) => items,
+            Items::Frozen(_) => unreachable!(),
         }
     }
 
+    /// Turn the items into a read-only vector sorted by the keys, in which they are found with a binary search.
+    /// It's meant to be done once all of the items are imported, as they can't be changed without thawing them.
+    /// Items that are already frozen stay where they are, so the handles made with `share` keep sharing them.
+    pub fn freeze(mut self) -> Self {
+        if let Items::Frozen(_) = &*self.items {
+            return self;
+        }
+        let mut frozen: Vec<(K, I)> = match Arc::try_unwrap(self.items) {
+            Ok(Items::Building(items)) => items.into_iter().collect(),
+            Err(shared) => match &*shared {
+                Items::Building(items) => {
+                    items.iter().map(|(k, i)| (k.clone(), i.clone())).collect()
+                }
+                Items::Frozen(_) => unreachable!("Frozen items are returned right away"),
+            },
+            Ok(Items::Frozen(_)) => unreachable!("Frozen items are returned right away"),
+        };
+        frozen.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        self.items = Arc::new(Items::Frozen(frozen));
+        self
+    }
+
     /// Import data from given path, read it with given data reader and save to the self.items
     /// This method is generic, so we are not tied to one particular way of importing the data, because of that
     /// we are able to import data from many different places or file formats.
@@ -77,22 +344,95 @@ impl<I, K: Eq + Hash> DataSource<K, I> {
     /// This operation might fail, because the deserialization process may not succeed or the file might not exist.
     pub fn import_from<R>(&mut self, path: &Path, reader: R) -> Result<()>
     where
-        R: Fn(&Path) -> Result<Vec<(K, I)>>,
+        R: FnOnce(&Path) -> Result<Vec<(K, I)>>,
     {
         let items = reader(path)?;
-        self.items.extend(items.into_iter());
+        self.building().extend(items);
         Ok(())
     }
 
-    /// Find data in the DataSource by the given key.
-    pub fn find(&self, key: &K) -> Option<&I> {
-        self.items.get(key)
+    /// Import data from the table of a database, read it with the given reader and save to the self.items.
+    /// It works just like the import from a file, but the reader gets the location of the table instead of a path.
+    pub fn import_from_table<R>(&mut self, location: &str, reader: R) -> Result<()>
+    where
+        R: FnOnce(&str) -> Result<Vec<(K, I)>>,
+    {
+        let items = reader(location)?;
+        self.building().extend(items);
+        Ok(())
+    }
+
+    /// Save the item under the given key, returns the item that was saved there before.
+    pub fn insert(&mut self, key: K, item: I) -> Option<I> {
+        self.building().insert(key, item)
+    }
+
+    /// Remove the item saved under the given key and return it.
+    pub fn remove(&mut self, key: &K) -> Option<I> {
+        self.building().remove(key)
+    }
+}
+
+impl<I: Clone + Serialize + DeserializeOwned + 'static> DataSource<String, I> {
+    /// Move the items in memory into a temporary file, from which they are looked up one by one,
+    /// keeping at most `cache_size` of them at hand. They have to be prefetched before they can be found.
+    pub fn spill(self, cache_size: NonZeroUsize) -> Result<Self> {
+        let frozen = self.freeze();
+        let lookup = match frozen.items.as_ref() {
+            Items::Frozen(items) => DiskLookup::new(items.iter().cloned())?,
+            Items::Building(_) => unreachable!("The items were frozen"),
+        };
+        Ok(Self::new().with_lookup(Box::new(lookup), cache_size))
+    }
+}
+
+impl<I: ApproximateSize, K: Ord + Hash + ApproximateSize> DataSource<K, I> {
+    /// Approximate number of bytes the items in memory take and how many of them there are.
+    pub fn approximate_size(&self) -> (usize, usize) {
+        self.iter().fold((0, 0), |(size, count), (key, item)| {
+            (
+                size + key.approximate_size() + item.approximate_size(),
+                count + 1,
+            )
+        })
+    }
+}
+
+impl<I, K: Ord + Hash + Clone> DataSource<K, I> {
+    /// Make sure that the item with the given key can be found, looking it up remotely when it's not at hand.
+    pub fn prefetch(&mut self, key: &K) -> Result<()> {
+        let remote = match &mut self.remote {
+            Some(remote) if self.items.get(key).is_none() => remote,
+            _ => return Ok(()),
+        };
+        if remote.cache.get(key).is_some() {
+            remote.stats.hits += 1;
+        } else {
+            remote.stats.misses += 1;
+            let item = remote.lookup.fetch(key)?;
+            remote.cache.put(key.clone(), item);
+        }
+        Ok(())
+    }
+
+    /// Statistics of the cache of the remote items, there's none when all of the items are in memory.
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.remote.as_ref().map(|remote| remote.stats)
+    }
+
+    /// Why the remote items are no longer looked up the way they were meant to be, see [Lookup::fallback].
+    pub fn fallback(&self) -> Option<String> {
+        self.remote
+            .as_ref()
+            .and_then(|remote| remote.lookup.fallback())
     }
 }
 
 #[cfg(test)]
 mod test {
     use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
 
     use anyhow::Result;
     use chrono::NaiveDate;
@@ -113,14 +453,16 @@ mod test {
     }
 
     #[test]
+    #[allow(clippy::needless_borrows_for_generic_args)]
     fn import_from() -> Result<()> {
         let mut data_source: DataSource<String, usize> = DataSource::new();
         data_source.import_from(Path::new("some_path"), &mock_data)?;
-        assert_eq!(data_source.items.len(), 5);
+        assert_eq!(data_source.iter().count(), 5);
         Ok(())
     }
 
     #[test]
+    #[allow(clippy::needless_borrows_for_generic_args)]
     fn find() -> Result<()> {
         let mut data_source: DataSource<String, usize> = DataSource::new();
         data_source.import_from(Path::new("some_path"), &mock_data)?;
@@ -159,6 +501,122 @@ mod test {
         Ok(())
     }
 
+    /// Items are their keys doubled, the fetched keys are counted.
+    struct MockLookup(Arc<AtomicUsize>);
+
+    impl Lookup<String, usize> for MockLookup {
+        fn fetch(&mut self, key: &String) -> Result<Option<usize>> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(key.parse::<usize>().ok().map(|number| number * 2))
+        }
+    }
+
+    #[test]
+    fn prefetch_remote_items() -> Result<()> {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let mut data_source: DataSource<String, usize> = DataSource::new().with_lookup(
+            Box::new(MockLookup(fetches.clone())),
+            NonZeroUsize::new(2).unwrap(),
+        );
+        data_source.insert(String::from("1"), 1);
+
+        let key = String::from("21");
+        assert_eq!(data_source.find(&key), None);
+        data_source.prefetch(&key)?;
+        data_source.prefetch(&key)?;
+        assert_eq!(data_source.find(&key), Some(&42));
+        // missing items are cached as well, items in memory are never looked up
+        data_source.prefetch(&String::from("missing"))?;
+        data_source.prefetch(&String::from("1"))?;
+        assert_eq!(data_source.find(&String::from("1")), Some(&1));
+        assert_eq!(fetches.load(Ordering::Relaxed), 2);
+        assert_eq!(
+            data_source.cache_stats(),
+            Some(CacheStats { hits: 1, misses: 2 })
+        );
+
+        // the least recently used item is evicted from the cache
+        data_source.prefetch(&String::from("5"))?;
+        assert_eq!(data_source.find(&key), None);
+        assert_eq!(data_source.find(&String::from("5")), Some(&10));
+        Ok(())
+    }
+
+    #[test]
+    fn share_items() -> Result<()> {
+        let mut data_source: DataSource<String, usize> = DataSource::new();
+        data_source.import_from(Path::new("some_path"), mock_data)?;
+        let shared = data_source.share();
+        assert!(Arc::ptr_eq(&data_source.items, &shared.items));
+
+        let found = thread::spawn(move || shared.find(&String::from("two")).copied())
+            .join()
+            .unwrap();
+        assert_eq!(found, Some(2));
+
+        // the changes don't reach the other handles
+        let mut changed = data_source.share();
+        changed.insert(String::from("six"), 6);
+        assert_eq!(changed.find(&String::from("six")), Some(&6));
+        assert_eq!(data_source.find(&String::from("six")), None);
+        Ok(())
+    }
+
+    #[test]
+    fn freeze() -> Result<()> {
+        let mut data_source: DataSource<String, usize> = DataSource::new();
+        data_source.import_from(Path::new("some_path"), mock_data)?;
+        let mut frozen = data_source.freeze();
+        assert!(matches!(*frozen.items, Items::Frozen(_)));
+        for (key, item) in mock_data(Path::new("some_path"))? {
+            assert_eq!(frozen.find(&key), Some(&item));
+        }
+        assert_eq!(frozen.find(&String::from("six")), None);
+        assert_eq!(
+            frozen
+                .iter()
+                .map(|(key, _)| key.as_str())
+                .collect::<Vec<_>>(),
+            vec!["five", "four", "one", "three", "two"]
+        );
+
+        // the items are thawed to be changed
+        frozen.insert(String::from("six"), 6);
+        assert!(matches!(*frozen.items, Items::Building(_)));
+        assert_eq!(frozen.find(&String::from("six")), Some(&6));
+        assert_eq!(frozen.find(&String::from("one")), Some(&1));
+        Ok(())
+    }
+
+    #[test]
+    fn keep_sharing_frozen_items() -> Result<()> {
+        let mut data_source: DataSource<String, usize> = DataSource::new();
+        data_source.import_from(Path::new("some_path"), mock_data)?;
+        let frozen = data_source.freeze();
+        let shared = frozen.share().freeze();
+        assert!(Arc::ptr_eq(&frozen.items, &shared.items));
+        Ok(())
+    }
+
+    #[test]
+    fn spill_items() -> Result<()> {
+        let mut data_source: DataSource<String, usize> = DataSource::new();
+        data_source.import_from(Path::new("some_path"), mock_data)?;
+        let mut spilled = data_source.spill(NonZeroUsize::new(1).unwrap())?;
+        assert_eq!(spilled.iter().count(), 0);
+        let key = String::from("three");
+        assert_eq!(spilled.find(&key), None);
+        spilled.prefetch(&key)?;
+        assert_eq!(spilled.find(&key), Some(&3));
+        spilled.prefetch(&String::from("six"))?;
+        assert_eq!(spilled.find(&String::from("six")), None);
+        assert_eq!(
+            spilled.cache_stats(),
+            Some(CacheStats { hits: 0, misses: 2 })
+        );
+        Ok(())
+    }
+
     // custom_date
 
     #[derive(Deserialize, Serialize, PartialEq, Debug)]
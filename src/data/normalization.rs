@@ -0,0 +1,120 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use csv::ReaderBuilder;
+use regex::Regex;
+use serde::Deserialize;
+
+/// Source used in the rules that apply to every source.
+const ANY_SOURCE: &str = "*";
+
+#[derive(Deserialize)]
+struct RawRule {
+    source: String,
+    pattern: String,
+    canonical: String,
+}
+
+struct Rule {
+    source: String,
+    pattern: Regex,
+    canonical: String,
+}
+
+/// Maps room names that come from different sources into the canonical ones,
+/// e.g "SINGLE DELUXE" from DOTW into "Deluxe Single".
+/// Each rule consists of the source it applies to (or * for all of them), a regular expression
+/// that has to match the whole room name and the canonical name, which may refer to the groups captured by the pattern e.g $1.
+/// Rules are checked in the order they were defined and the first matching one wins.
+pub struct RoomNameNormalizer {
+    rules: Vec<Rule>,
+}
+
+impl RoomNameNormalizer {
+    /// Read the rules from a CSV file in the format of `source|pattern|canonical`.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let mut csv_reader = ReaderBuilder::new()
+            .has_headers(false)
+            .delimiter(b'|')
+            .from_path(path)
+            .with_context(|| "Path to the room name rules is invalid!")?;
+
+        let rules = csv_reader
+            .deserialize::<RawRule>()
+            .map(|res| {
+                let raw = res.with_context(|| {
+                    "Encountered unparsable entity during parsing room name rules."
+                })?;
+                let pattern = Regex::new(&format!("^(?:{})$", raw.pattern))
+                    .with_context(|| format!("Invalid room name pattern: {}", raw.pattern))?;
+                Ok(Rule {
+                    source: raw.source,
+                    pattern,
+                    canonical: raw.canonical,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Canonical name of the room, or the name itself if none of the rules matches it.
+    pub fn normalize(&self, source: &str, room_name: &str) -> String {
+        self.rules
+            .iter()
+            .filter(|rule| rule.source == ANY_SOURCE || rule.source == source)
+            .find(|rule| rule.pattern.is_match(room_name))
+            .map(|rule| {
+                rule.pattern
+                    .replace(room_name, rule.canonical.as_str())
+                    .into_owned()
+            })
+            .unwrap_or_else(|| room_name.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize() {
+        let normalizer = RoomNameNormalizer::from_path(Path::new("test_data/room_name_rules.csv"))
+            .expect("Couldn't read room name rules from given path");
+
+        assert_eq!(
+            normalizer.normalize("DOTW", "SINGLE DELUXE"),
+            "Deluxe Single"
+        );
+        // rules apply only to their own source
+        assert_eq!(
+            normalizer.normalize("MARR", "SINGLE DELUXE"),
+            "SINGLE DELUXE"
+        );
+        // unless they apply to all of them
+        assert_eq!(normalizer.normalize("MARR", "DELUXE KING"), "Deluxe King");
+        assert_eq!(
+            normalizer.normalize("MARR", "Deluxe King Extra"),
+            "Deluxe King Extra"
+        );
+        // the pattern has to match the whole name
+        assert_eq!(
+            normalizer.normalize("IHG", "Einzelzimmer Komfort"),
+            "Einzelzimmer Komfort"
+        );
+        assert_eq!(
+            normalizer.normalize("IHG", "Einzelzimmer"),
+            "Single Standard"
+        );
+    }
+
+    #[test]
+    fn read_rules_from_invalid_path() {
+        assert_eq!(
+            RoomNameNormalizer::from_path(Path::new("nonexistentfile"))
+                .err()
+                .expect("This should fail")
+                .to_string(),
+            "Path to the room name rules is invalid!"
+        );
+    }
+}
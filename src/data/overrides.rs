@@ -0,0 +1,194 @@
+use std::fs::read_to_string;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::data::{Hotel, HotelDataSource, Room, RoomDataSource};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Entry that doesn't exist in the data source yet
+    Add,
+    /// Entry that exists in the data source, but its data is wrong
+    Replace,
+    /// Entry that exists in the data source, but shouldn't be there
+    Delete,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Entry {
+    Room(Room),
+    Hotel(Hotel),
+}
+
+/// Single change of the reference data, saved as one line of JSON e.g
+/// {"action":"add","room":{"hotel_code":"BER00002","source":"IHG","room_name":"Einzelzimmer","room_code":"BER898"}}
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Override {
+    pub action: Action,
+    #[serde(flatten)]
+    pub entry: Entry,
+}
+
+impl Override {
+    /// Apply the change to the data sources. Entries are matched by the same keys as the ones used
+    /// by the readers, so a deleted entry only needs to have its key fields filled in.
+    /// It throws an error if an added entry already exists or if a replaced or deleted one doesn't.
+    pub fn apply(&self, rooms: &mut RoomDataSource, hotels: &mut HotelDataSource) -> Result<()> {
+        let (exists, kind, key) = match &self.entry {
+            Entry::Room(room) => (rooms.find(&room.key()).is_some(), "room", room.key()),
+            Entry::Hotel(hotel) => (hotels.find(&hotel.id).is_some(), "hotel", hotel.id.clone()),
+        };
+        match (self.action, exists) {
+            (Action::Add, true) => {
+                return Err(anyhow!("Can't add {} {}, it already exists!", kind, key))
+            }
+            (Action::Replace, false) | (Action::Delete, false) => {
+                return Err(anyhow!("Can't change {} {}, it doesn't exist!", kind, key))
+            }
+            _ => {}
+        }
+        match (self.action, &self.entry) {
+            (Action::Delete, Entry::Room(room)) => {
+                rooms.remove(&room.key());
+            }
+            (Action::Delete, Entry::Hotel(hotel)) => {
+                hotels.remove(&hotel.id);
+            }
+            (_, Entry::Room(room)) => {
+                rooms.insert(room.key(), room.clone());
+            }
+            (_, Entry::Hotel(hotel)) => {
+                hotels.insert(hotel.id.clone(), hotel.clone());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Function used to read overrides of the reference data from a file where each line is a valid json object.
+/// It throws an error if the file doesn't exist at specified path or if
+/// it encounters data that isn't in the format of the Override.
+pub fn overrides_reader(path: &Path) -> Result<Vec<Override>> {
+    read_to_string(path)
+        .with_context(|| "Path to the overrides is invalid!")?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str::<Override>(line).with_context(|| {
+                format!(
+                    "Encountered unparsable entity during parsing overrides at line: {}",
+                    line
+                )
+            })
+        })
+        .collect()
+}
+
+/// Apply the overrides to the data sources in the order in which they are given.
+pub fn apply_overrides(
+    overrides: &[Override],
+    rooms: &mut RoomDataSource,
+    hotels: &mut HotelDataSource,
+) -> Result<()> {
+    for (index, change) in overrides.iter().enumerate() {
+        change
+            .apply(rooms, hotels)
+            .with_context(|| format!("Couldn't apply the override at line {}", index + 1))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::DataSource;
+
+    fn room(room_name: &str) -> Room {
+        Room {
+            hotel_code: String::from("BER00002"),
+            source: String::from("IHG"),
+            room_name: String::from(room_name),
+            room_code: String::from("BER898"),
+            valid_from: None,
+            valid_to: None,
+        }
+    }
+
+    fn hotel() -> Hotel {
+        Hotel {
+            id: String::from("BER00002"),
+            city_code: String::from("BER"),
+            name: String::from("Crowne Plaza Berlin City Centre"),
+            category: 4.0,
+            country_code: String::from("DE"),
+            city: String::from("Berlin"),
+            valid_from: None,
+            valid_to: None,
+        }
+    }
+
+    fn change(action: Action, entry: Entry) -> Override {
+        Override { action, entry }
+    }
+
+    #[test]
+    fn add_replace_and_delete() -> Result<()> {
+        let mut rooms: RoomDataSource = DataSource::new();
+        let mut hotels: HotelDataSource = DataSource::new();
+        let key = room("").key();
+
+        apply_overrides(
+            &[
+                change(Action::Add, Entry::Room(room("Einzelzimmer"))),
+                change(Action::Add, Entry::Hotel(hotel())),
+                change(Action::Replace, Entry::Room(room("Single Room"))),
+            ],
+            &mut rooms,
+            &mut hotels,
+        )?;
+        assert_eq!(rooms.find(&key), Some(&room("Single Room")));
+        assert_eq!(hotels.find(&String::from("BER00002")), Some(&hotel()));
+
+        apply_overrides(
+            &[change(Action::Delete, Entry::Room(room("")))],
+            &mut rooms,
+            &mut hotels,
+        )?;
+        assert_eq!(rooms.find(&key), None);
+        Ok(())
+    }
+
+    #[test]
+    fn reject_inconsistent_overrides() {
+        let mut rooms: RoomDataSource = DataSource::new();
+        let mut hotels: HotelDataSource = DataSource::new();
+        let error = apply_overrides(
+            &[
+                change(Action::Add, Entry::Room(room("Einzelzimmer"))),
+                change(Action::Delete, Entry::Hotel(hotel())),
+            ],
+            &mut rooms,
+            &mut hotels,
+        )
+        .expect_err("This should fail");
+        assert_eq!(
+            format!("{:#}", error),
+            "Couldn't apply the override at line 2: Can't change hotel BER00002, it doesn't exist!"
+        );
+    }
+
+    #[test]
+    fn deserialize_override() {
+        assert_eq!(
+            serde_json::from_str::<Override>(
+                r#"{"action":"replace","room":{"hotel_code":"BER00002","source":"IHG","room_name":"Einzelzimmer","room_code":"BER898"}}"#
+            )
+            .expect("Couldn't deserialize given json"),
+            change(Action::Replace, Entry::Room(room("Einzelzimmer")))
+        );
+    }
+}
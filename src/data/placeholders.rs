@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data::entities::{Hotel, Input, Room};
+
+/// What happens with the record that links to a room or hotel that's missing in the reference data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OnMissing {
+    /// It's rejected
+    Reject,
+    /// It's enriched with the placeholders of the missing room or hotel
+    Placeholder,
+}
+
+impl OnMissing {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "reject" => Ok(OnMissing::Reject),
+            "placeholder" => Ok(OnMissing::Placeholder),
+            _ => Err(format!(
+                "expected one of: reject, placeholder, got '{}'",
+                name
+            )),
+        }
+    }
+}
+
+/// Whether the room and hotel of the record were found, or their placeholders were used instead.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchStatus {
+    Matched,
+    MissingRoom,
+    MissingHotel,
+    MissingRoomAndHotel,
+}
+
+impl MatchStatus {
+    pub fn new(room_found: bool, hotel_found: bool) -> Self {
+        match (room_found, hotel_found) {
+            (true, true) => MatchStatus::Matched,
+            (false, true) => MatchStatus::MissingRoom,
+            (true, false) => MatchStatus::MissingHotel,
+            (false, false) => MatchStatus::MissingRoomAndHotel,
+        }
+    }
+}
+
+/// Values used in place of the rooms and hotels that are missing in the reference data,
+/// so the partially mapped feeds still end up in the output, e.g during the onboarding of a supplier.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placeholders {
+    pub room_name: String,
+    pub hotel_name: String,
+}
+
+impl Placeholders {
+    /// Placeholder of the room, the codes are taken from the input.
+    pub fn room(&self, input: &Input) -> Room {
+        Room {
+            hotel_code: input.hotel_code.clone(),
+            source: input.source.clone(),
+            room_name: self.room_name.clone(),
+            room_code: input.room_code.clone(),
+            valid_from: None,
+            valid_to: None,
+        }
+    }
+
+    /// Placeholder of the hotel, its city and category are unknown, so they are left empty and 0.
+    pub fn hotel(&self, input: &Input) -> Hotel {
+        Hotel {
+            id: input.hotel_code.clone(),
+            city_code: input.city_code.clone(),
+            name: self.hotel_name.clone(),
+            category: 0.0,
+            country_code: String::new(),
+            city: String::new(),
+            valid_from: None,
+            valid_to: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_of_match() {
+        assert_eq!(MatchStatus::new(true, true), MatchStatus::Matched);
+        assert_eq!(MatchStatus::new(false, true), MatchStatus::MissingRoom);
+        assert_eq!(MatchStatus::new(true, false), MatchStatus::MissingHotel);
+        assert_eq!(
+            MatchStatus::new(false, false),
+            MatchStatus::MissingRoomAndHotel
+        );
+        assert_eq!(
+            OnMissing::from_name("ignore").err(),
+            Some(String::from(
+                "expected one of: reject, placeholder, got 'ignore'"
+            ))
+        );
+    }
+}
@@ -3,8 +3,9 @@ use std::path::Path;
 
 use anyhow::{Context, Result};
 use csv::ReaderBuilder;
+use serde::Deserialize;
 
-use crate::data::{Hotel, Room};
+use crate::data::{Chain, Coordinates, Hotel, Room, Translation};
 
 /// Function used to read hotel data from a file which is not a valid json,
 /// but each line is a valid json object.
@@ -27,6 +28,46 @@ pub fn hotels_reader(path: &Path) -> Result<Vec<(String, Hotel)>> {
         .collect()
 }
 
+/// Hotels of a single YAML document, it might be either one hotel or a list of them.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HotelsDocument {
+    One(Hotel),
+    Many(Vec<Hotel>),
+}
+
+/// Function used to read hotel data from a YAML file, which might contain many documents separated with `---`.
+/// It throws an error if the file doesn't exist at specified path or if
+/// it encounters data that isn't in the format of the Hotel.
+pub fn hotels_yaml_reader(path: &Path) -> Result<Vec<(String, Hotel)>> {
+    let content = read_to_string(path).with_context(|| "Path to the hotels data is invalid!")?;
+    let mut hotels = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(&content) {
+        match HotelsDocument::deserialize(document)
+            .with_context(|| "Encountered unparsable entity during parsing hotels data.")?
+        {
+            HotelsDocument::One(hotel) => hotels.push(hotel),
+            HotelsDocument::Many(many) => hotels.extend(many),
+        }
+    }
+    Ok(hotels
+        .into_iter()
+        .map(|hotel| (hotel.id.clone(), hotel))
+        .collect())
+}
+
+pub type HotelsReader = fn(&Path) -> Result<Vec<(String, Hotel)>>;
+
+/// Pick the reader of the hotels data by the extension of the file, JSON lines are the default.
+pub fn hotels_reader_for(location: &str) -> HotelsReader {
+    let location = location.to_lowercase();
+    if location.ends_with(".yaml") || location.ends_with(".yml") {
+        hotels_yaml_reader
+    } else {
+        hotels_reader
+    }
+}
+
 /// Function used to read rooms data from a CSV file.
 /// It throws an error if the file doesn't exist at specified path or if
 /// it encounters data that isn't in the format of the Room.
@@ -34,6 +75,8 @@ pub fn rooms_reader(path: &Path) -> Result<Vec<(String, Room)>> {
     let mut csv_reader = ReaderBuilder::new()
         .has_headers(false)
         .delimiter(b'|')
+        // valid_from and valid_to are given only for the rooms that are valid for some time
+        .flexible(true)
         .from_path(path)
         .with_context(|| "Path to the rooms data is invalid!")?;
 
@@ -46,8 +89,71 @@ pub fn rooms_reader(path: &Path) -> Result<Vec<(String, Room)>> {
         .collect()
 }
 
+/// Function used to read coordinates of the hotels from a CSV file.
+/// It throws an error if the file doesn't exist at specified path or if
+/// it encounters data that isn't in the format of the Coordinates.
+pub fn coordinates_reader(path: &Path) -> Result<Vec<(String, Coordinates)>> {
+    let mut csv_reader = ReaderBuilder::new()
+        .has_headers(false)
+        .delimiter(b'|')
+        .from_path(path)
+        .with_context(|| "Path to the coordinates data is invalid!")?;
+
+    csv_reader
+        .deserialize::<Coordinates>()
+        .map(|res| {
+            res.map(|coordinates| (coordinates.hotel_id.clone(), coordinates))
+                .with_context(|| "Encountered unparsable entity during parsing coordinates data.")
+        })
+        .collect()
+}
+
+/// Function used to read chains and brands of the hotels from a CSV file.
+/// It throws an error if the file doesn't exist at specified path or if
+/// it encounters data that isn't in the format of the Chain.
+pub fn chains_reader(path: &Path) -> Result<Vec<(String, Chain)>> {
+    let mut csv_reader = ReaderBuilder::new()
+        .has_headers(false)
+        .delimiter(b'|')
+        .from_path(path)
+        .with_context(|| "Path to the chains data is invalid!")?;
+
+    csv_reader
+        .deserialize::<Chain>()
+        .map(|res| {
+            res.map(|chain| (chain.hotel_id.clone(), chain))
+                .with_context(|| "Encountered unparsable entity during parsing chains data.")
+        })
+        .collect()
+}
+
+/// Function used to read translations of the hotels data from a CSV file.
+/// It throws an error if the file doesn't exist at specified path or if
+/// it encounters data that isn't in the format of the Translation.
+pub fn translations_reader(path: &Path) -> Result<Vec<(String, Translation)>> {
+    let mut csv_reader = ReaderBuilder::new()
+        .has_headers(false)
+        .delimiter(b'|')
+        .from_path(path)
+        .with_context(|| "Path to the translations data is invalid!")?;
+
+    csv_reader
+        .deserialize::<Translation>()
+        .map(|res| {
+            res.map(|translation| (translation.key(), translation))
+                .with_context(|| "Encountered unparsable entity during parsing translations data.")
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
+    use std::fs::write;
+
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use tempfile::NamedTempFile;
+
     use super::*;
 
     #[test]
@@ -64,7 +170,9 @@ mod tests {
                         hotel_code: String::from("BER00003"),
                         room_code: String::from("BER849"),
                         source: String::from("MARR"),
-                        room_name: String::from("Single Standard")
+                        room_name: String::from("Single Standard"),
+                        valid_from: None,
+                        valid_to: None,
                     }
                 ),
                 (
@@ -73,7 +181,9 @@ mod tests {
                         hotel_code: String::from("BER00003"),
                         room_code: String::from("BER848"),
                         source: String::from("MARR"),
-                        room_name: String::from("Deluxe King")
+                        room_name: String::from("Deluxe King"),
+                        valid_from: None,
+                        valid_to: None,
                     }
                 ),
                 (
@@ -82,7 +192,9 @@ mod tests {
                         hotel_code: String::from("BER00003"),
                         room_code: String::from("BER848"),
                         source: String::from("DOTW"),
-                        room_name: String::from("SINGLE DELUXE")
+                        room_name: String::from("SINGLE DELUXE"),
+                        valid_from: None,
+                        valid_to: None,
                     }
                 ),
                 (
@@ -91,7 +203,9 @@ mod tests {
                         hotel_code: String::from("BER00002"),
                         room_code: String::from("BER898"),
                         source: String::from("GTA"),
-                        room_name: String::from("Standard")
+                        room_name: String::from("Standard"),
+                        valid_from: None,
+                        valid_to: None,
                     }
                 ),
                 (
@@ -100,7 +214,9 @@ mod tests {
                         hotel_code: String::from("BER00002"),
                         room_code: String::from("BER898"),
                         source: String::from("IHG"),
-                        room_name: String::from("Einzelzimmer")
+                        room_name: String::from("Einzelzimmer"),
+                        valid_from: None,
+                        valid_to: None,
                     }
                 ),
                 (
@@ -109,7 +225,9 @@ mod tests {
                         hotel_code: String::from("BER00002"),
                         room_code: String::from("BER848"),
                         source: String::from("MARR"),
-                        room_name: String::from("Deluxe King Extra")
+                        room_name: String::from("Deluxe King Extra"),
+                        valid_from: None,
+                        valid_to: None,
                     }
                 )
             ]
@@ -136,6 +254,95 @@ mod tests {
         );
     }
 
+    #[test]
+    fn read_coordinates() {
+        let data = coordinates_reader(Path::new("test_data/coordinates.csv"))
+            .expect("Couldn't read coordinates from given path");
+
+        assert_eq!(
+            data,
+            vec![
+                (
+                    String::from("BER00002"),
+                    Coordinates {
+                        hotel_id: String::from("BER00002"),
+                        latitude: 52.50424,
+                        longitude: 13.33224
+                    }
+                ),
+                (
+                    String::from("BER00003"),
+                    Coordinates {
+                        hotel_id: String::from("BER00003"),
+                        latitude: 52.50957,
+                        longitude: 13.37938
+                    }
+                )
+            ]
+        )
+    }
+
+    #[test]
+    fn read_chains() {
+        let data = chains_reader(Path::new("test_data/chains.csv"))
+            .expect("Couldn't read chains from given path");
+
+        assert_eq!(
+            data[1],
+            (
+                String::from("BER00003"),
+                Chain {
+                    hotel_id: String::from("BER00003"),
+                    chain_code: String::from("MAR"),
+                    brand_name: String::from("Marriott Hotels"),
+                }
+            )
+        );
+        assert_eq!(data.len(), 2);
+    }
+
+    #[test]
+    fn read_coordinates_in_invalid_format() {
+        assert_eq!(
+            coordinates_reader(Path::new("test_data/invalid_rooms_data.csv"))
+                .expect_err("This should fail")
+                .to_string(),
+            "Encountered unparsable entity during parsing coordinates data.",
+        );
+    }
+
+    #[test]
+    fn read_translations() {
+        let data = translations_reader(Path::new("test_data/translations.csv"))
+            .expect("Couldn't read translations from given path");
+
+        assert_eq!(data.len(), 4);
+        assert_eq!(
+            data[2],
+            (
+                String::from("BER00003-de"),
+                Translation {
+                    hotel_id: String::from("BER00003"),
+                    language: String::from("de"),
+                    name: String::from("Berlin Marriott Hotel"),
+                    city: String::new()
+                }
+            )
+        );
+        assert_eq!(
+            data[3],
+            (
+                String::from("BER00003-pl"),
+                Translation {
+                    hotel_id: String::from("BER00003"),
+                    language: String::from("pl"),
+                    name: String::from("Hotel Marriott w Berlinie"),
+                    city: String::from("Berlin")
+                }
+            )
+        );
+    }
+
     #[test]
     fn read_hotels() {
         let data = hotels_reader(Path::new("test_data/hotels.json"))
@@ -152,7 +359,9 @@ mod tests {
                         name: String::from("Crowne Plaza Berlin City Centre"),
                         category: 4.0,
                         country_code: String::from("DE"),
-                        city: String::from("Berlin")
+                        city: String::from("Berlin"),
+                        valid_from: None,
+                        valid_to: None,
                     }
                 ),
                 (
@@ -163,13 +372,33 @@ mod tests {
                         name: String::from("Berlin Marriott Hotel"),
                         category: 5.0,
                         country_code: String::from("DE"),
-                        city: String::from("Berlin")
+                        city: String::from("Berlin"),
+                        valid_from: None,
+                        valid_to: None,
                     }
                 )
             ]
         )
     }
 
+    #[test]
+    fn read_hotels_from_yaml() {
+        let json = hotels_reader(Path::new("test_data/hotels.json"))
+            .expect("Couldn't read hotels from given path");
+        let yaml = hotels_yaml_reader(Path::new("test_data/hotels.yaml"))
+            .expect("Couldn't read hotels from given path");
+        assert_eq!(yaml, json);
+    }
+
+    #[test]
+    fn pick_hotels_reader_by_extension() {
+        let yaml = Path::new("test_data/hotels.yaml");
+        assert!(hotels_reader_for("hotels.yaml")(yaml).is_ok());
+        assert!(hotels_reader_for("https://example.com/HOTELS.YML")(yaml).is_ok());
+        // YAML is not a valid JSON
+        assert!(hotels_reader_for("hotels.json")(yaml).is_err());
+    }
+
     #[test]
     fn read_hotels_from_invalid_path() {
         assert_eq!(
@@ -189,4 +418,28 @@ mod tests {
             r#"Encountered unparsable entity during parsing hotels data at line: {"id": "BER00003", "city_code": "BER", "country_code": "DE", "city": "Berlin" }"#
         );
     }
+
+    proptest! {
+        // malformed quotes and records of the production feeds have to end up as errors, never as panics
+        #[test]
+        fn read_arbitrary_rooms(data in "([A-Z0-9 \"|,]{0,12}\n?){0,8}", bytes in vec(any::<u8>(), 0..256)) {
+            for content in [data.as_bytes(), &bytes] {
+                let file = NamedTempFile::new().unwrap();
+                write(file.path(), content).unwrap();
+                let _ = rooms_reader(file.path());
+                let _ = coordinates_reader(file.path());
+                let _ = translations_reader(file.path());
+            }
+        }
+
+        #[test]
+        fn read_arbitrary_hotels(data in "([{}\":,A-Za-z0-9. -]{0,24}\n?){0,8}", bytes in vec(any::<u8>(), 0..256)) {
+            for content in [data.as_bytes(), &bytes] {
+                let file = NamedTempFile::new().unwrap();
+                write(file.path(), content).unwrap();
+                let _ = hotels_reader(file.path());
+                let _ = hotels_yaml_reader(file.path());
+            }
+        }
+    }
 }
@@ -0,0 +1,47 @@
+use std::marker::PhantomData;
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+
+use crate::data::Lookup;
+
+/// Lookup of the items saved as JSON in a Redis (or keydb) hash, under the same keys as in the data sources,
+/// e.g rooms under `BER00002-BER898-IHG` and hotels under `BER00002`.
+pub struct RedisLookup<I> {
+    connection: redis::Connection,
+    hash: String,
+    item: PhantomData<fn() -> I>,
+}
+
+impl<I> RedisLookup<I> {
+    pub fn connect(url: &str, hash: &str) -> Result<Self> {
+        let connection = redis::Client::open(url)
+            .and_then(|client| client.get_connection())
+            .with_context(|| format!("Couldn't connect to Redis at {}!", url))?;
+        Ok(Self {
+            connection,
+            hash: hash.to_string(),
+            item: PhantomData,
+        })
+    }
+}
+
+impl<I: DeserializeOwned> Lookup<String, I> for RedisLookup<I> {
+    fn fetch(&mut self, key: &String) -> Result<Option<I>> {
+        let value: Option<String> = redis::cmd("HGET")
+            .arg(&self.hash)
+            .arg(key)
+            .query(&mut self.connection)
+            .with_context(|| format!("Couldn't look up {} in the {} hash!", key, self.hash))?;
+        value
+            .map(|value| {
+                serde_json::from_str(&value).with_context(|| {
+                    format!(
+                        "Encountered unparsable entity in the {} hash at: {}",
+                        self.hash, key
+                    )
+                })
+            })
+            .transpose()
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectReason {
+    /// Record couldn't be read or deserialized into the Input
+    Unparsable,
+    /// Input links to a room that doesn't exist in the rooms data source
+    MissingRoom,
+    /// Input links to a hotel that doesn't exist in the hotels data source
+    MissingHotel,
+    /// One of the fields of the input isn't in the expected format, e.g a negative price
+    InvalidField,
+    /// Record is the same as one of the records before it
+    Duplicate,
+    /// Record takes more bytes than it's allowed to
+    RecordTooLarge,
+}
+
+impl RejectReason {
+    /// Name of the reason, the same one that's used in the rejects file.
+    pub fn code(self) -> &'static str {
+        match self {
+            RejectReason::Unparsable => "unparsable",
+            RejectReason::MissingRoom => "missing_room",
+            RejectReason::MissingHotel => "missing_hotel",
+            RejectReason::InvalidField => "invalid_field",
+            RejectReason::Duplicate => "duplicate",
+            RejectReason::RecordTooLarge => "record_too_large",
+        }
+    }
+}
+
+/// Record of the input data that couldn't be enriched.
+/// It's the error returned by the DataIntegrator and it's also what gets saved in the rejects file,
+/// so the rejected records can be inspected and fixed later on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reject {
+    /// Line of the input file where the record begins, if it's known
+    pub line: Option<u64>,
+    pub reason: RejectReason,
+    pub message: String,
+    /// Raw values of the record by the name of the column
+    pub record: BTreeMap<String, String>,
+    /// Where exactly in the record the problem is, when it's known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<Snippet>,
+}
+
+/// Line of the input with the offending value of the record pointed out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snippet {
+    /// Values of the record joined by the delimiter of the input
+    pub source: String,
+    /// Column of the offending value
+    pub column: String,
+    /// Characters of the source taken by the offending value
+    pub start: usize,
+    pub len: usize,
+    /// What was expected instead of the offending value
+    pub hint: String,
+}
+
+impl Snippet {
+    pub fn new(
+        headers: &StringRecord,
+        record: &StringRecord,
+        index: usize,
+        delimiter: char,
+        hint: String,
+    ) -> Self {
+        let start = record
+            .iter()
+            .take(index)
+            .map(|value| value.chars().count() + 1)
+            .sum();
+        Self {
+            source: record
+                .iter()
+                .collect::<Vec<_>>()
+                .join(&delimiter.to_string()),
+            column: headers
+                .get(index)
+                .map_or_else(|| format!("field_{}", index + 1), String::from),
+            start,
+            len: record.get(index).map_or(0, |value| value.chars().count()),
+            hint,
+        }
+    }
+}
+
+impl Reject {
+    pub fn new(
+        reason: RejectReason,
+        message: String,
+        line: Option<u64>,
+        headers: &StringRecord,
+        record: Option<&StringRecord>,
+    ) -> Self {
+        let record = match record {
+            Some(record) => record
+                .iter()
+                .enumerate()
+                .map(|(index, value)| {
+                    let column = headers
+                        .get(index)
+                        .map_or_else(|| format!("field_{}", index + 1), String::from);
+                    (column, value.to_string())
+                })
+                .collect(),
+            None => BTreeMap::new(),
+        };
+        Self {
+            line,
+            reason,
+            message,
+            record,
+            snippet: None,
+        }
+    }
+
+    /// Point out the offending value of the record.
+    pub fn with_snippet(mut self, snippet: Snippet) -> Self {
+        self.snippet = Some(snippet);
+        self
+    }
+
+    /// Message of the reject together with the line of the input where the problem is, e.g
+    /// ```text
+    /// Input contains data that can't be deserialized!
+    ///  --> line 3, column checkin
+    ///   |
+    /// 3 | BER|BER00002|EZ|BER898|F|2019-07-30|1|0|85.50|IHG
+    ///   |                          ^^^^^^^^^^ expected date in %Y%m%d format, got '2019-07-30'
+    /// ```
+    pub fn diagnostic(&self) -> String {
+        let snippet = match &self.snippet {
+            Some(snippet) => snippet,
+            None => return self.message.clone(),
+        };
+        let line = self
+            .line
+            .map_or_else(|| String::from("?"), |line| line.to_string());
+        let margin = " ".repeat(line.len());
+        format!(
+            "{}\n{} --> line {}, column {}\n{} |\n{} | {}\n{} | {}{} {}",
+            self.message,
+            margin,
+            line,
+            snippet.column,
+            margin,
+            line,
+            snippet.source,
+            margin,
+            " ".repeat(snippet.start),
+            "^".repeat(snippet.len.max(1)),
+            snippet.hint
+        )
+    }
+
+    /// Raw value of the given column of the rejected record, or an empty string if there's none.
+    pub fn value(&self, column: &str) -> &str {
+        self.record.get(column).map_or("", String::as_str)
+    }
+}
+
+impl fmt::Display for Reject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Reject {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_values_by_columns() {
+        let headers = StringRecord::from(vec!["hotel_code", "room_code"]);
+        let record = StringRecord::from(vec!["BER00002", "BER898", "IHG"]);
+        let reject = Reject::new(
+            RejectReason::MissingRoom,
+            String::from("Input links to a non existent room"),
+            Some(2),
+            &headers,
+            Some(&record),
+        );
+
+        assert_eq!(reject.value("hotel_code"), "BER00002");
+        assert_eq!(reject.value("room_code"), "BER898");
+        assert_eq!(reject.value("field_3"), "IHG");
+        assert_eq!(reject.value("source"), "");
+        assert_eq!(reject.to_string(), "Input links to a non existent room");
+        assert_eq!(reject.diagnostic(), "Input links to a non existent room");
+    }
+
+    #[test]
+    fn point_out_offending_value() {
+        let headers = StringRecord::from(vec!["hotel_code", "checkin", "adults"]);
+        let record = StringRecord::from(vec!["BER00002", "2019-07-30", "1"]);
+        let reject = Reject::new(
+            RejectReason::Unparsable,
+            String::from("Input contains data that can't be deserialized!"),
+            Some(3),
+            &headers,
+            Some(&record),
+        )
+        .with_snippet(Snippet::new(
+            &headers,
+            &record,
+            1,
+            '|',
+            String::from("expected date in %Y%m%d format, got '2019-07-30'"),
+        ));
+
+        assert_eq!(
+            reject.diagnostic(),
+            concat!(
+                "Input contains data that can't be deserialized!\n",
+                "  --> line 3, column checkin\n",
+                "  |\n",
+                "3 | BER00002|2019-07-30|1\n",
+                "  |          ^^^^^^^^^^ expected date in %Y%m%d format, got '2019-07-30'"
+            )
+        );
+    }
+}
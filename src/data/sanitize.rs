@@ -0,0 +1,184 @@
+use csv::StringRecord;
+
+use crate::data::entities::{Hotel, Room};
+
+/// Cleanup of the whitespace in the string fields of the input and the reference data,
+/// e.g the codes padded with spaces ("BER00003 ") or the room names with stray tabs.
+/// It's applied before the rooms and hotels are looked up, so such codes still match.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Sanitizer {
+    /// Whitespace at both ends of the field is removed
+    pub trim: bool,
+    /// Runs of whitespace inside of the field are replaced with a single space
+    pub collapse: bool,
+    /// Control characters (other than the whitespace ones) are removed
+    pub strip_control: bool,
+}
+
+impl Sanitizer {
+    /// Read the steps of the sanitization from a comma separated list, e.g `trim,collapse,control`.
+    /// `all` turns on all of them and `none` none of them.
+    pub fn from_names(names: &str) -> Result<Self, String> {
+        let mut sanitizer = Sanitizer::default();
+        for name in names.split(',').map(str::trim) {
+            match name {
+                "none" => {}
+                "all" => {
+                    sanitizer = Sanitizer {
+                        trim: true,
+                        collapse: true,
+                        strip_control: true,
+                    }
+                }
+                "trim" => sanitizer.trim = true,
+                "collapse" => sanitizer.collapse = true,
+                "control" => sanitizer.strip_control = true,
+                _ => {
+                    return Err(format!(
+                        "expected a list of: trim, collapse, control, all, none, got '{}'",
+                        name
+                    ))
+                }
+            }
+        }
+        Ok(sanitizer)
+    }
+
+    /// Whether any of the steps is turned on.
+    pub fn is_enabled(&self) -> bool {
+        self.trim || self.collapse || self.strip_control
+    }
+
+    /// Sanitized value of the single field.
+    pub fn apply(&self, value: &str) -> String {
+        let mut sanitized = String::with_capacity(value.len());
+        let mut whitespace = false;
+        for c in value.chars() {
+            if self.strip_control && c.is_control() && !c.is_whitespace() {
+                continue;
+            }
+            if self.collapse && c.is_whitespace() {
+                whitespace = true;
+                continue;
+            }
+            if whitespace {
+                sanitized.push(' ');
+                whitespace = false;
+            }
+            sanitized.push(c);
+        }
+        // collapsed whitespace at the end is kept as a single space unless it's trimmed
+        if whitespace {
+            sanitized.push(' ');
+        }
+        if self.trim {
+            sanitized.trim().to_string()
+        } else {
+            sanitized
+        }
+    }
+
+    /// Record of the input with all of its fields sanitized.
+    pub fn record(&self, record: &StringRecord) -> StringRecord {
+        record.iter().map(|value| self.apply(value)).collect()
+    }
+
+    pub fn room(&self, room: Room) -> Room {
+        Room {
+            hotel_code: self.apply(&room.hotel_code),
+            source: self.apply(&room.source),
+            room_name: self.apply(&room.room_name),
+            room_code: self.apply(&room.room_code),
+            valid_from: room.valid_from,
+            valid_to: room.valid_to,
+        }
+    }
+
+    pub fn hotel(&self, hotel: Hotel) -> Hotel {
+        Hotel {
+            id: self.apply(&hotel.id),
+            city_code: self.apply(&hotel.city_code),
+            name: self.apply(&hotel.name),
+            category: hotel.category,
+            country_code: self.apply(&hotel.country_code),
+            city: self.apply(&hotel.city),
+            valid_from: hotel.valid_from,
+            valid_to: hotel.valid_to,
+        }
+    }
+
+    /// Sanitized rooms read by one of the readers, the keys are generated again from the sanitized codes.
+    pub fn rooms(&self, rooms: Vec<(String, Room)>) -> Vec<(String, Room)> {
+        if !self.is_enabled() {
+            return rooms;
+        }
+        rooms
+            .into_iter()
+            .map(|(_, room)| {
+                let room = self.room(room);
+                (room.key(), room)
+            })
+            .collect()
+    }
+
+    /// Sanitized hotels read by one of the readers, the keys are their sanitized ids.
+    pub fn hotels(&self, hotels: Vec<(String, Hotel)>) -> Vec<(String, Hotel)> {
+        if !self.is_enabled() {
+            return hotels;
+        }
+        hotels
+            .into_iter()
+            .map(|(_, hotel)| {
+                let hotel = self.hotel(hotel);
+                (hotel.id.clone(), hotel)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::entities::generate_room_key;
+
+    #[test]
+    fn sanitize_values() -> Result<(), String> {
+        let all = Sanitizer::from_names("all")?;
+        assert_eq!(all.apply(" BER00003 "), "BER00003");
+        assert_eq!(
+            all.apply("Double\t\tDeluxe\u{0}  Room\n"),
+            "Double Deluxe Room"
+        );
+
+        let trim = Sanitizer::from_names("trim")?;
+        assert_eq!(trim.apply(" Double\t Room "), "Double\t Room");
+
+        let collapse = Sanitizer::from_names("collapse,control")?;
+        assert_eq!(collapse.apply(" Double\t\u{7}Room "), " Double Room ");
+
+        assert!(!Sanitizer::from_names("none")?.is_enabled());
+        assert_eq!(
+            Sanitizer::from_names("trim,squash").err(),
+            Some(String::from(
+                "expected a list of: trim, collapse, control, all, none, got 'squash'"
+            ))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sanitize_room_keys() {
+        let sanitizer = Sanitizer::from_names("trim").unwrap();
+        let room = Room {
+            hotel_code: String::from("BER00003 "),
+            source: String::from(" IHG"),
+            room_name: String::from("Double Room"),
+            room_code: String::from("BER848\t"),
+            valid_from: None,
+            valid_to: None,
+        };
+        let rooms = sanitizer.rooms(vec![(room.key(), room)]);
+        assert_eq!(rooms[0].0, generate_room_key("BER00003", "BER848", "IHG"));
+        assert_eq!(rooms[0].1.room_code, "BER848");
+    }
+}
@@ -0,0 +1,354 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Lines, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::mem::size_of;
+
+use anyhow::{anyhow, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::data::{Hotel, Lookup, Output, Room};
+
+/// How many of the spilled items there are between the keys that are kept in memory.
+const INDEX_STEP: usize = 64;
+
+/// Approximate number of bytes the item takes in memory, together with whatever it owns on the heap.
+pub trait ApproximateSize {
+    fn approximate_size(&self) -> usize;
+}
+
+impl ApproximateSize for String {
+    fn approximate_size(&self) -> usize {
+        size_of::<Self>() + self.capacity()
+    }
+}
+
+impl ApproximateSize for Room {
+    fn approximate_size(&self) -> usize {
+        size_of::<Self>()
+            + self.hotel_code.capacity()
+            + self.source.capacity()
+            + self.room_name.capacity()
+            + self.room_code.capacity()
+    }
+}
+
+impl ApproximateSize for Hotel {
+    fn approximate_size(&self) -> usize {
+        size_of::<Self>()
+            + self.id.capacity()
+            + self.city_code.capacity()
+            + self.name.capacity()
+            + self.country_code.capacity()
+            + self.city.capacity()
+    }
+}
+
+impl ApproximateSize for Output {
+    fn approximate_size(&self) -> usize {
+        let optional = |value: &Option<String>| value.as_ref().map_or(0, String::capacity);
+        size_of::<Self>()
+            + self.room_type_meal.capacity()
+            + self.room_code.capacity()
+            + self.source.capacity()
+            + self.hotel_name.capacity()
+            + self.city_name.capacity()
+            + self.city_code.capacity()
+            + self.room_name.capacity()
+            + self.hotel_code.capacity()
+            + self.meal.capacity()
+            + self
+                .ranking
+                .as_ref()
+                .map_or(0, |ranking| ranking.winning_source.capacity())
+            + self
+                .candidate_room_names
+                .iter()
+                .map(ApproximateSize::approximate_size)
+                .sum::<usize>()
+            + optional(&self.chain_code)
+            + optional(&self.brand_name)
+            + self.warnings.capacity() * 64
+    }
+}
+
+/// Item together with the position by which it's sorted, e.g its index in the input.
+impl<A, B: ApproximateSize> ApproximateSize for (A, B) {
+    fn approximate_size(&self) -> usize {
+        size_of::<A>() + self.1.approximate_size()
+    }
+}
+
+/// Approximate limit of the memory of the run. Half of it is for the rooms and hotels, the rest is split
+/// between the cache of the ones spilled to the disk, the seen records of the duplicates detection
+/// and the records buffered by the consolidation and the anomalies detection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MemoryBudget(usize);
+
+impl MemoryBudget {
+    pub fn new(bytes: usize) -> Self {
+        Self(bytes)
+    }
+
+    pub fn reference_data(&self) -> usize {
+        self.0 / 2
+    }
+
+    pub fn cache(&self) -> usize {
+        self.0 / 8
+    }
+
+    /// How many of the seen records of the duplicates detection fit into its part of the budget,
+    /// each is kept as its 16 bytes long hash with as much of the set around it.
+    pub fn seen_records(&self) -> usize {
+        self.0 / 8 / 32
+    }
+
+    /// Budget of each of the buffers of the records.
+    pub fn buffer(&self) -> usize {
+        self.0 / 8
+    }
+}
+
+/// Items of the data source moved to a temporary file, sorted by their keys. Only every INDEX_STEP-th key
+/// is kept in memory, with the position of its item in the file, the others are read from the disk when they are looked up.
+/// Each line of the file is the key and the item as JSON, separated by a tab (a JSON string has no raw tabs).
+pub struct DiskLookup<I> {
+    file: BufReader<File>,
+    index: Vec<(String, u64)>,
+    items: PhantomData<fn() -> I>,
+}
+
+impl<I: Serialize> DiskLookup<I> {
+    /// Spill the items, which have to be sorted by their keys.
+    pub fn new(items: impl Iterator<Item = (String, I)>) -> Result<Self> {
+        let file = tempfile::tempfile().with_context(|| "Couldn't spill the data source!")?;
+        let mut writer = BufWriter::new(file);
+        let mut index = Vec::new();
+        let mut position = 0;
+        for (count, (key, item)) in items.enumerate() {
+            let line = format!(
+                "{}\t{}\n",
+                serde_json::to_string(&key)?,
+                serde_json::to_string(&item)?
+            );
+            if count % INDEX_STEP == 0 {
+                index.push((key, position));
+            }
+            writer.write_all(line.as_bytes())?;
+            position += line.len() as u64;
+        }
+        let file = writer
+            .into_inner()
+            .map_err(|e| e.into_error())
+            .with_context(|| "Couldn't spill the data source!")?;
+        Ok(Self {
+            file: BufReader::new(file),
+            index,
+            items: PhantomData,
+        })
+    }
+}
+
+impl<I: DeserializeOwned> Lookup<String, I> for DiskLookup<I> {
+    fn fetch(&mut self, key: &String) -> Result<Option<I>> {
+        // the item can only be between the last indexed key that's not after it and the next indexed key
+        let start = match self.index.partition_point(|(indexed, _)| indexed <= key) {
+            0 => return Ok(None),
+            next => self.index[next - 1].1,
+        };
+        self.file.seek(SeekFrom::Start(start))?;
+        let mut line = String::new();
+        for _ in 0..INDEX_STEP {
+            line.clear();
+            if self.file.read_line(&mut line)? == 0 {
+                break;
+            }
+            let (spilled_key, item) = line
+                .split_once('\t')
+                .ok_or_else(|| anyhow!("The spilled data source is corrupted!"))?;
+            let spilled_key: String = serde_json::from_str(spilled_key)?;
+            match spilled_key.cmp(key) {
+                std::cmp::Ordering::Less => continue,
+                std::cmp::Ordering::Equal => return Ok(Some(serde_json::from_str(item)?)),
+                std::cmp::Ordering::Greater => break,
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Sorted run of the items saved in a temporary file, one JSON per line.
+struct Run<T> {
+    lines: Lines<BufReader<File>>,
+    next: Option<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> Run<T> {
+    fn new(items: Vec<T>) -> Result<Self> {
+        let file = tempfile::tempfile().with_context(|| "Couldn't spill the records!")?;
+        let mut writer = BufWriter::new(file);
+        for item in &items {
+            serde_json::to_writer(&mut writer, item)?;
+            writer.write_all(b"\n")?;
+        }
+        let mut file = writer
+            .into_inner()
+            .map_err(|e| e.into_error())
+            .with_context(|| "Couldn't spill the records!")?;
+        file.seek(SeekFrom::Start(0))?;
+        let mut run = Self {
+            lines: BufReader::new(file).lines(),
+            next: None,
+        };
+        run.advance()?;
+        Ok(run)
+    }
+
+    fn advance(&mut self) -> Result<()> {
+        self.next = match self.lines.next() {
+            Some(line) => Some(
+                serde_json::from_str(&line?)
+                    .with_context(|| "Couldn't read the spilled records!")?,
+            ),
+            None => None,
+        };
+        Ok(())
+    }
+}
+
+/// Items sorted by their keys, the ones that don't fit into the given number of bytes are sorted in runs
+/// on the disk, which are merged once all of the items are known. Items with the same key keep no particular order.
+pub fn sort_spilling<'a, T, K, F>(
+    items: impl Iterator<Item = Result<T>>,
+    key: F,
+    budget: usize,
+) -> Result<Box<dyn Iterator<Item = Result<T>> + 'a>>
+where
+    T: Serialize + DeserializeOwned + ApproximateSize + 'a,
+    K: Ord,
+    F: Fn(&T) -> K + 'a,
+{
+    let mut runs = Vec::new();
+    let mut buffer = Vec::new();
+    let mut size = 0;
+    for item in items {
+        let item = item?;
+        size += item.approximate_size();
+        buffer.push(item);
+        if size > budget {
+            buffer.sort_by_cached_key(&key);
+            runs.push(Run::new(std::mem::take(&mut buffer))?);
+            size = 0;
+        }
+    }
+    buffer.sort_by_cached_key(&key);
+    if runs.is_empty() {
+        return Ok(Box::new(buffer.into_iter().map(Ok)));
+    }
+    runs.push(Run::new(buffer)?);
+    Ok(Box::new(std::iter::from_fn(move || {
+        let smallest = runs
+            .iter()
+            .enumerate()
+            .filter_map(|(index, run)| run.next.as_ref().map(|next| (key(next), index)))
+            .min_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(_, index)| index)?;
+        let run = &mut runs[smallest];
+        let item = run.next.take();
+        Some(run.advance().map(|_| item.unwrap()))
+    })))
+}
+
+/// Consecutive items of the sorted ones that have the same key, e.g the offers of the same room.
+pub fn group_sorted<'a, T, K, F>(
+    sorted: impl Iterator<Item = Result<T>> + 'a,
+    key: F,
+) -> impl Iterator<Item = Result<Vec<T>>> + 'a
+where
+    T: 'a,
+    K: PartialEq,
+    F: Fn(&T) -> K + 'a,
+{
+    let mut sorted = sorted.peekable();
+    std::iter::from_fn(move || {
+        let first = match sorted.next()? {
+            Ok(first) => first,
+            Err(e) => return Some(Err(e)),
+        };
+        let group_key = key(&first);
+        let mut group = vec![first];
+        // an error ends the group, it's returned on its own in the next call
+        while let Some(Ok(next)) = sorted.peek() {
+            if key(next) != group_key {
+                break;
+            }
+            if let Some(Ok(next)) = sorted.next() {
+                group.push(next);
+            }
+        }
+        Some(Ok(group))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn room(room_code: &str) -> Room {
+        Room {
+            hotel_code: String::from("BER00002"),
+            source: String::from("IHG"),
+            room_name: format!("Room {}", room_code),
+            room_code: String::from(room_code),
+            valid_from: None,
+            valid_to: None,
+        }
+    }
+
+    #[test]
+    fn look_up_spilled_items() -> Result<()> {
+        let mut items: Vec<(String, Room)> = (0..200)
+            .map(|code| room(&format!("R{:03}", code * 2)))
+            .map(|room| (room.key(), room))
+            .collect();
+        items.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        let mut lookup = DiskLookup::new(items.clone().into_iter())?;
+        assert_eq!(lookup.index.len(), 4);
+        for (key, room) in items.iter().rev() {
+            assert_eq!(lookup.fetch(key)?.as_ref(), Some(room));
+        }
+        for missing in [
+            "BER00001-R000-IHG",
+            "BER00002-R001-IHG",
+            "BER00002-R999-IHG",
+        ] {
+            assert_eq!(lookup.fetch(&String::from(missing))?, None);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn sort_in_runs() -> Result<()> {
+        let items: Vec<(usize, String)> = (0..100)
+            .map(|index| (index, format!("{:02}", (index * 37) % 100)))
+            .collect();
+        let budget = 10 * items[0].approximate_size();
+        let sorted = sort_spilling(
+            items.clone().into_iter().map(Ok),
+            |(_, value)| value.clone(),
+            budget,
+        )?
+        .collect::<Result<Vec<_>>>()?;
+        let mut expected = items.clone();
+        expected.sort_by(|(_, a), (_, b)| a.cmp(b));
+        assert_eq!(sorted, expected);
+
+        // everything that fits is sorted in memory
+        let sorted = sort_spilling(items.into_iter().map(Ok), |(index, _)| *index, usize::MAX)?
+            .map(|item| item.map(|(index, _)| index))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(sorted, (0..100).collect::<Vec<_>>());
+        Ok(())
+    }
+}
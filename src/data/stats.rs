@@ -0,0 +1,195 @@
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+
+use anyhow::Result;
+use csv::Writer;
+
+use crate::data::entities::Price;
+use crate::data::{custom_date, NumberFormat, Output};
+
+/// Columns of the output by which the records can be grouped in the stats.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupBy {
+    CityCode,
+    CityName,
+    HotelCode,
+    HotelName,
+    RoomCode,
+    Source,
+    Meal,
+    Checkin,
+}
+
+impl GroupBy {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "city_code" => Ok(GroupBy::CityCode),
+            "city_name" => Ok(GroupBy::CityName),
+            "hotel_code" => Ok(GroupBy::HotelCode),
+            "hotel_name" => Ok(GroupBy::HotelName),
+            "room_code" => Ok(GroupBy::RoomCode),
+            "source" => Ok(GroupBy::Source),
+            "meal" => Ok(GroupBy::Meal),
+            "checkin" => Ok(GroupBy::Checkin),
+            _ => Err(format!(
+                "expected one of: city_code, city_name, hotel_code, hotel_name, room_code, source, meal, checkin, got '{}'",
+                name
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GroupBy::CityCode => "city_code",
+            GroupBy::CityName => "city_name",
+            GroupBy::HotelCode => "hotel_code",
+            GroupBy::HotelName => "hotel_name",
+            GroupBy::RoomCode => "room_code",
+            GroupBy::Source => "source",
+            GroupBy::Meal => "meal",
+            GroupBy::Checkin => "checkin",
+        }
+    }
+
+    /// Value of the column in the given record, as it's presented in the stats.
+    pub fn value(&self, output: &Output) -> String {
+        match self {
+            GroupBy::CityCode => output.city_code.clone(),
+            GroupBy::CityName => output.city_name.clone(),
+            GroupBy::HotelCode => output.hotel_code.clone(),
+            GroupBy::HotelName => output.hotel_name.clone(),
+            GroupBy::RoomCode => output.room_code.clone(),
+            GroupBy::Source => output.source.clone(),
+            GroupBy::Meal => output.meal.clone(),
+            GroupBy::Checkin => output
+                .checkin
+                .format(custom_date::OUTPUT_FORMAT)
+                .to_string(),
+        }
+    }
+}
+
+struct Aggregate {
+    rows: usize,
+    min_price: Price,
+    max_price: Price,
+    total_price: Price,
+    hotels: HashSet<String>,
+}
+
+impl Aggregate {
+    fn new() -> Self {
+        Self {
+            rows: 0,
+            min_price: Price::INFINITY,
+            max_price: Price::NEG_INFINITY,
+            total_price: 0.0,
+            hotels: HashSet::new(),
+        }
+    }
+
+    fn add(&mut self, output: &Output) {
+        self.rows += 1;
+        self.min_price = self.min_price.min(output.price);
+        self.max_price = self.max_price.max(output.price);
+        self.total_price += output.price;
+        if !self.hotels.contains(&output.hotel_code) {
+            self.hotels.insert(output.hotel_code.clone());
+        }
+    }
+}
+
+/// Aggregates of the enriched records, grouped by the chosen columns.
+/// For each of the groups it counts the records and distinct hotels and calculates the min, average and max
+/// of the price per person. Groups are sorted by the values of the columns they're grouped by.
+pub struct Stats {
+    group_by: Vec<GroupBy>,
+    groups: BTreeMap<Vec<String>, Aggregate>,
+}
+
+impl Stats {
+    pub fn new(group_by: Vec<GroupBy>) -> Self {
+        Self {
+            group_by,
+            groups: BTreeMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, output: &Output) {
+        let key = self
+            .group_by
+            .iter()
+            .map(|group_by| group_by.value(output))
+            .collect();
+        self.groups
+            .entry(key)
+            .or_insert_with(Aggregate::new)
+            .add(output);
+    }
+
+    /// Write the stats as CSV, one group per record. Returns how many groups were written.
+    /// Columns of the stats grouped by the given properties.
+    pub fn columns(group_by: &[GroupBy]) -> Vec<&'static str> {
+        group_by
+            .iter()
+            .map(GroupBy::name)
+            .chain([
+                "rows",
+                "min_price",
+                "avg_price",
+                "max_price",
+                "distinct_hotels",
+            ])
+            .collect()
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut Writer<W>, number: &NumberFormat) -> Result<usize> {
+        writer.write_record(Self::columns(&self.group_by))?;
+
+        for (key, aggregate) in &self.groups {
+            let values = [
+                aggregate.rows.to_string(),
+                number.format(aggregate.min_price),
+                number.format(aggregate.total_price / aggregate.rows as Price),
+                number.format(aggregate.max_price),
+                aggregate.hotels.len().to_string(),
+            ];
+            writer.write_record(key.iter().chain(values.iter()))?;
+        }
+        Ok(self.groups.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn aggregate_groups() -> Result<()> {
+        let mut stats = Stats::new(vec![GroupBy::CityCode, GroupBy::Source]);
+        stats.add(&testing::output().source("MARR").price(100.0).build());
+        stats.add(&testing::output().build());
+        stats.add(&testing::output().hotel_code("BER00003").price(78.0).build());
+        stats.add(&testing::output().price(70.5).build());
+
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(b';')
+            .from_writer(vec![]);
+        let groups = stats.write(&mut writer, &NumberFormat::from_locale("plain").unwrap())?;
+
+        assert_eq!(groups, 2);
+        assert_eq!(
+            String::from_utf8(writer.into_inner()?)?,
+            "city_code;source;rows;min_price;avg_price;max_price;distinct_hotels\n\
+             BER;IHG;3;70.50;78.00;85.50;2\n\
+             BER;MARR;1;100.00;100.00;100.00;1\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_column() {
+        assert!(GroupBy::from_name("price").is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::NaiveDate;
+
+use crate::data::entities::{Hotel, Room};
+
+/// Serde of the optional dates of the reference data, e.g 2018-07-01. Empty values are no dates.
+pub mod validity_date {
+    use chrono::NaiveDate;
+    use serde::{self, Deserialize, Deserializer, Serializer};
+
+    pub const FORMAT: &str = "%Y-%m-%d";
+
+    pub fn serialize<S>(date: &Option<NaiveDate>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match date {
+            Some(date) => serializer.serialize_str(&date.format(FORMAT).to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<NaiveDate>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Option::<String>::deserialize(deserializer)?;
+        match value.as_deref().map(str::trim) {
+            None | Some("") => Ok(None),
+            Some(value) => NaiveDate::parse_from_str(value, FORMAT)
+                .map(Some)
+                .map_err(|_| {
+                    serde::de::Error::custom(format!(
+                        "expected date in {} format, got '{}'",
+                        FORMAT, value
+                    ))
+                }),
+        }
+    }
+}
+
+/// Item of the reference data that's valid only for some time, e.g the room that got renamed.
+/// Both of the ends are included, a missing end is not bounded.
+pub trait Dated {
+    fn valid_from(&self) -> Option<NaiveDate>;
+    fn valid_to(&self) -> Option<NaiveDate>;
+
+    /// Whether it's limited in time at all.
+    fn is_dated(&self) -> bool {
+        self.valid_from().is_some() || self.valid_to().is_some()
+    }
+
+    /// Whether it's valid on the given day.
+    fn is_effective(&self, date: NaiveDate) -> bool {
+        self.valid_from().is_none_or(|from| from <= date)
+            && self.valid_to().is_none_or(|to| date <= to)
+    }
+}
+
+impl Dated for Room {
+    fn valid_from(&self) -> Option<NaiveDate> {
+        self.valid_from
+    }
+
+    fn valid_to(&self) -> Option<NaiveDate> {
+        self.valid_to
+    }
+}
+
+impl Dated for Hotel {
+    fn valid_from(&self) -> Option<NaiveDate> {
+        self.valid_from
+    }
+
+    fn valid_to(&self) -> Option<NaiveDate> {
+        self.valid_to
+    }
+}
+
+/// Versions of the items of the reference data that are valid only for some time, by their keys.
+/// The items without the validity stay in the data source, they are used when none of the versions is effective.
+/// Just like the items of the data sources, they are kept behind an Arc, so they can be shared.
+#[derive(Debug)]
+pub struct Versions<I> {
+    versions: Arc<HashMap<String, Vec<I>>>,
+}
+
+impl<I> Default for Versions<I> {
+    fn default() -> Self {
+        Self {
+            versions: Arc::new(HashMap::new()),
+        }
+    }
+}
+
+impl<I> Versions<I> {
+    /// Another handle to the same versions, they are shared with it instead of being copied.
+    pub fn share(&self) -> Self {
+        Self {
+            versions: Arc::clone(&self.versions),
+        }
+    }
+
+    /// Whether the versions are the same ones as those of the other handle.
+    #[cfg(test)]
+    pub(crate) fn shares_versions_with(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.versions, &other.versions)
+    }
+}
+
+impl<I: Dated + Clone> Versions<I> {
+    /// Keep the dated items read by one of the readers, the ones without the validity are returned.
+    pub fn split(&mut self, items: Vec<(String, I)>) -> Vec<(String, I)> {
+        let mut undated = Vec::with_capacity(items.len());
+        let versions = Arc::make_mut(&mut self.versions);
+        for (key, item) in items {
+            if item.is_dated() {
+                versions.entry(key).or_default().push(item);
+            } else {
+                undated.push((key, item));
+            }
+        }
+        undated
+    }
+
+    /// Version of the item effective on the given day, when the validities overlap the one that starts later wins.
+    pub fn find(&self, key: &str, date: NaiveDate) -> Option<&I> {
+        self.versions
+            .get(key)?
+            .iter()
+            .filter(|item| item.is_effective(date))
+            .max_by_key(|item| item.valid_from())
+    }
+
+    /// Whether there's any version of the item, effective on any day.
+    pub fn contains(&self, key: &str) -> bool {
+        self.versions.contains_key(key)
+    }
+
+    /// Keep only the versions for which the predicate holds.
+    pub fn retain(&mut self, mut keep: impl FnMut(&I) -> bool) {
+        Arc::make_mut(&mut self.versions).retain(|_, items| {
+            items.retain(|item| keep(item));
+            !items.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn room(room_name: &str, valid_from: Option<&str>, valid_to: Option<&str>) -> (String, Room) {
+        let date = |date: &str| NaiveDate::parse_from_str(date, validity_date::FORMAT).unwrap();
+        let room = Room {
+            hotel_code: String::from("BER00002"),
+            source: String::from("IHG"),
+            room_name: String::from(room_name),
+            room_code: String::from("BER898"),
+            valid_from: valid_from.map(date),
+            valid_to: valid_to.map(date),
+        };
+        (room.key(), room)
+    }
+
+    #[test]
+    fn find_effective_versions() {
+        let mut versions = Versions::default();
+        let undated = versions.split(vec![
+            room("Einzelzimmer", None, None),
+            room("Single Room", Some("2018-01-01"), Some("2018-06-30")),
+            room("Single Deluxe", Some("2018-07-01"), None),
+            room("Single Summer", Some("2018-07-15"), Some("2018-07-31")),
+        ]);
+        assert_eq!(undated.len(), 1);
+        let key = "BER00002-BER898-IHG";
+        let name = |versions: &Versions<Room>, date| {
+            versions.find(key, date).map(|room| room.room_name.clone())
+        };
+        assert_eq!(name(&versions, NaiveDate::from_ymd(2017, 12, 31)), None);
+        assert_eq!(
+            name(&versions, NaiveDate::from_ymd(2018, 6, 30)),
+            Some(String::from("Single Room"))
+        );
+        assert_eq!(
+            name(&versions, NaiveDate::from_ymd(2018, 7, 1)),
+            Some(String::from("Single Deluxe"))
+        );
+        // the version that starts later wins
+        assert_eq!(
+            name(&versions, NaiveDate::from_ymd(2018, 7, 20)),
+            Some(String::from("Single Summer"))
+        );
+        assert_eq!(
+            name(&versions, NaiveDate::from_ymd(2019, 1, 1)),
+            Some(String::from("Single Deluxe"))
+        );
+
+        versions.retain(|room| room.valid_to.is_some());
+        assert_eq!(name(&versions, NaiveDate::from_ymd(2019, 1, 1)), None);
+        assert!(versions.contains(key));
+    }
+}
@@ -0,0 +1,186 @@
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::data::entities::{Input, Price};
+use crate::data::Output;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    /// Offer for a day that has already passed
+    CheckinInPast,
+    /// Price per person above the limit given in the settings
+    HighPrice,
+    /// Hotel without a category, it's most likely missing in the hotels data
+    ZeroCategory,
+    /// Checkout of the input that's not as many nights after the checkin as the input says, the checkout is kept
+    CheckoutConflict,
+}
+
+impl WarningKind {
+    /// Name of the kind, the same one that's used in the warnings file.
+    pub fn code(self) -> &'static str {
+        match self {
+            WarningKind::CheckinInPast => "checkin_in_past",
+            WarningKind::HighPrice => "high_price",
+            WarningKind::ZeroCategory => "zero_category",
+            WarningKind::CheckoutConflict => "checkout_conflict",
+        }
+    }
+}
+
+/// Enriched record that looks suspicious, but is still saved in the output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Warning {
+    /// Line of the input file where the record begins
+    pub line: u64,
+    pub kind: WarningKind,
+    pub message: String,
+}
+
+/// Rules by which the enriched records are checked.
+#[derive(Debug, Clone)]
+pub struct WarningRules {
+    /// Offers with the checkin before this day are in the past
+    pub today: NaiveDate,
+    /// Highest price per person that is not suspicious
+    pub max_price: Price,
+}
+
+impl WarningRules {
+    pub fn check(&self, output: &Output, line: u64) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+        let mut warn = |kind, message| {
+            warnings.push(Warning {
+                line,
+                kind,
+                message,
+            })
+        };
+        if output.checkin < self.today {
+            warn(
+                WarningKind::CheckinInPast,
+                format!("Checkin {} is in the past", output.checkin),
+            );
+        }
+        if output.price > self.max_price {
+            warn(
+                WarningKind::HighPrice,
+                format!(
+                    "Price per person {} is higher than {}",
+                    output.price, self.max_price
+                ),
+            );
+        }
+        if output.hotel_category == 0.0 {
+            warn(
+                WarningKind::ZeroCategory,
+                format!("Hotel {} has no category", output.hotel_code),
+            );
+        }
+        warnings
+    }
+
+    /// Check the record of the input before it's enriched, when it's enriched anyway.
+    pub fn check_input(&self, input: &Input, line: u64) -> Vec<Warning> {
+        match (input.nights, input.checkout) {
+            (Some(nights), Some(checkout))
+                if input.checkin + Duration::days(nights.get() as i64) != checkout =>
+            {
+                vec![Warning {
+                    line,
+                    kind: WarningKind::CheckoutConflict,
+                    message: format!(
+                        "Checkout {} isn't {} nights after the checkin {}",
+                        checkout, nights, input.checkin
+                    ),
+                }]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn kinds(warnings: Vec<Warning>) -> Vec<WarningKind> {
+        warnings.into_iter().map(|warning| warning.kind).collect()
+    }
+
+    #[test]
+    fn warn_about_suspicious_records() {
+        let rules = WarningRules {
+            today: NaiveDate::from_ymd(2018, 7, 21),
+            max_price: 500.0,
+        };
+        assert_eq!(kinds(rules.check(&testing::output().build(), 2)), vec![]);
+        assert_eq!(
+            kinds(
+                rules.check(
+                    &testing::output()
+                        .checkin(NaiveDate::from_ymd(2018, 7, 20))
+                        .build(),
+                    2
+                )
+            ),
+            vec![WarningKind::CheckinInPast]
+        );
+        assert_eq!(
+            kinds(
+                rules.check(
+                    &testing::output()
+                        .checkin(NaiveDate::from_ymd(2018, 7, 22))
+                        .price(800.0)
+                        .hotel_category(0.0)
+                        .build(),
+                    2
+                )
+            ),
+            vec![WarningKind::HighPrice, WarningKind::ZeroCategory]
+        );
+    }
+
+    #[test]
+    fn warn_about_conflicting_checkout() {
+        let rules = WarningRules {
+            today: NaiveDate::from_ymd(2018, 7, 21),
+            max_price: 500.0,
+        };
+        let checkin = NaiveDate::from_ymd(2018, 7, 21);
+        let input = |nights, checkout| Input {
+            city_code: String::from("BER"),
+            hotel_code: String::from("BER00002"),
+            room_type: String::from("EZ"),
+            room_code: String::from("BER898"),
+            meal: String::from("F"),
+            checkin,
+            adults: 1,
+            children: 0,
+            price: 85.5,
+            source: String::from("IHG"),
+            nights: std::num::NonZeroU16::new(nights),
+            checkout,
+        };
+        assert_eq!(
+            rules.check_input(&input(2, Some(checkin + Duration::days(2))), 2),
+            vec![]
+        );
+        assert_eq!(
+            rules.check_input(&input(0, Some(checkin + Duration::days(5))), 2),
+            vec![]
+        );
+        assert_eq!(
+            rules.check_input(&input(2, Some(checkin + Duration::days(3))), 2),
+            vec![Warning {
+                line: 2,
+                kind: WarningKind::CheckoutConflict,
+                message: String::from(
+                    "Checkout 2018-07-24 isn't 2 nights after the checkin 2018-07-21"
+                ),
+            }]
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use csv::StringRecord;
+use quick_xml::events::Event;
+
+use crate::data::input::{RawInput, RawInputs, INPUT_COLUMNS};
+
+/// Read the records of the XML input, where each of the records is an element with the given name and its
+/// values are either child elements or attributes named after the columns of the input, e.g
+/// `<offer source="IHG"><hotel_code>BER00002</hotel_code>...</offer>`.
+/// The whole file is read at once, it throws an error if it's not a valid XML.
+pub fn xml_input(path: &Path, record_element: &str) -> Result<(StringRecord, RawInputs)> {
+    let content = read_to_string(path).with_context(|| "Path to the input data is invalid!")?;
+    let headers = StringRecord::from(INPUT_COLUMNS.to_vec());
+    let record = |values: &HashMap<String, String>| -> StringRecord {
+        INPUT_COLUMNS
+            .iter()
+            .map(|column| values.get(*column).map_or("", String::as_str))
+            .collect()
+    };
+
+    let mut reader = quick_xml::Reader::from_str(&content);
+    reader.trim_text(true);
+    let mut records = Vec::new();
+    // line and values of the record that's being read and the column of the value that's being read
+    let mut current: Option<(u64, HashMap<String, String>)> = None;
+    let mut column: Option<String> = None;
+    loop {
+        let event = reader.read_event().with_context(|| {
+            format!(
+                "Couldn't parse the XML input at byte {}!",
+                reader.buffer_position()
+            )
+        })?;
+        let line = content[..reader.buffer_position()].matches('\n').count() as u64 + 1;
+        let empty = matches!(event, Event::Empty(_));
+        match event {
+            Event::Start(element) | Event::Empty(element)
+                if element.local_name().as_ref() == record_element.as_bytes() =>
+            {
+                let mut values = HashMap::new();
+                for attribute in element.attributes() {
+                    let attribute = attribute.with_context(|| {
+                        format!("Couldn't parse the XML input at line {}!", line)
+                    })?;
+                    values.insert(
+                        String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned(),
+                        attribute.unescape_value()?.into_owned(),
+                    );
+                }
+                current = Some((line, values));
+                // record without any child elements ends right away
+                if empty {
+                    if let Some((line, values)) = current.take() {
+                        records.push(Ok(RawInput {
+                            line,
+                            record: record(&values),
+                        }));
+                    }
+                }
+            }
+            Event::Start(element) if current.is_some() => {
+                column = Some(String::from_utf8_lossy(element.local_name().as_ref()).into_owned());
+            }
+            Event::Text(text) => {
+                if let (Some((_, values)), Some(column)) = (&mut current, &column) {
+                    values.insert(column.clone(), text.unescape()?.into_owned());
+                }
+            }
+            Event::End(element) if element.local_name().as_ref() == record_element.as_bytes() => {
+                if let Some((line, values)) = current.take() {
+                    records.push(Ok(RawInput {
+                        line,
+                        record: record(&values),
+                    }));
+                }
+            }
+            Event::End(_) => column = None,
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok((headers, Box::new(records.into_iter())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_xml_input() -> Result<()> {
+        let (headers, records) = xml_input(Path::new("test_data/input.xml"), "offer")?;
+        assert_eq!(headers, StringRecord::from(INPUT_COLUMNS.to_vec()));
+        let records = records.collect::<csv::Result<Vec<_>>>()?;
+        assert_eq!(
+            records
+                .iter()
+                .map(|raw| (raw.line, raw.record.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    3,
+                    StringRecord::from(vec![
+                        "BER", "BER00002", "EZ", "BER898", "F", "20180721", "1", "0", "85.50",
+                        "IHG"
+                    ])
+                ),
+                (
+                    15,
+                    StringRecord::from(vec![
+                        "BER", "BER00003", "DZ", "BER848", "F", "20180721", "2", "0", "140.00",
+                        "MARR"
+                    ])
+                ),
+            ]
+        );
+        Ok(())
+    }
+}
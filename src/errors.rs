@@ -0,0 +1,187 @@
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::{stderr, Write};
+
+use anyhow::{Context, Error, Result};
+use serde::Serialize;
+
+use crate::data::{Reject, Warning};
+
+/// How the errors are presented.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorFormat {
+    /// Human readable message of the error that stopped the run
+    Text,
+    /// One JSON object per line for every rejected record, warning and the error that stopped the run
+    Json,
+}
+
+impl ErrorFormat {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "text" => Ok(ErrorFormat::Text),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(format!("expected one of: text, json, got '{}'", name)),
+        }
+    }
+}
+
+/// Structured description of an error, e.g
+/// {"code":"missing_hotel","message":"...","line":3,"fields":{"hotel_code":"BER00009"}}
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ErrorReport {
+    /// Reason of the rejection or kind of the warning, or just `error` for the other errors
+    pub code: String,
+    pub message: String,
+    /// Line of the input where the rejected or suspicious record begins, if it's known
+    pub line: Option<u64>,
+    /// Raw values of the rejected record by the name of the column
+    pub fields: BTreeMap<String, String>,
+    /// Column of the offending value and what was expected instead of it, when they are known
+    pub field: Option<String>,
+    pub hint: Option<String>,
+}
+
+impl ErrorReport {
+    pub fn from_error(error: &Error) -> Self {
+        match error.downcast_ref::<Reject>() {
+            Some(reject) => Self {
+                code: reject.reason.code().to_string(),
+                message: reject.message.clone(),
+                line: reject.line,
+                fields: reject.record.clone(),
+                field: reject
+                    .snippet
+                    .as_ref()
+                    .map(|snippet| snippet.column.clone()),
+                hint: reject.snippet.as_ref().map(|snippet| snippet.hint.clone()),
+            },
+            None => Self {
+                code: String::from("error"),
+                message: format!("{:#}", error),
+                line: None,
+                fields: BTreeMap::new(),
+                field: None,
+                hint: None,
+            },
+        }
+    }
+
+    pub fn from_warning(warning: &Warning) -> Self {
+        Self {
+            code: warning.kind.code().to_string(),
+            message: warning.message.clone(),
+            line: Some(warning.line),
+            fields: BTreeMap::new(),
+            field: None,
+            hint: None,
+        }
+    }
+}
+
+/// Destination of the errors, either the stderr or the file given in the settings.
+/// The file is appended to, so it can be shared by consecutive runs.
+pub struct ErrorReporter {
+    format: ErrorFormat,
+    output: Box<dyn Write>,
+}
+
+impl ErrorReporter {
+    pub fn new(format: ErrorFormat, path: Option<&str>) -> Result<Self> {
+        let output: Box<dyn Write> = match path {
+            Some(path) => Box::new(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("Couldn't open the errors file {}!", path))?,
+            ),
+            None => Box::new(stderr()),
+        };
+        Ok(Self { format, output })
+    }
+
+    /// Report the record that was rejected, but didn't stop the run.
+    /// Only structured output has these reports, the human readable one is left for the summary.
+    pub fn rejected(&mut self, error: &Error) -> Result<()> {
+        match self.format {
+            ErrorFormat::Text => Ok(()),
+            ErrorFormat::Json => self.write(error),
+        }
+    }
+
+    /// Report the warning of the record that was saved, only structured output has these reports as well.
+    pub fn warned(&mut self, warning: &Warning) -> Result<()> {
+        match self.format {
+            ErrorFormat::Text => Ok(()),
+            ErrorFormat::Json => self.write_report(&ErrorReport::from_warning(warning)),
+        }
+    }
+
+    /// Report the error that stopped the run.
+    pub fn failed(&mut self, error: &Error) -> Result<()> {
+        match self.format {
+            ErrorFormat::Text => {
+                // rejects show where exactly the problem is in the input
+                let message = match error.downcast_ref::<Reject>() {
+                    Some(reject) => reject.diagnostic(),
+                    None => error.to_string(),
+                };
+                writeln!(self.output, "Error occurred: {}", message)
+                    .with_context(|| "Couldn't report the error!")
+            }
+            ErrorFormat::Json => self.write(error),
+        }
+    }
+
+    fn write(&mut self, error: &Error) -> Result<()> {
+        self.write_report(&ErrorReport::from_error(error))
+    }
+
+    fn write_report(&mut self, report: &ErrorReport) -> Result<()> {
+        serde_json::to_writer(&mut self.output, report)
+            .with_context(|| "Couldn't report the error!")?;
+        writeln!(self.output).with_context(|| "Couldn't report the error!")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use anyhow::anyhow;
+    use csv::StringRecord;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::data::RejectReason;
+
+    #[test]
+    fn report_errors_as_json() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("errors.jsonl");
+        let reject = Reject::new(
+            RejectReason::MissingHotel,
+            String::from("Input links to a non existent hotel"),
+            Some(3),
+            &StringRecord::from(vec!["hotel_code"]),
+            Some(&StringRecord::from(vec!["BER00009"])),
+        );
+
+        let mut reporter = ErrorReporter::new(ErrorFormat::Json, path.to_str())?;
+        reporter.rejected(&reject.into())?;
+        reporter.failed(&anyhow!("Disk is full").context("Couldn't save the output"))?;
+        drop(reporter);
+
+        assert_eq!(
+            read_to_string(&path)?,
+            concat!(
+                r#"{"code":"missing_hotel","message":"Input links to a non existent hotel","line":3,"fields":{"hotel_code":"BER00009"},"field":null,"hint":null}"#,
+                "\n",
+                r#"{"code":"error","message":"Couldn't save the output: Disk is full","line":null,"fields":{},"field":null,"hint":null}"#,
+                "\n"
+            )
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use crate::data::{Reject, Warning};
+
+/// How many records of the input are read between two of the progress ticks.
+pub const PROGRESS_INTERVAL: usize = 1000;
+
+/// Stages of the run, in the order they are entered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// The reference data is loaded and the input is checked before it's enriched
+    Load,
+    /// The records of the input are enriched and written one by one
+    Enrich,
+    /// The output is checked and its checksum, manifest and state are saved
+    Finish,
+}
+
+/// Callbacks of the run for the programs embedding axiv, so they can show its progress in their own UI.
+/// None of the events is required, all of them do nothing by default. Skipped runs have no events.
+pub trait EventHandler {
+    /// The run entered the next stage.
+    fn stage(&mut self, _stage: Stage) {}
+
+    /// This many records of the input have been read so far, it's called every [PROGRESS_INTERVAL] records
+    /// and once more when the input is over.
+    fn progress(&mut self, _records: usize) {}
+
+    /// The enriched record got the warning, it's still saved in the output.
+    fn warning(&mut self, _warning: &Warning) {}
+
+    /// The record of the input couldn't be enriched.
+    fn reject(&mut self, _reject: &Reject) {}
+}
+
+/// No reaction to any of the events, that's what the command line does.
+impl EventHandler for () {}
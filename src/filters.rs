@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+
+use crate::data::Output;
+use crate::settings::Settings;
+
+/// Quality gates of the enriched records. They need the hotel data, so they are applied after the enrichment,
+/// and the records they exclude are counted separately from the rejects.
+#[derive(Debug, Default, Clone)]
+pub struct Filters {
+    pub min_category: Option<f32>,
+    /// Sources whose offers are left out of the output
+    pub exclude_sources: Vec<String>,
+}
+
+impl Filters {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            min_category: settings.min_category,
+            exclude_sources: settings.exclude_sources.clone(),
+        }
+    }
+
+    /// Name of the filter which excludes the record, if there's any.
+    pub fn exclusion(&self, output: &Output) -> Option<&'static str> {
+        if self.exclude_sources.contains(&output.source) {
+            return Some("exclude_sources");
+        }
+        match self.min_category {
+            Some(min_category) if output.hotel_category < min_category => Some("min_category"),
+            _ => None,
+        }
+    }
+}
+
+/// Counts of the records excluded by each of the filters.
+#[derive(Debug, Default)]
+pub struct Exclusions {
+    filters: Filters,
+    counts: BTreeMap<String, usize>,
+}
+
+impl Exclusions {
+    pub fn new(filters: Filters) -> Self {
+        Self {
+            filters,
+            counts: BTreeMap::new(),
+        }
+    }
+
+    /// Pass the record further, unless one of the filters excludes it.
+    pub fn handle(&mut self, output: Output) -> Option<Output> {
+        match self.filters.exclusion(&output) {
+            Some(filter) => {
+                *self.counts.entry(filter.to_string()).or_insert(0) += 1;
+                None
+            }
+            None => Some(output),
+        }
+    }
+
+    pub fn finish(self) -> BTreeMap<String, usize> {
+        self.counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn count_exclusions() {
+        let mut exclusions = Exclusions::new(Filters {
+            min_category: Some(3.0),
+            exclude_sources: vec![String::from("DOTW")],
+        });
+        let outputs = vec![
+            testing::output().build(),
+            testing::output().hotel_category(2.5).build(),
+            testing::output().source("DOTW").hotel_category(5.0).build(),
+            testing::output().source("DOTW").hotel_category(1.0).build(),
+            testing::output().source("MARR").hotel_category(3.0).build(),
+        ];
+        let passed = outputs
+            .into_iter()
+            .filter_map(|output| exclusions.handle(output))
+            .map(|output| output.hotel_category)
+            .collect::<Vec<_>>();
+        assert_eq!(passed, vec![4.0, 3.0]);
+
+        let counts = exclusions.finish();
+        assert_eq!(counts.get("exclude_sources"), Some(&2));
+        assert_eq!(counts.get("min_category"), Some(&1));
+    }
+
+    #[test]
+    fn pass_without_filters() {
+        assert_eq!(
+            Filters::default()
+                .exclusion(&testing::output().source("DOTW").hotel_category(0.0).build()),
+            None
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::json;
+
+use crate::shutdown;
+
+/// How often the endpoints check whether the daemon stopped while they wait for the requests.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// State of the daemon reported by its health endpoints: it's alive as long as the endpoints answer
+/// and ready once its reference data is loaded and it listens for the jobs.
+#[derive(Debug, Default)]
+pub struct Health {
+    loaded_at: Mutex<Option<DateTime<Utc>>>,
+    stopped: AtomicBool,
+}
+
+impl Health {
+    /// The reference data was loaded at the given time and the jobs are accepted from now on.
+    pub fn ready(&self, loaded_at: DateTime<Utc>) {
+        *self.loaded_at.lock().unwrap() = Some(loaded_at);
+    }
+
+    /// The daemon stopped, so the endpoints stop too.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    /// Status code and the JSON body of the response to the request of the path, at the given time.
+    fn respond(&self, path: &str, now: DateTime<Utc>) -> (u16, String) {
+        match path {
+            "/healthz" => (200, json!({"status": "ok"}).to_string()),
+            "/readyz" => match *self.loaded_at.lock().unwrap() {
+                Some(loaded_at) => (
+                    200,
+                    json!({
+                        "status": "ready",
+                        "reference_data": {
+                            "loaded_at": loaded_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+                            "age_seconds": (now - loaded_at).num_seconds(),
+                        }
+                    })
+                    .to_string(),
+                ),
+                None => (503, json!({"status": "loading"}).to_string()),
+            },
+            _ => (404, json!({"status": "not_found"}).to_string()),
+        }
+    }
+}
+
+/// Listen for the requests of the health endpoints at the address, e.g `0.0.0.0:8080`.
+pub fn bind(address: &str) -> Result<TcpListener> {
+    let listener = TcpListener::bind(address)
+        .with_context(|| format!("Couldn't listen for the health checks on {}!", address))?;
+    listener
+        .set_nonblocking(true)
+        .with_context(|| "Couldn't set up the listener of the health checks!")?;
+    Ok(listener)
+}
+
+/// Answer the requests of `/healthz` and `/readyz` until the daemon stops or it's asked to stop.
+/// They are answered one by one, each of them is just a look at the state.
+pub fn serve(listener: TcpListener, health: &Health) -> Result<()> {
+    while !health.stopped.load(Ordering::Relaxed) && !shutdown::is_requested() {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                if let Err(e) = answer(stream, health) {
+                    eprintln!("Couldn't answer the health check: {:#}", e);
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+            Err(e) => return Err(e).with_context(|| "Couldn't accept the health check!"),
+        }
+    }
+    Ok(())
+}
+
+fn answer(stream: TcpStream, health: &Health) -> Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    // e.g `GET /readyz HTTP/1.1`, the query and the headers don't matter
+    let path = request_line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or_default()
+        .split('?')
+        .next()
+        .unwrap_or_default();
+    let (status, body) = health.respond(path, Utc::now());
+    let reason = match status {
+        200 => "OK",
+        404 => "Not Found",
+        _ => "Service Unavailable",
+    };
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn report_readiness() {
+        let health = Health::default();
+        let now = Utc.ymd(2020, 9, 17).and_hms(12, 0, 0);
+        assert_eq!(health.respond("/healthz", now).0, 200);
+        assert_eq!(
+            health.respond("/readyz", now),
+            (503, String::from(r#"{"status":"loading"}"#))
+        );
+        health.ready(Utc.ymd(2020, 9, 17).and_hms(11, 0, 0));
+        assert_eq!(
+            health.respond("/readyz", now),
+            (
+                200,
+                String::from(
+                    r#"{"reference_data":{"age_seconds":3600,"loaded_at":"2020-09-17T11:00:00Z"},"status":"ready"}"#
+                )
+            )
+        );
+        assert_eq!(health.respond("/metrics", now).0, 404);
+    }
+
+    #[test]
+    fn answer_requests() -> Result<()> {
+        let listener = bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?;
+        let health = Health::default();
+        thread::scope(|scope| -> Result<()> {
+            let server = scope.spawn(|| serve(listener, &health));
+            let mut stream = TcpStream::connect(address)?;
+            write!(
+                stream,
+                "GET /readyz?verbose HTTP/1.1\r\nHost: localhost\r\n\r\n"
+            )?;
+            let mut response = String::new();
+            stream.read_to_string(&mut response)?;
+            assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+            assert!(response.ends_with("\r\n\r\n{\"status\":\"loading\"}"));
+            health.stop();
+            server.join().unwrap()
+        })
+    }
+}
@@ -0,0 +1,79 @@
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+
+/// Open the file for writing and take an exclusive advisory lock on it, so two runs can't
+/// write to the same file at the same time. It fails immediately if another instance holds the lock.
+/// The file is truncated only after the lock is taken and the lock is released when the file is closed.
+pub fn lock_for_writing(path: &Path) -> Result<File> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .with_context(|| format!("Couldn't open {} for writing!", path.display()))?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            return Err(anyhow!(
+                "{} is locked by another running instance of axiv!",
+                path.display()
+            ))
+        }
+        Err(TryLockError::Error(e)) => {
+            return Err(e).with_context(|| format!("Couldn't lock {}!", path.display()))
+        }
+    }
+    file.set_len(0)
+        .with_context(|| format!("Couldn't truncate {}!", path.display()))?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{read_to_string, write};
+    use std::io::Write;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn lock_held_by_another_writer() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("output.csv");
+        write(&path, "previous run")?;
+
+        let lock = lock_for_writing(&path)?;
+        assert_eq!(
+            lock_for_writing(&path)
+                .expect_err("This should fail")
+                .to_string(),
+            format!(
+                "{} is locked by another running instance of axiv!",
+                path.display()
+            )
+        );
+        drop(lock);
+
+        // lock is released together with the file
+        lock_for_writing(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn truncate_after_locking() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("output.csv");
+        write(&path, "previous run")?;
+
+        let mut held = lock_for_writing(&path)?;
+        assert_eq!(read_to_string(&path)?, "");
+        held.write_all(b"current run")?;
+        // the file in use isn't touched by the instance that failed to lock it
+        lock_for_writing(&path).expect_err("This should fail");
+        assert_eq!(read_to_string(&path)?, "current run");
+        Ok(())
+    }
+}
@@ -1,17 +1,117 @@
+use std::io::stdout;
+use std::process::exit;
+
+use anyhow::{anyhow, Error};
 use clap::Clap;
 
-use axiv::{run, Settings};
+use axiv::{
+    batch, completions, daemon, handle_signals, missing_keys, preview, reporter, run, submit, tui,
+    Command, Reply, Settings, TERMINATED_EXIT_CODE,
+};
+
+/// Report the error that stopped the run in the format given in the settings and exit with non-zero code.
+fn fail(settings: &Settings, error: Error) -> ! {
+    if let Err(e) = reporter(settings).and_then(|mut reporter| reporter.failed(&error)) {
+        eprintln!("Error occurred: {}", error);
+        eprintln!("Couldn't report the error: {}", e);
+    }
+    exit(1)
+}
 
 fn main() {
     let settings: Settings = Settings::parse();
 
+    if let Some(limit) = settings.preview {
+        match preview(&settings, limit) {
+            Ok(table) => print!("{}", table),
+            Err(e) => fail(&settings, e),
+        }
+        return;
+    }
+
+    match &settings.command {
+        Some(Command::Completions(completions_settings)) => {
+            completions(completions_settings.shell, &mut stdout());
+            return;
+        }
+        Some(Command::Tui(tui_settings)) => {
+            match tui(&settings, tui_settings) {
+                Ok(corrections) => println!(
+                    "Exported {} correction(s) to {}",
+                    corrections, &tui_settings.patch
+                ),
+                Err(e) => fail(&settings, e),
+            }
+            return;
+        }
+        Some(Command::MissingKeys(missing_settings)) => {
+            match missing_keys(&settings, missing_settings) {
+                Ok(missing) => {
+                    if let Some(path) = &missing_settings.keys {
+                        println!("Saved {} missing key(s) to {}", missing, path);
+                    }
+                }
+                Err(e) => fail(&settings, e),
+            }
+            return;
+        }
+        Some(Command::Submit(submit_settings)) => match submit(submit_settings) {
+            Ok(Reply::Done {
+                summary,
+                terminated,
+            }) => {
+                println!("{}", summary);
+                if terminated {
+                    exit(TERMINATED_EXIT_CODE)
+                }
+                return;
+            }
+            Ok(Reply::Failed { error }) => fail(&settings, anyhow!(error)),
+            Err(e) => fail(&settings, e),
+        },
+        _ => {}
+    }
+
+    if let Err(e) = handle_signals() {
+        fail(&settings, e);
+    }
+    if let Some(Command::Daemon(daemon_settings)) = &settings.command {
+        match daemon(&settings, daemon_settings) {
+            Ok(jobs) => println!("Daemon stopped after {} job(s)", jobs),
+            Err(e) => fail(&settings, e),
+        }
+        return;
+    }
+    if let Some(Command::Batch(batch_settings)) = &settings.command {
+        let outcomes = match batch(&settings, batch_settings) {
+            Ok(outcomes) => outcomes,
+            Err(e) => fail(&settings, e),
+        };
+        let (mut failed, mut terminated) = (0, false);
+        for (name, outcome) in outcomes {
+            match outcome {
+                Ok(summary) => {
+                    terminated |= summary.terminated;
+                    println!("[{}] {}", name, summary);
+                }
+                Err(e) => {
+                    failed += 1;
+                    eprintln!("[{}] Error occurred: {}", name, e);
+                }
+            }
+        }
+        if failed > 0 {
+            exit(1)
+        } else if terminated {
+            exit(TERMINATED_EXIT_CODE)
+        }
+        return;
+    }
     match run(&settings) {
-        Ok(()) => println!(
-            "The data was successfully parsed and saved at {}",
-            &settings.output
-        ),
-        Err(e) => {
-            println!("Error occurred: {}", e);
+        Ok(summary) => {
+            println!("{}", summary);
+            exit(summary.exit_code())
         }
+        Err(e) => fail(&settings, e),
     }
 }
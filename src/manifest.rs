@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{Local, SecondsFormat};
+use serde::Serialize;
+
+use crate::checksum;
+use crate::data::is_database;
+use crate::remote::is_remote;
+use crate::stamp::Stamp;
+use crate::{bundle, lock};
+
+/// Control file saved next to the output, so the loaders further down the pipeline can verify
+/// that they got the whole output, e.g `output.csv.manifest.json`.
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    pub axiv_version: &'static str,
+    /// When the output was generated, in RFC 3339 format
+    pub generated_at: String,
+    pub output: String,
+    pub output_sha256: Option<String>,
+    /// How many rows were saved in the output, apart from the header
+    pub rows: usize,
+    pub columns: Vec<&'static str>,
+    /// Whether the whole input was processed, runs terminated early have partial output
+    pub complete: bool,
+    /// Checksums of the files the output was generated from, by the name of the setting
+    pub inputs: BTreeMap<&'static str, InputFile>,
+    /// Checksums of the settings and of the reference data, when the output is stamped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settings_sha256: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference_sha256: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InputFile {
+    pub location: String,
+    /// There's none for the data that lives behind an URL or in a database.
+    /// Members of the ZIP archives have the checksum of the whole archive.
+    pub sha256: Option<String>,
+}
+
+impl InputFile {
+    pub fn new(location: &str) -> Result<Self> {
+        let file = match bundle::split(location, "") {
+            Some((archive, _)) => archive,
+            None => location,
+        };
+        let sha256 = if is_remote(file) || is_database(file) {
+            None
+        } else {
+            Some(checksum::sha256_file(Path::new(file))?)
+        };
+        Ok(Self {
+            location: location.to_string(),
+            sha256,
+        })
+    }
+}
+
+impl Manifest {
+    pub fn new(
+        output: &str,
+        output_sha256: Option<String>,
+        rows: usize,
+        columns: Vec<&'static str>,
+        complete: bool,
+    ) -> Self {
+        Self {
+            axiv_version: env!("CARGO_PKG_VERSION"),
+            generated_at: Local::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            output: output.to_string(),
+            output_sha256,
+            rows,
+            columns,
+            complete,
+            inputs: BTreeMap::new(),
+            settings_sha256: None,
+            reference_sha256: None,
+        }
+    }
+
+    /// Record the checksum of the file given in the setting of the given name.
+    pub fn with_input(mut self, setting: &'static str, location: &str) -> Result<Self> {
+        self.inputs.insert(setting, InputFile::new(location)?);
+        Ok(self)
+    }
+
+    /// Record where the output came from, the same way as its stamped rows.
+    pub fn with_stamp(mut self, stamp: Stamp) -> Self {
+        self.settings_sha256 = Some(stamp.settings_sha256);
+        self.reference_sha256 = stamp.reference_sha256;
+        self
+    }
+
+    /// Location of the manifest of the given output.
+    pub fn path(output: &str) -> String {
+        format!("{}.manifest.json", output)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path(&self.output);
+        let mut file = BufWriter::new(lock::lock_for_writing(Path::new(&path))?);
+        serde_json::to_writer_pretty(&mut file, self)
+            .with_context(|| format!("Couldn't save the manifest at {}", &path))?;
+        writeln!(file).with_context(|| format!("Couldn't save the manifest at {}", &path))?;
+        file.flush()
+            .with_context(|| format!("Couldn't save the manifest at {}", &path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn save_manifest() -> Result<()> {
+        let dir = tempdir()?;
+        let output = dir.path().join("output.csv");
+        let output = output.to_str().unwrap();
+
+        Manifest::new(output, None, 2, vec!["room_code", "price"], true)
+            .with_input("input", "test_data/input.csv")?
+            .with_input("rooms", "test_data/feed.zip#room_names.csv")?
+            .with_input("hotels", "https://example.com/hotels.json")?
+            .save()?;
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&read_to_string(Manifest::path(output))?)?;
+        assert_eq!(manifest["rows"], 2);
+        assert_eq!(
+            manifest["columns"],
+            serde_json::json!(["room_code", "price"])
+        );
+        assert_eq!(manifest["complete"], true);
+        assert_eq!(
+            manifest["inputs"]["input"]["sha256"],
+            checksum::sha256_file(Path::new("test_data/input.csv"))?
+        );
+        assert_eq!(
+            manifest["inputs"]["rooms"]["sha256"],
+            checksum::sha256_file(Path::new("test_data/feed.zip"))?
+        );
+        assert_eq!(
+            manifest["inputs"]["hotels"]["sha256"],
+            serde_json::Value::Null
+        );
+        Ok(())
+    }
+}
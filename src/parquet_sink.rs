@@ -0,0 +1,407 @@
+use std::collections::HashMap;
+use std::fs::{create_dir_all, read_dir, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDate;
+use parquet::basic::Compression;
+use parquet::data_type::{ByteArray, ByteArrayType, DataType, DoubleType, FloatType, Int32Type};
+use parquet::file::properties::{WriterProperties, WriterPropertiesPtr};
+use parquet::file::writer::{SerializedColumnWriter, SerializedFileWriter};
+use parquet::schema::parser::parse_message_type;
+use parquet::schema::types::TypePtr;
+
+use crate::data::{GroupBy, Output};
+use crate::Outputs;
+
+/// How many records of a partition are kept in memory before they are written as a row group.
+const ROW_GROUP_SIZE: usize = 10_000;
+
+/// Columns of the Parquet files with their types, dates are the days since 1970-01-01.
+const COLUMNS: [(&str, &str); 21] = [
+    (
+        "room_type_meal",
+        "REQUIRED BYTE_ARRAY room_type_meal (UTF8)",
+    ),
+    ("room_code", "REQUIRED BYTE_ARRAY room_code (UTF8)"),
+    ("source", "REQUIRED BYTE_ARRAY source (UTF8)"),
+    ("hotel_name", "REQUIRED BYTE_ARRAY hotel_name (UTF8)"),
+    ("city_name", "REQUIRED BYTE_ARRAY city_name (UTF8)"),
+    ("city_code", "REQUIRED BYTE_ARRAY city_code (UTF8)"),
+    ("hotel_category", "REQUIRED FLOAT hotel_category"),
+    ("pax", "REQUIRED INT32 pax"),
+    ("adults", "REQUIRED INT32 adults"),
+    ("children", "REQUIRED INT32 children"),
+    ("room_name", "REQUIRED BYTE_ARRAY room_name (UTF8)"),
+    ("checkin", "REQUIRED INT32 checkin (DATE)"),
+    ("checkout", "REQUIRED INT32 checkout (DATE)"),
+    ("price", "REQUIRED DOUBLE price"),
+    ("latitude", "OPTIONAL DOUBLE latitude"),
+    ("longitude", "OPTIONAL DOUBLE longitude"),
+    ("hotel_code", "REQUIRED BYTE_ARRAY hotel_code (UTF8)"),
+    ("meal", "REQUIRED BYTE_ARRAY meal (UTF8)"),
+    ("rank", "OPTIONAL INT32 rank"),
+    ("offers", "OPTIONAL INT32 offers"),
+    (
+        "winning_source",
+        "OPTIONAL BYTE_ARRAY winning_source (UTF8)",
+    ),
+];
+
+/// How the Parquet files are laid out in the directory of the output.
+#[derive(Debug, Clone)]
+pub struct ParquetOptions {
+    /// Columns the records are partitioned by, in the order of the directories
+    pub partition_by: Vec<GroupBy>,
+    /// How many records are saved in each of the files of a partition, all of them by default
+    pub rows_per_file: Option<usize>,
+    /// Whether the empty _SUCCESS file is saved once all of the files are complete
+    pub success_marker: bool,
+}
+
+/// Directory the enriched records are saved in as Parquet files, in the Hive-style layout of the data lakes,
+/// e.g `city_code=BER/checkin=2019-07-30/part-0001.parquet`. Just like in Hive, the columns the records are partitioned
+/// by are a part of the path instead of the files. Unlike the CSV output, the values are not formatted.
+pub struct ParquetWriter {
+    root: PathBuf,
+    options: ParquetOptions,
+    /// Names of the columns of the files, in the order of the schema
+    columns: Vec<&'static str>,
+    schema: TypePtr,
+    properties: WriterPropertiesPtr,
+    partitions: HashMap<String, Partition>,
+    files: Vec<String>,
+}
+
+/// Records of the partition that wait for the next row group, with the file they are written to.
+#[derive(Default)]
+struct Partition {
+    part: usize,
+    rows: usize,
+    file: Option<SerializedFileWriter<File>>,
+    pending: Vec<Output>,
+}
+
+impl ParquetWriter {
+    /// It refuses to write into the directory that isn't empty, so the files of the previous runs don't end up
+    /// in the same data set.
+    pub fn create(root: &Path, options: ParquetOptions) -> Result<Self> {
+        if root.exists() {
+            let mut entries = read_dir(root).with_context(|| {
+                format!(
+                    "Couldn't open the directory of the output {}!",
+                    root.display()
+                )
+            })?;
+            if entries.next().is_some() {
+                return Err(anyhow!(
+                    "The directory of the Parquet output {} isn't empty!",
+                    root.display()
+                ));
+            }
+        }
+        create_dir_all(root).with_context(|| {
+            format!(
+                "Couldn't create the directory of the output {}!",
+                root.display()
+            )
+        })?;
+        let columns: Vec<(&'static str, &'static str)> = COLUMNS
+            .iter()
+            .filter(|(name, _)| !options.partition_by.iter().any(|by| by.name() == *name))
+            .copied()
+            .collect();
+        let types: Vec<&str> = columns.iter().map(|(_, column)| *column).collect();
+        let schema = parse_message_type(&format!("message output {{ {}; }}", types.join("; ")))
+            .with_context(|| "Couldn't prepare the schema of the Parquet output!")?;
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        Ok(Self {
+            root: root.to_path_buf(),
+            options,
+            columns: columns.into_iter().map(|(name, _)| name).collect(),
+            schema: Arc::new(schema),
+            properties: Arc::new(properties),
+            partitions: HashMap::new(),
+            files: Vec::new(),
+        })
+    }
+
+    /// Save the enriched records in the files of their partitions, returns how many of them were saved.
+    pub fn write(&mut self, outputs: Outputs) -> Result<usize> {
+        let mut records = 0;
+        for output_res in outputs {
+            let output = output_res?;
+            let directory = self.directory(&output);
+            let partition = self.partitions.entry(directory.clone()).or_default();
+            partition.pending.push(output);
+            let file_is_full = self
+                .options
+                .rows_per_file
+                .is_some_and(|rows| partition.rows + partition.pending.len() >= rows);
+            if partition.pending.len() >= ROW_GROUP_SIZE || file_is_full {
+                self.flush(&directory)?;
+            }
+            records += 1;
+        }
+        let directories: Vec<String> = self.partitions.keys().cloned().collect();
+        for directory in &directories {
+            self.flush(directory)?;
+            if let Some(file) = self
+                .partitions
+                .get_mut(directory)
+                .and_then(|p| p.file.take())
+            {
+                file.close()
+                    .with_context(|| format!("Couldn't save the partition {}!", directory))?;
+            }
+        }
+        if self.options.success_marker {
+            let marker = self.root.join("_SUCCESS");
+            File::create(&marker)
+                .with_context(|| format!("Couldn't save the marker {}!", marker.display()))?;
+        }
+        self.files.sort_unstable();
+        Ok(records)
+    }
+
+    /// Files the output was split into.
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    /// Directory of the partition of the record, relative to the directory of the output.
+    fn directory(&self, output: &Output) -> String {
+        self.options
+            .partition_by
+            .iter()
+            .map(|by| format!("{}={}", by.name(), escape(&by.value(output))))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Write the pending records of the partition as a row group, the next file is opened when there's none.
+    fn flush(&mut self, directory: &str) -> Result<()> {
+        let partition = match self.partitions.get_mut(directory) {
+            Some(partition) if !partition.pending.is_empty() => partition,
+            _ => return Ok(()),
+        };
+        if partition.file.is_none() {
+            partition.part += 1;
+            let name = format!("part-{:04}.parquet", partition.part);
+            let path = self.root.join(directory).join(&name);
+            if let Some(parent) = path.parent() {
+                create_dir_all(parent).with_context(|| {
+                    format!("Couldn't create the partition {}!", parent.display())
+                })?;
+            }
+            let file = File::create(&path)
+                .with_context(|| format!("Couldn't create {}!", path.display()))?;
+            partition.file = Some(SerializedFileWriter::new(
+                file,
+                Arc::clone(&self.schema),
+                Arc::clone(&self.properties),
+            )?);
+            self.files.push(path.display().to_string());
+        }
+        let rows = std::mem::take(&mut partition.pending);
+        let file = partition.file.as_mut().expect("The file was just opened");
+        let mut row_group = file.next_row_group()?;
+        for name in &self.columns {
+            let mut column = row_group
+                .next_column()?
+                .ok_or_else(|| anyhow!("There's no column {} in the Parquet schema!", name))?;
+            write_column(&mut column, name, &rows)
+                .with_context(|| format!("Couldn't save the column {}!", name))?;
+            column.close()?;
+        }
+        row_group.close()?;
+        partition.rows += rows.len();
+        let file_is_full = self
+            .options
+            .rows_per_file
+            .is_some_and(|rows| partition.rows >= rows);
+        if file_is_full {
+            partition.rows = 0;
+            if let Some(file) = partition.file.take() {
+                file.close()
+                    .with_context(|| format!("Couldn't save the partition {}!", directory))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Write the values of the column of the given name of all of the records.
+fn write_column(column: &mut SerializedColumnWriter, name: &str, rows: &[Output]) -> Result<()> {
+    let text = |value: &String| ByteArray::from(value.as_str());
+    match name {
+        "room_type_meal" => required::<ByteArrayType>(column, rows, |o| text(&o.room_type_meal)),
+        "room_code" => required::<ByteArrayType>(column, rows, |o| text(&o.room_code)),
+        "source" => required::<ByteArrayType>(column, rows, |o| text(&o.source)),
+        "hotel_name" => required::<ByteArrayType>(column, rows, |o| text(&o.hotel_name)),
+        "city_name" => required::<ByteArrayType>(column, rows, |o| text(&o.city_name)),
+        "city_code" => required::<ByteArrayType>(column, rows, |o| text(&o.city_code)),
+        "hotel_category" => required::<FloatType>(column, rows, |o| o.hotel_category),
+        "pax" => required::<Int32Type>(column, rows, |o| o.pax.into()),
+        "adults" => required::<Int32Type>(column, rows, |o| o.adults.into()),
+        "children" => required::<Int32Type>(column, rows, |o| o.children.into()),
+        "room_name" => required::<ByteArrayType>(column, rows, |o| text(&o.room_name)),
+        "checkin" => required::<Int32Type>(column, rows, |o| days(o.checkin)),
+        "checkout" => required::<Int32Type>(column, rows, |o| days(o.checkout)),
+        "price" => required::<DoubleType>(column, rows, |o| o.price),
+        "latitude" => optional::<DoubleType>(column, rows, |o| o.latitude),
+        "longitude" => optional::<DoubleType>(column, rows, |o| o.longitude),
+        "hotel_code" => required::<ByteArrayType>(column, rows, |o| text(&o.hotel_code)),
+        "meal" => required::<ByteArrayType>(column, rows, |o| text(&o.meal)),
+        "rank" => optional::<Int32Type>(column, rows, |o| {
+            o.ranking.as_ref().map(|ranking| ranking.rank as i32)
+        }),
+        "offers" => optional::<Int32Type>(column, rows, |o| {
+            o.ranking.as_ref().map(|ranking| ranking.offers as i32)
+        }),
+        "winning_source" => optional::<ByteArrayType>(column, rows, |o| {
+            o.ranking
+                .as_ref()
+                .map(|ranking| text(&ranking.winning_source))
+        }),
+        _ => Err(anyhow!("There's no column {} in the output!", name)),
+    }
+}
+
+fn required<T: DataType>(
+    column: &mut SerializedColumnWriter,
+    rows: &[Output],
+    value: impl Fn(&Output) -> T::T,
+) -> Result<()> {
+    let values: Vec<T::T> = rows.iter().map(value).collect();
+    column.typed::<T>().write_batch(&values, None, None)?;
+    Ok(())
+}
+
+/// Missing values are only marked in the definition levels, there are no values for them.
+fn optional<T: DataType>(
+    column: &mut SerializedColumnWriter,
+    rows: &[Output],
+    value: impl Fn(&Output) -> Option<T::T>,
+) -> Result<()> {
+    let values: Vec<Option<T::T>> = rows.iter().map(value).collect();
+    let levels: Vec<i16> = values.iter().map(|value| value.is_some() as i16).collect();
+    let values: Vec<T::T> = values.into_iter().flatten().collect();
+    column
+        .typed::<T>()
+        .write_batch(&values, Some(&levels), None)?;
+    Ok(())
+}
+
+/// Date as the number of days since 1970-01-01, the way Parquet keeps the dates.
+fn days(date: NaiveDate) -> i32 {
+    (date - NaiveDate::from_ymd(1970, 1, 1)).num_days() as i32
+}
+
+/// Escape the characters that can't be used in the names of the directories the same way as Hive does,
+/// the empty values go into the default partition of Hive.
+fn escape(value: &str) -> String {
+    if value.is_empty() {
+        return String::from("__HIVE_DEFAULT_PARTITION__");
+    }
+    value
+        .chars()
+        .map(|character| {
+            if character.is_control() || "\"#%'*/:=?\\{[]^".contains(character) {
+                format!("%{:02X}", character as u32)
+            } else {
+                character.to_string()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn save_partitioned_files() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path().join("offers");
+        let options = ParquetOptions {
+            partition_by: vec![GroupBy::CityCode, GroupBy::Checkin],
+            rows_per_file: Some(2),
+            success_marker: true,
+        };
+        let mut writer = ParquetWriter::create(&root, options.clone())?;
+        let outputs: Outputs = Box::new(
+            vec![
+                testing::output().latitude(52.5).build(),
+                testing::output().price(90.0).latitude(52.5).build(),
+                testing::output().price(95.0).latitude(52.5).build(),
+                testing::output()
+                    .checkin(NaiveDate::from_ymd(2018, 7, 22))
+                    .price(70.0)
+                    .latitude(52.5)
+                    .build(),
+                testing::output()
+                    .city_code("LON")
+                    .price(120.0)
+                    .latitude(52.5)
+                    .build(),
+            ]
+            .into_iter()
+            .map(Ok),
+        );
+        assert_eq!(writer.write(outputs)?, 5);
+
+        let relative: Vec<String> = writer
+            .files()
+            .iter()
+            .map(|file| {
+                Path::new(file)
+                    .strip_prefix(&root)
+                    .unwrap()
+                    .display()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(
+            relative,
+            vec![
+                "city_code=BER/checkin=2018-07-21/part-0001.parquet",
+                "city_code=BER/checkin=2018-07-21/part-0002.parquet",
+                "city_code=BER/checkin=2018-07-22/part-0001.parquet",
+                "city_code=LON/checkin=2018-07-21/part-0001.parquet",
+            ]
+        );
+        assert!(root.join("_SUCCESS").exists());
+
+        let reader = SerializedFileReader::new(File::open(&writer.files()[0])?)?;
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+        let schema = reader.metadata().file_metadata().schema_descr();
+        // the partition columns are a part of the path
+        assert_eq!(schema.num_columns(), COLUMNS.len() - 2);
+        let rows: Vec<String> = reader
+            .get_row_iter(None)?
+            .map(|row| row.to_string())
+            .collect();
+        assert!(rows[0].contains("price: 85.5"));
+        assert!(rows[0].contains("latitude: 52.5, longitude: null"));
+        assert!(rows[1].contains("checkout: 2018-07-22"));
+
+        // the files of the previous runs are not mixed with the new ones
+        assert!(ParquetWriter::create(&root, options).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn escape_partition_values() {
+        assert_eq!(escape("Berlin Mitte"), "Berlin Mitte");
+        assert_eq!(escape("A/B=C"), "A%2FB%3DC");
+        assert_eq!(escape(""), "__HIVE_DEFAULT_PARTITION__");
+    }
+}
@@ -0,0 +1,168 @@
+use std::num::NonZeroUsize;
+
+use anyhow::{Context, Result};
+use csv::{QuoteStyle, WriterBuilder};
+use postgres::{Client, NoTls};
+
+use crate::backpressure::{write_bounded, QueueStats};
+use crate::data::{validate_table, Output};
+use crate::Outputs;
+
+const COLUMNS: &str = "room_type_meal, room_code, source, hotel_name, city_name, city_code, hotel_category, \
+    pax, adults, children, room_name, checkin, checkout, price, latitude, longitude, hotel_code, meal, \
+    rank, offers, winning_source";
+
+/// Columns that are empty when the offers are not consolidated or the coordinates are not given.
+const NULLABLE_COLUMNS: &str = "latitude, longitude, rank, offers, winning_source";
+
+/// What is done with the table before the records are loaded into it.
+#[derive(Debug, Clone)]
+pub struct PostgresOptions {
+    pub table: String,
+    /// Create the table if it doesn't exist yet
+    pub create_table: bool,
+    /// Remove the records of the previous runs
+    pub truncate: bool,
+    /// How many chunks of the rows can wait for the database at once
+    pub buffer: NonZeroUsize,
+}
+
+/// Table the enriched records are streamed into with `COPY ... FROM STDIN`, in the same transaction
+/// as the truncation of the table, so the table is never left half-loaded.
+pub struct PostgresWriter {
+    client: Client,
+    options: PostgresOptions,
+    queue: Option<QueueStats>,
+}
+
+impl PostgresWriter {
+    pub fn connect(url: &str, options: PostgresOptions) -> Result<Self> {
+        validate_table(&options.table)?;
+        let client = Client::connect(url, NoTls)
+            .with_context(|| "Couldn't connect to the PostgreSQL database!")?;
+        Ok(Self {
+            client,
+            options,
+            queue: None,
+        })
+    }
+
+    /// Save the enriched records in the table, returns how many of them were saved.
+    pub fn write(&mut self, outputs: Outputs) -> Result<usize> {
+        let table = &self.options.table;
+        let mut transaction = self
+            .client
+            .transaction()
+            .with_context(|| "Couldn't start the transaction!")?;
+        if self.options.create_table {
+            transaction
+                .batch_execute(&create_table(table))
+                .with_context(|| format!("Couldn't create the table {}!", table))?;
+        }
+        if self.options.truncate {
+            transaction
+                .batch_execute(&format!("TRUNCATE {}", table))
+                .with_context(|| format!("Couldn't truncate the table {}!", table))?;
+        }
+
+        let copy = transaction
+            .copy_in(copy_statement(table).as_str())
+            .with_context(|| format!("Couldn't start loading the table {}!", table))?;
+        // the rows are sent to the database on another thread, a slow database holds up the reading of the input
+        let (copy, records, queue) = write_bounded(copy, self.options.buffer, |queue| {
+            let mut records = 0;
+            // strings are always quoted, so the empty ones are not confused with NULLs
+            let mut rows = WriterBuilder::new()
+                .has_headers(false)
+                .quote_style(QuoteStyle::NonNumeric)
+                .from_writer(queue);
+            for output_res in outputs {
+                let output = output_res?;
+                rows.write_record(row(&output))
+                    .with_context(|| format!("Couldn't save {:#?}", &output))?;
+                records += 1;
+            }
+            rows.flush()
+                .with_context(|| format!("Couldn't load the table {}!", table))?;
+            Ok(records)
+        })
+        .with_context(|| format!("Couldn't load the table {}!", table))?;
+        self.queue = Some(queue);
+        copy.finish()
+            .with_context(|| format!("Couldn't load the table {}!", table))?;
+        transaction
+            .commit()
+            .with_context(|| "Couldn't commit the transaction!")?;
+        Ok(records)
+    }
+
+    /// How full the queue in front of the database was, once the records are saved.
+    pub fn queue(&self) -> Option<QueueStats> {
+        self.queue
+    }
+}
+
+fn create_table(table: &str) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} (
+            room_type_meal TEXT NOT NULL,
+            room_code TEXT NOT NULL,
+            source TEXT NOT NULL,
+            hotel_name TEXT NOT NULL,
+            city_name TEXT NOT NULL,
+            city_code TEXT NOT NULL,
+            hotel_category REAL NOT NULL,
+            pax SMALLINT NOT NULL,
+            adults SMALLINT NOT NULL,
+            children SMALLINT NOT NULL,
+            room_name TEXT NOT NULL,
+            checkin DATE NOT NULL,
+            checkout DATE NOT NULL,
+            price NUMERIC NOT NULL,
+            latitude DOUBLE PRECISION,
+            longitude DOUBLE PRECISION,
+            hotel_code TEXT NOT NULL,
+            meal TEXT NOT NULL,
+            rank INTEGER,
+            offers INTEGER,
+            winning_source TEXT
+        )",
+        table
+    )
+}
+
+fn copy_statement(table: &str) -> String {
+    format!(
+        "COPY {} ({}) FROM STDIN WITH (FORMAT csv, FORCE_NULL ({}))",
+        table, COLUMNS, NULLABLE_COLUMNS
+    )
+}
+
+/// Values of the output in the order of the columns, unformatted, the empty ones are NULLs.
+fn row(output: &Output) -> Vec<String> {
+    let optional = |value: Option<String>| value.unwrap_or_default();
+    let ranking = output.ranking.as_ref();
+    vec![
+        output.room_type_meal.clone(),
+        output.room_code.clone(),
+        output.source.clone(),
+        output.hotel_name.clone(),
+        output.city_name.clone(),
+        output.city_code.clone(),
+        output.hotel_category.to_string(),
+        output.pax.to_string(),
+        output.adults.to_string(),
+        output.children.to_string(),
+        output.room_name.clone(),
+        output.checkin.to_string(),
+        output.checkout.to_string(),
+        output.price.to_string(),
+        optional(output.latitude.map(|latitude| latitude.to_string())),
+        optional(output.longitude.map(|longitude| longitude.to_string())),
+        output.hotel_code.clone(),
+        output.meal.clone(),
+        optional(ranking.map(|ranking| ranking.rank.to_string())),
+        optional(ranking.map(|ranking| ranking.offers.to_string())),
+        optional(ranking.map(|ranking| ranking.winning_source.clone())),
+    ]
+}
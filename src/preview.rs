@@ -0,0 +1,144 @@
+use anyhow::{Context, Result};
+use csv::{ReaderBuilder, StringRecord, WriterBuilder};
+
+use crate::data::{Output, OutputFormat, OutputRecord};
+
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+enum Row {
+    Record,
+    Reject(String),
+}
+
+/// Render the first `limit` enriched records as an aligned table.
+/// Records that couldn't be enriched don't stop the preview, they are presented inline
+/// (and highlighted, if `highlight` is set) next to the records that surround them.
+pub fn render<I>(outputs: I, format: &OutputFormat, limit: usize, highlight: bool) -> Result<String>
+where
+    I: Iterator<Item = Result<Output>>,
+{
+    // records are serialized exactly like they would be in the output file
+    let mut writer = WriterBuilder::new().from_writer(vec![]);
+    let mut rows = Vec::new();
+    let mut records = 0;
+    for output_res in outputs {
+        if records == limit {
+            break;
+        }
+        match output_res {
+            Ok(output) => {
+                writer
+                    .serialize(OutputRecord::new(&output, format))
+                    .with_context(|| format!("Couldn't serialize {:#?}", &output))?;
+                rows.push(Row::Record);
+                records += 1;
+            }
+            Err(e) => rows.push(Row::Reject(
+                e.to_string()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            )),
+        }
+    }
+    let serialized = writer
+        .into_inner()
+        .with_context(|| "Couldn't render the preview")?;
+    let mut reader = ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(serialized.as_slice());
+    let mut serialized_records = reader.records().collect::<Result<Vec<_>, _>>()?.into_iter();
+    let header = serialized_records.next().unwrap_or_default();
+    let serialized_records: Vec<StringRecord> = serialized_records.collect();
+
+    let widths: Vec<usize> = (0..header.len())
+        .map(|column| {
+            std::iter::once(&header)
+                .chain(serialized_records.iter())
+                .map(|record| record.get(column).unwrap_or("").chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let mut table = String::new();
+    if !header.is_empty() {
+        table.push_str(&line(&header, &widths));
+        table.push_str(&separator(&widths));
+    }
+    let mut serialized_records = serialized_records.iter();
+    for row in rows {
+        match row {
+            Row::Record => {
+                if let Some(record) = serialized_records.next() {
+                    table.push_str(&line(record, &widths));
+                }
+            }
+            Row::Reject(reason) if highlight => {
+                table.push_str(&format!("{}REJECTED: {}{}\n", RED, reason, RESET))
+            }
+            Row::Reject(reason) => table.push_str(&format!("REJECTED: {}\n", reason)),
+        }
+    }
+    Ok(table)
+}
+
+fn line(record: &StringRecord, widths: &[usize]) -> String {
+    let cells: Vec<String> = record
+        .iter()
+        .zip(widths)
+        .map(|(value, width)| format!("{:width$}", value, width = width))
+        .collect();
+    format!("{}\n", cells.join(" | ").trim_end())
+}
+
+fn separator(widths: &[usize]) -> String {
+    let cells: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+    format!("{}\n", cells.join("-+-"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{CategoryFormat, NumberFormat};
+    use crate::testing;
+    use anyhow::anyhow;
+
+    #[test]
+    fn render_table() -> Result<()> {
+        let format = OutputFormat {
+            number: NumberFormat::from_locale("plain").unwrap(),
+            category: CategoryFormat::Raw,
+            coordinates: false,
+            chains: false,
+            ranking: false,
+            run_date: None,
+            seasons: None,
+            match_status: false,
+            match_confidence: false,
+            candidate_room_names: None,
+            derived_metrics: false,
+            stamp: None,
+            dropped: Vec::new(),
+        };
+        let outputs = vec![
+            Ok(testing::output().build()),
+            Err(anyhow!("Input links to a non existent room:\n    BER898")),
+            Ok(testing::output().room_name("Single").price(1078.0).build()),
+            Ok(testing::output().room_name("Not shown").price(1.0).build()),
+        ];
+
+        let table = render(outputs.into_iter(), &format, 2, false)?;
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].starts_with("room_type meal | room_code | source | hotel_name   |"));
+        assert!(lines[1].starts_with("---------------+-----------+--------+--------------+"));
+        assert!(lines[2].ends_with("| Einzelzimmer | 2018-07-21 | 2018-07-22 | 85.50"));
+        assert_eq!(
+            lines[3],
+            "REJECTED: Input links to a non existent room: BER898"
+        );
+        assert!(lines[4].ends_with("| Single       | 2018-07-21 | 2018-07-22 | 1078.00"));
+        Ok(())
+    }
+}
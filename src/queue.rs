@@ -0,0 +1,321 @@
+// the queues are only reachable with the sqs or rabbitmq feature
+#![cfg_attr(not(any(feature = "sqs", feature = "rabbitmq")), allow(dead_code))]
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use csv::{ReaderBuilder, StringRecord};
+use serde_json::Value;
+
+use crate::data::{RawInput, RawInputs, INPUT_COLUMNS};
+use crate::shutdown;
+
+/// Message queue the records of the input are consumed from instead of the input file.
+pub trait Queue {
+    /// Whatever the queue needs to acknowledge the message, e.g its receipt handle.
+    type Receipt;
+
+    /// At most `limit` messages that came within the wait time, there are none when the queue stayed empty.
+    fn receive(&mut self, wait: Duration, limit: usize) -> Result<Vec<(String, Self::Receipt)>>;
+
+    /// Let the queue know that the messages were handled, so they are not delivered again.
+    fn acknowledge(&mut self, receipts: Vec<Self::Receipt>) -> Result<()>;
+}
+
+/// Format of the messages of the queue, each of them is a single record of the input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueuePayload {
+    /// Object with the values by the columns of the input, e.g `{"hotel_code": "BER00002", "adults": 2, ...}`
+    Json,
+    /// Line with the values of all of the columns of the input, in their order
+    Csv,
+}
+
+impl QueuePayload {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "json" => Ok(QueuePayload::Json),
+            "csv" => Ok(QueuePayload::Csv),
+            _ => Err(format!("expected one of: json, csv, got '{}'", name)),
+        }
+    }
+
+    /// Record of the input with the values of the message, in the order of INPUT_COLUMNS.
+    fn record(&self, body: &str, delimiter: u8) -> Result<StringRecord> {
+        match self {
+            QueuePayload::Json => {
+                let values: serde_json::Map<String, Value> = serde_json::from_str(body)
+                    .with_context(|| "The message is not a JSON object!")?;
+                INPUT_COLUMNS
+                    .iter()
+                    .map(|column| match values.get(*column) {
+                        None | Some(Value::Null) => Ok(String::new()),
+                        Some(Value::String(value)) => Ok(value.clone()),
+                        Some(Value::Number(value)) => Ok(value.to_string()),
+                        Some(Value::Bool(value)) => Ok(value.to_string()),
+                        Some(_) => Err(anyhow!("Column {} of the message is not a value!", column)),
+                    })
+                    .collect()
+            }
+            QueuePayload::Csv => {
+                let mut records = ReaderBuilder::new()
+                    .has_headers(false)
+                    .delimiter(delimiter)
+                    .from_reader(body.trim_end().as_bytes())
+                    .into_records();
+                let record = match (records.next(), records.next()) {
+                    (Some(record), None) => record?,
+                    _ => return Err(anyhow!("The message has to be a single line!")),
+                };
+                if record.len() != INPUT_COLUMNS.len() {
+                    return Err(anyhow!(
+                        "The message has {} values, expected {}: {}!",
+                        record.len(),
+                        INPUT_COLUMNS.len(),
+                        INPUT_COLUMNS.join(", ")
+                    ));
+                }
+                Ok(record)
+            }
+        }
+    }
+}
+
+/// How the messages of the queue are read and when the run stops waiting for them.
+#[derive(Debug, Clone)]
+pub struct QueueOptions {
+    pub payload: QueuePayload,
+    /// Delimiter of the values of the CSV messages
+    pub delimiter: u8,
+    /// The input is over once no message came for this long
+    pub wait: Duration,
+    /// The input is over once this many messages came
+    pub max_messages: Option<usize>,
+}
+
+/// Acknowledgement of the messages that were given to the run, it's sent once their records are saved,
+/// so the messages of the failed run are delivered again.
+pub struct Acknowledgement(Box<dyn FnOnce() -> Result<usize>>);
+
+impl Acknowledgement {
+    /// Acknowledge the messages, returns how many of them were acknowledged.
+    pub fn send(self) -> Result<usize> {
+        (self.0)()
+    }
+}
+
+/// Messages received from the queue, the ones given to the run are acknowledged together once it's done.
+struct Consumer<Q: Queue> {
+    queue: Q,
+    options: QueueOptions,
+    received: VecDeque<(String, Q::Receipt)>,
+    delivered: Vec<Q::Receipt>,
+    idle_since: Instant,
+    failure: Option<anyhow::Error>,
+}
+
+impl<Q: Queue> Consumer<Q> {
+    fn next(&mut self) -> Option<csv::Result<RawInput>> {
+        loop {
+            let remaining = match self.options.max_messages {
+                Some(max_messages) if self.delivered.len() >= max_messages => return None,
+                Some(max_messages) => max_messages - self.delivered.len(),
+                None => usize::MAX,
+            };
+            if let Some((body, receipt)) = self.received.pop_front() {
+                self.delivered.push(receipt);
+                let line = self.delivered.len() as u64;
+                // the broken messages are rejected as unreadable, like the broken lines of the file
+                return Some(
+                    self.options
+                        .payload
+                        .record(&body, self.options.delimiter)
+                        .map(|record| RawInput { line, record })
+                        .map_err(|e| {
+                            io::Error::new(io::ErrorKind::InvalidData, format!("{:#}", e)).into()
+                        }),
+                );
+            }
+            if self.failure.is_some() || shutdown::is_requested() {
+                return None;
+            }
+            let wait = self.options.wait.saturating_sub(self.idle_since.elapsed());
+            match self.queue.receive(wait, remaining) {
+                Ok(messages) if messages.is_empty() => {
+                    if self.idle_since.elapsed() >= self.options.wait {
+                        return None;
+                    }
+                }
+                Ok(messages) => {
+                    self.idle_since = Instant::now();
+                    self.received.extend(messages);
+                }
+                // the input ends and the run fails once the messages it already got are saved
+                Err(e) => self.failure = Some(e),
+            }
+        }
+    }
+
+    fn acknowledge(&mut self) -> Result<usize> {
+        let delivered = std::mem::take(&mut self.delivered);
+        let acknowledged = delivered.len();
+        if !delivered.is_empty() {
+            self.queue
+                .acknowledge(delivered)
+                .with_context(|| "Couldn't acknowledge the messages of the queue!")?;
+        }
+        match self.failure.take() {
+            Some(e) => Err(e).with_context(|| {
+                format!(
+                    "Couldn't receive the messages of the queue, {} of them were saved and acknowledged!",
+                    acknowledged
+                )
+            }),
+            None => Ok(acknowledged),
+        }
+    }
+}
+
+/// Records of the messages of the queue, which are received as they are read, with the acknowledgement
+/// of the ones that were read. The messages that were received but not read are delivered again.
+pub fn consume<Q: Queue + 'static>(
+    queue: Q,
+    options: QueueOptions,
+) -> (StringRecord, RawInputs, Acknowledgement) {
+    let consumer = Rc::new(RefCell::new(Consumer {
+        queue,
+        options,
+        received: VecDeque::new(),
+        delivered: Vec::new(),
+        idle_since: Instant::now(),
+        failure: None,
+    }));
+    let reading = Rc::clone(&consumer);
+    let records = std::iter::from_fn(move || reading.borrow_mut().next());
+    (
+        StringRecord::from(INPUT_COLUMNS.to_vec()),
+        Box::new(records),
+        Acknowledgement(Box::new(move || consumer.borrow_mut().acknowledge())),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Queue with the given batches of messages, the acknowledged receipts are shared with the test.
+    struct MockQueue {
+        batches: VecDeque<Result<Vec<String>>>,
+        next_receipt: usize,
+        acknowledged: Rc<RefCell<Vec<usize>>>,
+    }
+
+    impl Queue for MockQueue {
+        type Receipt = usize;
+
+        fn receive(&mut self, _: Duration, limit: usize) -> Result<Vec<(String, usize)>> {
+            let batch = match self.batches.pop_front() {
+                Some(batch) => batch?,
+                None => return Ok(Vec::new()),
+            };
+            Ok(batch
+                .into_iter()
+                .take(limit)
+                .map(|body| {
+                    self.next_receipt += 1;
+                    (body, self.next_receipt)
+                })
+                .collect())
+        }
+
+        fn acknowledge(&mut self, receipts: Vec<usize>) -> Result<()> {
+            self.acknowledged.borrow_mut().extend(receipts);
+            Ok(())
+        }
+    }
+
+    const MESSAGE: &str = r#"{"city_code": "BER", "hotel_code": "BER00002", "room_type": "EZ",
+        "room_code": "BER898", "meal": "F", "checkin": "20180721", "adults": 1, "children": 0,
+        "price": 85.5, "source": "IHG"}"#;
+
+    fn options(max_messages: Option<usize>) -> QueueOptions {
+        QueueOptions {
+            payload: QueuePayload::Json,
+            delimiter: b'|',
+            wait: Duration::from_millis(0),
+            max_messages,
+        }
+    }
+
+    fn queue(batches: Vec<Result<Vec<String>>>) -> (MockQueue, Rc<RefCell<Vec<usize>>>) {
+        let acknowledged = Rc::new(RefCell::new(Vec::new()));
+        let queue = MockQueue {
+            batches: batches.into(),
+            next_receipt: 0,
+            acknowledged: Rc::clone(&acknowledged),
+        };
+        (queue, acknowledged)
+    }
+
+    #[test]
+    fn read_payloads() -> Result<()> {
+        let expected = vec![
+            "BER", "BER00002", "EZ", "BER898", "F", "20180721", "1", "0", "85.5", "IHG",
+        ];
+        assert_eq!(QueuePayload::Json.record(MESSAGE, b'|')?, expected);
+        assert_eq!(
+            QueuePayload::Csv.record("BER|BER00002|EZ|BER898|F|20180721|1|0|85.5|IHG\n", b'|')?,
+            expected
+        );
+        assert_eq!(
+            QueuePayload::Json.record(r#"{"hotel_code": "BER00002", "meal": null}"#, b'|')?,
+            vec!["", "BER00002", "", "", "", "", "", "", "", ""]
+        );
+        assert!(QueuePayload::Json
+            .record(r#"{"adults": [1]}"#, b'|')
+            .is_err());
+        assert!(QueuePayload::Csv.record("BER|BER00002", b'|').is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn acknowledge_read_messages() -> Result<()> {
+        let batch = vec![
+            String::from(MESSAGE),
+            String::from("broken"),
+            String::from(MESSAGE),
+        ];
+        let (queue, acknowledged) = queue(vec![Ok(batch.clone()), Ok(batch)]);
+        let (headers, records, acknowledgement) = consume(queue, options(Some(4)));
+        assert_eq!(headers.len(), INPUT_COLUMNS.len());
+        let records: Vec<_> = records.collect();
+        assert_eq!(records.len(), 4);
+        assert!(records[1].is_err());
+        assert_eq!(records[3].as_ref().unwrap().line, 4);
+        // nothing is acknowledged until the records are saved, the ones over the limit are not acknowledged
+        assert!(acknowledged.borrow().is_empty());
+        assert_eq!(acknowledgement.send()?, 4);
+        assert_eq!(*acknowledged.borrow(), vec![1, 2, 3, 4]);
+        Ok(())
+    }
+
+    #[test]
+    fn fail_after_acknowledging() {
+        let (queue, acknowledged) = queue(vec![
+            Ok(vec![String::from(MESSAGE)]),
+            Err(anyhow!("Connection reset")),
+        ]);
+        let (_, records, acknowledgement) = consume(queue, options(None));
+        assert_eq!(records.count(), 1);
+        let error = acknowledgement.send().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Couldn't receive the messages of the queue, 1 of them were saved and acknowledged!"
+        );
+        assert_eq!(*acknowledged.borrow(), vec![1]);
+    }
+}
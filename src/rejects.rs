@@ -0,0 +1,232 @@
+use std::fs::{read_to_string, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::data::{Output, Reject};
+use crate::errors::ErrorReporter;
+use crate::lock;
+
+/// Limits of the rejected records above which the whole run fails, even though it's lenient.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RejectThreshold {
+    /// How many records can be rejected at most
+    pub max_rejects: Option<usize>,
+    /// How many percent of the records can be rejected at most
+    pub max_rate: Option<f64>,
+}
+
+impl RejectThreshold {
+    pub fn is_set(&self) -> bool {
+        self.max_rejects.is_some() || self.max_rate.is_some()
+    }
+}
+
+/// Destination of the records that couldn't be enriched.
+/// When the rejects file or the threshold is given, the run is lenient: rejected records are saved in the file,
+/// one JSON object per line, and the run goes on as long as there are not too many of them.
+/// Otherwise the first rejected record stops the run, like any other error.
+pub struct RejectsSink {
+    file: Option<BufWriter<File>>,
+    reporter: Option<ErrorReporter>,
+    threshold: RejectThreshold,
+    count: usize,
+    passed: usize,
+}
+
+impl RejectsSink {
+    pub fn new(path: Option<&str>) -> Result<Self> {
+        let file = match path {
+            Some(path) => Some(BufWriter::new(lock::lock_for_writing(Path::new(path))?)),
+            None => None,
+        };
+        Ok(Self {
+            file,
+            reporter: None,
+            threshold: RejectThreshold::default(),
+            count: 0,
+            passed: 0,
+        })
+    }
+
+    /// Report every saved record to the given reporter as well.
+    pub fn with_reporter(mut self, reporter: ErrorReporter) -> Self {
+        self.reporter = Some(reporter);
+        self
+    }
+
+    /// Fail the run once there are too many rejected records.
+    pub fn with_threshold(mut self, threshold: RejectThreshold) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    fn is_lenient(&self) -> bool {
+        self.file.is_some() || self.threshold.is_set()
+    }
+
+    /// Pass the enriched record through, save the rejected one or return the error that stops the run.
+    pub fn handle(&mut self, output_res: Result<Output>) -> Result<Option<Output>> {
+        let e = match output_res {
+            Ok(output) => {
+                self.passed += 1;
+                return Ok(Some(output));
+            }
+            Err(e) if self.is_lenient() => e,
+            Err(e) => return Err(e),
+        };
+        let reject = e.downcast::<Reject>()?;
+        if let Some(reporter) = &mut self.reporter {
+            reporter.rejected(&reject.clone().into())?;
+        }
+        if let Some(file) = &mut self.file {
+            serde_json::to_writer(&mut *file, &reject)
+                .with_context(|| "Couldn't save the rejected record!")?;
+            writeln!(file).with_context(|| "Couldn't save the rejected record!")?;
+        }
+        self.count += 1;
+        // there's no point in going on once the limit is exceeded
+        match self.threshold.max_rejects {
+            Some(max_rejects) if self.count > max_rejects => Err(anyhow!(
+                "Too many records were rejected, at most {} are allowed!",
+                max_rejects
+            )),
+            _ => Ok(None),
+        }
+    }
+
+    /// Save whatever is still buffered and return how many records were rejected.
+    /// It throws an error if the rejected records exceed the threshold.
+    pub fn finish(self) -> Result<usize> {
+        if let Some(mut file) = self.file {
+            file.flush()
+                .with_context(|| "Couldn't save the rejected records!")?;
+        }
+        let total = self.count + self.passed;
+        if let Some(max_rate) = self.threshold.max_rate {
+            let rate = if total == 0 {
+                0.0
+            } else {
+                self.count as f64 * 100.0 / total as f64
+            };
+            if rate > max_rate {
+                return Err(anyhow!(
+                    "Too many records were rejected: {} of {} ({:.1}%), at most {}% are allowed!",
+                    self.count,
+                    total,
+                    rate,
+                    max_rate
+                ));
+            }
+        }
+        Ok(self.count)
+    }
+}
+
+/// Parse the rate of the rejected records, e.g 5% or just 5.
+pub fn parse_rate(value: &str) -> Result<f64, String> {
+    match value.trim_end_matches('%').parse::<f64>() {
+        Ok(rate) if (0.0..=100.0).contains(&rate) => Ok(rate),
+        _ => Err(format!("expected a percentage, e.g 5%, got '{}'", value)),
+    }
+}
+
+/// Load the rejects saved during one of the previous runs.
+pub fn load(path: &Path) -> Result<Vec<Reject>> {
+    read_to_string(path)
+        .with_context(|| "Path to the rejects is invalid!")?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str::<Reject>(line).with_context(|| {
+                format!(
+                    "Encountered unparsable entity during parsing rejects at line: {}",
+                    line
+                )
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use csv::StringRecord;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::data::RejectReason;
+
+    fn reject() -> Reject {
+        Reject::new(
+            RejectReason::MissingHotel,
+            String::from("Input links to a non existent hotel"),
+            Some(3),
+            &StringRecord::from(vec!["hotel_code"]),
+            Some(&StringRecord::from(vec!["BER00009"])),
+        )
+    }
+
+    #[test]
+    fn save_and_load_rejects() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("rejects.jsonl");
+
+        let mut sink = RejectsSink::new(path.to_str())?;
+        assert!(sink.handle(Err(reject().into()))?.is_none());
+        assert!(sink.handle(Err(reject().into()))?.is_none());
+        // errors other than rejects still stop the run
+        sink.handle(Err(anyhow!("Disk is full")))
+            .expect_err("This should fail");
+        assert_eq!(sink.finish()?, 2);
+
+        assert_eq!(load(&path)?, vec![reject(), reject()]);
+        Ok(())
+    }
+
+    #[test]
+    fn fail_above_the_threshold() -> Result<()> {
+        let mut sink = RejectsSink::new(None)?.with_threshold(RejectThreshold {
+            max_rejects: Some(1),
+            max_rate: None,
+        });
+        assert!(sink.handle(Err(reject().into()))?.is_none());
+        assert_eq!(
+            sink.handle(Err(reject().into()))
+                .expect_err("This should fail")
+                .to_string(),
+            "Too many records were rejected, at most 1 are allowed!"
+        );
+
+        let threshold = RejectThreshold {
+            max_rejects: None,
+            max_rate: Some(parse_rate("25%").unwrap()),
+        };
+        let mut sink = RejectsSink::new(None)?.with_threshold(threshold);
+        sink.handle(Err(reject().into()))?;
+        assert_eq!(
+            sink.finish().expect_err("This should fail").to_string(),
+            "Too many records were rejected: 1 of 1 (100.0%), at most 25% are allowed!"
+        );
+
+        // nothing was rejected
+        assert_eq!(
+            RejectsSink::new(None)?.with_threshold(threshold).finish()?,
+            0
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn stop_at_reject_without_a_file() -> Result<()> {
+        let mut sink = RejectsSink::new(None)?;
+        assert_eq!(
+            sink.handle(Err(reject().into()))
+                .expect_err("This should fail")
+                .to_string(),
+            "Input links to a non existent hotel"
+        );
+        Ok(())
+    }
+}
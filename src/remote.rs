@@ -0,0 +1,256 @@
+// only the local files can be resolved without the http feature
+#![cfg_attr(not(feature = "http"), allow(dead_code))]
+
+#[cfg(feature = "http")]
+use std::io::copy;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "http")]
+use std::thread::sleep;
+use std::time::Duration;
+
+#[cfg(feature = "http")]
+use anyhow::Context;
+use anyhow::{anyhow, Result};
+use tempfile::NamedTempFile;
+
+use crate::settings::Settings;
+
+/// Describes how hard we try to download the data that lives behind a URL.
+/// Every failed attempt that looks transient (timeouts, broken connections, 5xx or 429 responses)
+/// is retried after a delay that doubles with each attempt, starting at `backoff`.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub retries: u32,
+    pub backoff: Duration,
+    pub timeout: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            // fail fast means that the first failure is the final one
+            retries: if settings.remote.fail_fast_remote {
+                0
+            } else {
+                settings.remote.retries
+            },
+            backoff: Duration::from_millis(settings.remote.retry_backoff_ms),
+            timeout: Duration::from_secs(settings.remote.timeout_secs),
+        }
+    }
+
+    /// Delay before the given retry, e.g 500ms, 1s, 2s, 4s for the backoff of 500ms.
+    fn delay(&self, retry: u32) -> Duration {
+        self.backoff * 2u32.saturating_pow(retry)
+    }
+}
+
+/// File that can be handed over to the readers. Remote data is downloaded to a temporary file
+/// which is removed as soon as this value is dropped.
+pub enum LocalFile {
+    Path(PathBuf),
+    Temp(NamedTempFile),
+}
+
+impl LocalFile {
+    pub fn path(&self) -> &Path {
+        match self {
+            LocalFile::Path(path) => path,
+            LocalFile::Temp(file) => file.path(),
+        }
+    }
+}
+
+pub fn is_remote(location: &str) -> bool {
+    location.starts_with("http://") || location.starts_with("https://")
+}
+
+/// Turn the location given in the settings into a file on the disk.
+/// Local paths are returned as they are, URLs are downloaded according to the retry policy.
+pub fn resolve(location: &str, policy: &RetryPolicy) -> Result<LocalFile> {
+    if is_remote(location) {
+        download(location, policy).map(LocalFile::Temp)
+    } else {
+        Ok(LocalFile::Path(PathBuf::from(location)))
+    }
+}
+
+/// Outcome of a single failed download attempt.
+#[cfg(feature = "http")]
+enum Failure {
+    Transient(String),
+    Permanent(String),
+}
+
+#[cfg(feature = "http")]
+fn download(url: &str, policy: &RetryPolicy) -> Result<NamedTempFile> {
+    let agent = ureq::AgentBuilder::new().timeout(policy.timeout).build();
+    let mut failures = Vec::new();
+
+    for attempt in 0..=policy.retries {
+        if attempt > 0 {
+            sleep(policy.delay(attempt - 1));
+        }
+        match try_download(&agent, url) {
+            Ok(file) => return Ok(file),
+            Err(Failure::Transient(reason)) => failures.push(reason),
+            Err(Failure::Permanent(reason)) => {
+                failures.push(reason);
+                break;
+            }
+        }
+    }
+
+    let details = failures
+        .iter()
+        .enumerate()
+        .map(|(attempt, reason)| format!("attempt {}: {}", attempt + 1, reason))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Err(anyhow!(
+        "Couldn't download data from {} after {} attempt(s) ({})",
+        url,
+        failures.len(),
+        details
+    ))
+}
+
+#[cfg(not(feature = "http"))]
+fn download(url: &str, _policy: &RetryPolicy) -> Result<NamedTempFile> {
+    Err(anyhow!(
+        "axiv was built without the http feature, so {} can't be downloaded!",
+        url
+    ))
+}
+
+#[cfg(feature = "http")]
+fn try_download(agent: &ureq::Agent, url: &str) -> Result<NamedTempFile, Failure> {
+    let response = match agent.get(url).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(status, _)) if status == 429 || status >= 500 => {
+            return Err(Failure::Transient(format!(
+                "server responded with {}",
+                status
+            )))
+        }
+        Err(ureq::Error::Status(status, _)) => {
+            return Err(Failure::Permanent(format!(
+                "server responded with {}",
+                status
+            )))
+        }
+        Err(e) => return Err(Failure::Transient(e.to_string())),
+    };
+
+    let mut file = NamedTempFile::new()
+        .with_context(|| "Couldn't create a temporary file for the downloaded data!")
+        .map_err(|e| Failure::Permanent(e.to_string()))?;
+    copy(&mut response.into_reader(), &mut file)
+        .map_err(|e| Failure::Transient(format!("connection broke during transfer: {}", e)))?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "http")]
+    use std::fs::read_to_string;
+    #[cfg(feature = "http")]
+    use std::io::{Read, Write};
+    #[cfg(feature = "http")]
+    use std::net::TcpListener;
+    #[cfg(feature = "http")]
+    use std::thread;
+
+    use super::*;
+
+    /// Start a server that answers consecutive requests with given statuses and returns its address.
+    #[cfg(feature = "http")]
+    fn serve(statuses: Vec<u16>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Couldn't bind the test server");
+        let address = format!("http://{}/data", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for status in statuses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0; 1024];
+                let _ = stream.read(&mut request);
+                let body = "some data";
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {} Status\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+            }
+        });
+        address
+    }
+
+    fn policy(retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            retries,
+            backoff: Duration::from_millis(1),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    #[test]
+    fn delay_grows_exponentially() {
+        let policy = RetryPolicy {
+            retries: 3,
+            backoff: Duration::from_millis(500),
+            timeout: Duration::from_secs(1),
+        };
+        assert_eq!(policy.delay(0), Duration::from_millis(500));
+        assert_eq!(policy.delay(1), Duration::from_millis(1000));
+        assert_eq!(policy.delay(2), Duration::from_millis(2000));
+    }
+
+    #[test]
+    fn local_paths_are_not_downloaded() {
+        let file = resolve("hotels.json", &policy(0)).expect("This shouldn't fail");
+        assert_eq!(file.path(), Path::new("hotels.json"));
+    }
+
+    #[test]
+    #[cfg(feature = "http")]
+    fn retry_transient_failures() {
+        let url = serve(vec![503, 503, 200]);
+        let file = resolve(&url, &policy(2)).expect("This shouldn't fail");
+        assert_eq!(read_to_string(file.path()).unwrap(), "some data");
+    }
+
+    #[test]
+    #[cfg(feature = "http")]
+    fn report_every_failed_attempt() {
+        let url = serve(vec![503, 500]);
+        let error = resolve(&url, &policy(1))
+            .err()
+            .expect("This should fail")
+            .to_string();
+        assert_eq!(
+            error,
+            format!(
+                "Couldn't download data from {} after 2 attempt(s) (attempt 1: server responded with 503, attempt 2: server responded with 500)",
+                url
+            )
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "http")]
+    fn do_not_retry_client_errors() {
+        let url = serve(vec![404, 200]);
+        let error = resolve(&url, &policy(3))
+            .err()
+            .expect("This should fail")
+            .to_string();
+        assert_eq!(
+            error,
+            format!(
+                "Couldn't download data from {} after 1 attempt(s) (attempt 1: server responded with 404)",
+                url
+            )
+        );
+    }
+}
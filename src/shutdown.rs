@@ -0,0 +1,30 @@
+use std::process::exit;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Context, Result};
+
+/// Exit code of the run that was terminated early, same as the one of the shells for SIGINT.
+pub const TERMINATED_EXIT_CODE: i32 = 130;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Stop the run gracefully on Ctrl-C or SIGTERM: the records which are already enriched are saved
+/// and the run ends as if the input was over. The second signal stops the program immediately.
+pub fn handle_signals() -> Result<()> {
+    ctrlc::set_handler(|| {
+        if REQUESTED.swap(true, Ordering::SeqCst) {
+            exit(TERMINATED_EXIT_CODE);
+        }
+    })
+    .with_context(|| "Couldn't set up the handler of the signals!")
+}
+
+/// Whether the run was asked to stop.
+pub fn is_requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Ask the run to stop gracefully just like Ctrl-C does, e.g by the application axiv is embedded in.
+pub fn request_shutdown() {
+    REQUESTED.store(true, Ordering::SeqCst);
+}
@@ -0,0 +1,158 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+
+use crate::data::Output;
+use crate::Outputs;
+
+/// How many records are inserted in a single transaction.
+const BATCH_SIZE: usize = 1000;
+
+const CREATE_TABLE: &str = "
+    DROP TABLE IF EXISTS output;
+    CREATE TABLE output (
+        room_type_meal TEXT NOT NULL,
+        room_code TEXT NOT NULL,
+        source TEXT NOT NULL,
+        hotel_name TEXT NOT NULL,
+        city_name TEXT NOT NULL,
+        city_code TEXT NOT NULL,
+        hotel_category REAL NOT NULL,
+        pax INTEGER NOT NULL,
+        adults INTEGER NOT NULL,
+        children INTEGER NOT NULL,
+        room_name TEXT NOT NULL,
+        checkin TEXT NOT NULL,
+        checkout TEXT NOT NULL,
+        price NUMERIC NOT NULL,
+        latitude REAL,
+        longitude REAL,
+        hotel_code TEXT NOT NULL,
+        meal TEXT NOT NULL,
+        rank INTEGER,
+        offers INTEGER,
+        winning_source TEXT
+    );";
+
+const INSERT: &str = "
+    INSERT INTO output VALUES (
+        ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21
+    )";
+
+/// Database the enriched records are saved in, so they can be queried with SQL right after the run.
+/// The records are kept in the `output` table, which is created from scratch on every run.
+/// Unlike the CSV output, the values are not formatted: dates are ISO 8601 texts, numbers are numbers.
+pub struct SqliteWriter {
+    connection: Connection,
+}
+
+impl SqliteWriter {
+    pub fn create(path: &Path) -> Result<Self> {
+        let connection = Connection::open(path)
+            .with_context(|| format!("Couldn't open the database at {}!", path.display()))?;
+        connection
+            .execute_batch(CREATE_TABLE)
+            .with_context(|| "Couldn't create the output table!")?;
+        Ok(Self { connection })
+    }
+
+    /// Save the enriched records in batches, returns how many of them were saved.
+    pub fn write(&mut self, outputs: Outputs) -> Result<usize> {
+        let mut outputs = outputs.peekable();
+        let mut records = 0;
+        while outputs.peek().is_some() {
+            let transaction = self
+                .connection
+                .transaction()
+                .with_context(|| "Couldn't start the transaction!")?;
+            {
+                let mut insert = transaction.prepare_cached(INSERT)?;
+                for output_res in outputs.by_ref().take(BATCH_SIZE) {
+                    let output = output_res?;
+                    insert_output(&mut insert, &output)
+                        .with_context(|| format!("Couldn't save {:#?}", &output))?;
+                    records += 1;
+                }
+            }
+            transaction
+                .commit()
+                .with_context(|| "Couldn't commit the transaction!")?;
+        }
+        Ok(records)
+    }
+}
+
+fn insert_output(insert: &mut rusqlite::CachedStatement, output: &Output) -> Result<()> {
+    let ranking = output.ranking.as_ref();
+    insert.execute(params![
+        output.room_type_meal,
+        output.room_code,
+        output.source,
+        output.hotel_name,
+        output.city_name,
+        output.city_code,
+        output.hotel_category,
+        output.pax,
+        output.adults,
+        output.children,
+        output.room_name,
+        output.checkin.to_string(),
+        output.checkout.to_string(),
+        output.price,
+        output.latitude,
+        output.longitude,
+        output.hotel_code,
+        output.meal,
+        ranking.map(|ranking| ranking.rank as i64),
+        ranking.map(|ranking| ranking.offers as i64),
+        ranking.map(|ranking| &ranking.winning_source),
+    ])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::data::{consolidate, Consolidation};
+    use crate::testing;
+
+    #[test]
+    fn save_typed_records() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("out.db");
+        // more records than fit in a single batch
+        let outputs: Outputs =
+            Box::new((0..=BATCH_SIZE).map(|i| Ok(testing::output().price(i as f64).build())));
+        let mut writer = SqliteWriter::create(&path)?;
+        assert_eq!(writer.write(outputs)?, BATCH_SIZE + 1);
+
+        // the table is created from scratch on every run
+        let mut writer = SqliteWriter::create(&path)?;
+        let ranked = consolidate(
+            vec![
+                testing::output().build(),
+                testing::output().source("MARR").price(140.0).build(),
+            ],
+            Consolidation::Ranked,
+        );
+        let outputs: Outputs = Box::new(ranked.into_iter().map(Ok));
+        assert_eq!(writer.write(outputs)?, 2);
+
+        let connection = Connection::open(&path)?;
+        let rows: Vec<(String, f64, Option<i64>)> = connection
+            .prepare("SELECT checkin, price, rank FROM output ORDER BY price")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<_, _>>()?;
+        assert_eq!(
+            rows,
+            vec![
+                (String::from("2018-07-21"), 85.5, Some(1)),
+                (String::from("2018-07-21"), 140.0, Some(2))
+            ]
+        );
+        Ok(())
+    }
+}
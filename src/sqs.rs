@@ -0,0 +1,329 @@
+use std::env;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use hmac::{Hmac, Mac, NewMac};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::queue::Queue;
+
+/// SQS waits for the messages at most this long in a single request.
+const MAX_WAIT: Duration = Duration::from_secs(20);
+
+/// SQS receives and deletes at most this many messages in a single request.
+const MAX_BATCH: usize = 10;
+
+/// Credentials of the AWS account, from the same variables as the ones of the AWS CLI.
+struct Credentials {
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+}
+
+/// SQS queue called through its JSON API, the requests are signed with the Signature Version 4.
+pub struct SqsQueue {
+    agent: ureq::Agent,
+    queue_url: String,
+    /// Scheme and authority of the queue URL, where the requests are sent
+    endpoint: String,
+    host: String,
+    region: String,
+    credentials: Credentials,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Received {
+    #[serde(default)]
+    messages: Vec<Message>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Message {
+    body: String,
+    receipt_handle: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Deleted {
+    #[serde(default)]
+    failed: Vec<Failed>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Failed {
+    code: String,
+    #[serde(default)]
+    message: String,
+}
+
+impl SqsQueue {
+    /// Queue at the URL, e.g `https://sqs.eu-west-1.amazonaws.com/123456789012/offers`. The region is the one
+    /// of the URL, or the AWS_REGION for the other URLs (e.g the ones of ElasticMQ or LocalStack).
+    /// The credentials are taken from AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY and AWS_SESSION_TOKEN.
+    pub fn new(queue_url: &str) -> Result<Self> {
+        let (scheme, rest) = queue_url
+            .split_once("://")
+            .filter(|(scheme, _)| *scheme == "https" || *scheme == "http")
+            .ok_or_else(|| anyhow!("URL of the SQS queue {} is invalid!", queue_url))?;
+        let host = rest.split('/').next().unwrap_or_default().to_string();
+        let region = match region_of(&host) {
+            Some(region) => region,
+            None => env::var("AWS_REGION")
+                .or_else(|_| env::var("AWS_DEFAULT_REGION"))
+                .with_context(|| {
+                    format!(
+                        "Region of the SQS queue {} is unknown, it has to be given with AWS_REGION!",
+                        queue_url
+                    )
+                })?,
+        };
+        let variable = |name: &str| {
+            env::var(name).with_context(|| format!("Credentials of SQS need the {}!", name))
+        };
+        let credentials = Credentials {
+            access_key: variable("AWS_ACCESS_KEY_ID")?,
+            secret_key: variable("AWS_SECRET_ACCESS_KEY")?,
+            session_token: env::var("AWS_SESSION_TOKEN").ok(),
+        };
+        Ok(Self {
+            agent: ureq::AgentBuilder::new()
+                .timeout_connect(Duration::from_secs(10))
+                .timeout_read(MAX_WAIT + Duration::from_secs(10))
+                .build(),
+            queue_url: queue_url.to_string(),
+            endpoint: format!("{}://{}/", scheme, host),
+            host,
+            region,
+            credentials,
+        })
+    }
+
+    /// Call the action of the SQS API with the given parameters.
+    fn call(&self, action: &str, parameters: Value) -> Result<Value> {
+        let payload = parameters.to_string();
+        let amz_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let mut headers = vec![
+            ("content-type", String::from("application/x-amz-json-1.0")),
+            ("host", self.host.clone()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(token) = &self.credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        headers.push(("x-amz-target", format!("AmazonSQS.{}", action)));
+        let authorization = authorization(
+            &self.credentials,
+            &self.region,
+            "sqs",
+            "POST",
+            "/",
+            &headers,
+            payload.as_bytes(),
+            &amz_date,
+        );
+        let mut request = self
+            .agent
+            .post(&self.endpoint)
+            .set("authorization", &authorization);
+        // the host is set by the agent from the URL
+        for (name, value) in headers.iter().filter(|(name, _)| *name != "host") {
+            request = request.set(name, value);
+        }
+        match request.send_string(&payload) {
+            Ok(response) => serde_json::from_reader(response.into_reader())
+                .with_context(|| format!("Couldn't read the response of SQS to {}!", action)),
+            Err(ureq::Error::Status(status, response)) => {
+                let error: Value =
+                    serde_json::from_reader(response.into_reader()).unwrap_or_default();
+                Err(anyhow!(
+                    "SQS responded to {} with {}: {}!",
+                    action,
+                    status,
+                    error["message"]
+                        .as_str()
+                        .or_else(|| error["__type"].as_str())
+                        .unwrap_or("unknown error")
+                ))
+            }
+            Err(e) => {
+                Err(e).with_context(|| format!("Couldn't reach the SQS queue {}!", self.queue_url))
+            }
+        }
+    }
+}
+
+impl Queue for SqsQueue {
+    type Receipt = String;
+
+    fn receive(&mut self, wait: Duration, limit: usize) -> Result<Vec<(String, String)>> {
+        let response = self.call(
+            "ReceiveMessage",
+            json!({
+                "QueueUrl": self.queue_url,
+                "MaxNumberOfMessages": limit.clamp(1, MAX_BATCH),
+                "WaitTimeSeconds": wait.min(MAX_WAIT).as_secs(),
+            }),
+        )?;
+        let received: Received = serde_json::from_value(response)
+            .with_context(|| "Couldn't read the messages of the SQS queue!")?;
+        Ok(received
+            .messages
+            .into_iter()
+            .map(|message| (message.body, message.receipt_handle))
+            .collect())
+    }
+
+    fn acknowledge(&mut self, receipts: Vec<String>) -> Result<()> {
+        for batch in receipts.chunks(MAX_BATCH) {
+            let entries: Vec<Value> = batch
+                .iter()
+                .enumerate()
+                .map(|(id, receipt)| json!({"Id": id.to_string(), "ReceiptHandle": receipt}))
+                .collect();
+            let response = self.call(
+                "DeleteMessageBatch",
+                json!({"QueueUrl": self.queue_url, "Entries": entries}),
+            )?;
+            let deleted: Deleted = serde_json::from_value(response)
+                .with_context(|| "Couldn't read the deleted messages of the SQS queue!")?;
+            if let Some(failed) = deleted.failed.first() {
+                return Err(anyhow!(
+                    "SQS couldn't delete {} of the messages, e.g {}: {}!",
+                    deleted.failed.len(),
+                    failed.code,
+                    failed.message
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Region of the queue at the host, e.g `sqs.eu-west-1.amazonaws.com` or `eu-west-1.queue.amazonaws.com`.
+fn region_of(host: &str) -> Option<String> {
+    let parts: Vec<&str> = host.split('.').collect();
+    match parts.as_slice() {
+        ["sqs", region, "amazonaws", ..] | [region, "queue", "amazonaws", ..] => {
+            Some(region.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Authorization header of the request signed with the Signature Version 4. The names of the signed headers
+/// have to be in lowercase and in their alphabetical order.
+#[allow(clippy::too_many_arguments)]
+fn authorization(
+    credentials: &Credentials,
+    region: &str,
+    service: &str,
+    method: &str,
+    path: &str,
+    headers: &[(&str, String)],
+    payload: &[u8],
+    amz_date: &str,
+) -> String {
+    let date = &amz_date[..8];
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_request = format!(
+        "{}\n{}\n\n{}\n{}\n{}",
+        method,
+        path,
+        canonical_headers,
+        signed_headers,
+        hex(&Sha256::digest(payload))
+    );
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+    let mut key = hmac(
+        format!("AWS4{}", credentials.secret_key).as_bytes(),
+        date.as_bytes(),
+    );
+    for part in [region, service, "aws4_request"] {
+        key = hmac(&key, part.as_bytes());
+    }
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        credentials.access_key,
+        scope,
+        signed_headers,
+        hex(&hmac(&key, string_to_sign.as_bytes()))
+    )
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_varkey(key).expect("HMAC takes keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_request() {
+        // the get-vanilla case of the test suite of the Signature Version 4
+        let credentials = Credentials {
+            access_key: String::from("AKIDEXAMPLE"),
+            secret_key: String::from("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY"),
+            session_token: None,
+        };
+        let headers = [
+            ("host", String::from("example.amazonaws.com")),
+            ("x-amz-date", String::from("20150830T123600Z")),
+        ];
+        assert_eq!(
+            authorization(
+                &credentials,
+                "us-east-1",
+                "service",
+                "GET",
+                "/",
+                &headers,
+                b"",
+                "20150830T123600Z"
+            ),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn find_region() {
+        assert_eq!(
+            region_of("sqs.eu-west-1.amazonaws.com").as_deref(),
+            Some("eu-west-1")
+        );
+        assert_eq!(
+            region_of("us-east-2.queue.amazonaws.com").as_deref(),
+            Some("us-east-2")
+        );
+        assert_eq!(region_of("localhost:9324"), None);
+    }
+}
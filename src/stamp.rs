@@ -0,0 +1,72 @@
+use sha2::{Digest, Sha256};
+
+use crate::manifest::InputFile;
+
+/// Where the output came from, so any archived output can be traced back to exactly the code and data behind it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stamp {
+    pub axiv_version: &'static str,
+    /// SHA-256 of all of the settings of the run
+    pub settings_sha256: String,
+    /// SHA-256 of the checksums of the reference data, there's none when any of it lives behind an URL,
+    /// in a database or in Redis
+    pub reference_sha256: Option<String>,
+}
+
+impl Stamp {
+    /// Stamp of the run with the given settings and files of the reference data, by the name of the setting.
+    /// The locations of the files don't matter, so the same data moved elsewhere has the same stamp.
+    pub fn new(settings: &str, reference: Option<&[(&str, InputFile)]>) -> Self {
+        let reference_sha256 = reference.and_then(|reference| {
+            let mut hasher = Sha256::new();
+            for (setting, file) in reference {
+                hasher.update(format!("{}={}\n", setting, file.sha256.as_ref()?));
+            }
+            Some(hex(hasher))
+        });
+        let mut hasher = Sha256::new();
+        hasher.update(settings);
+        Self {
+            axiv_version: env!("CARGO_PKG_VERSION"),
+            settings_sha256: hex(hasher),
+            reference_sha256,
+        }
+    }
+}
+
+fn hex(hasher: Sha256) -> String {
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stamp_settings_and_reference_data() -> anyhow::Result<()> {
+        let rooms = || InputFile::new("test_data/room_names.csv");
+        let stamp = Stamp::new("settings", Some(&[("rooms", rooms()?)]));
+        assert_eq!(stamp.axiv_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(stamp.settings_sha256.len(), 64);
+        assert_ne!(
+            stamp.settings_sha256,
+            Stamp::new("other settings", None).settings_sha256
+        );
+
+        // the same data elsewhere has the same stamp
+        let moved = InputFile {
+            location: String::from("/archive/room_names.csv"),
+            ..rooms()?
+        };
+        assert_eq!(Stamp::new("settings", Some(&[("rooms", moved)])), stamp);
+
+        let remote = InputFile::new("https://example.com/hotels.json")?;
+        let stamp = Stamp::new("settings", Some(&[("rooms", rooms()?), ("hotels", remote)]));
+        assert_eq!(stamp.reference_sha256, None);
+        Ok(())
+    }
+}
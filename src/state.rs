@@ -0,0 +1,199 @@
+use std::fs::{create_dir_all, read_to_string};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{Local, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::checksum;
+use crate::lock;
+use crate::manifest::InputFile;
+
+/// Idempotency key of the run: hash of the version of axiv, the settings and the checksums of the inputs.
+/// There's none when any of the inputs lives behind an URL or in a database, as it can change unnoticed.
+pub fn run_key(settings: &str, inputs: &[(&str, InputFile)]) -> Option<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(env!("CARGO_PKG_VERSION"));
+    hasher.update(settings);
+    for (setting, input) in inputs {
+        hasher.update(format!(
+            "\n{}={}:{}",
+            setting,
+            input.location,
+            input.sha256.as_ref()?
+        ));
+    }
+    Some(
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect(),
+    )
+}
+
+/// Outcome of the complete run, recorded in the state directory under its idempotency key.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct RunState {
+    pub key: String,
+    /// When the run was finished, in RFC 3339 format
+    pub finished_at: String,
+    pub output: String,
+    pub files: Vec<String>,
+    pub output_sha256: Option<String>,
+    pub records: usize,
+}
+
+impl RunState {
+    /// Whether the output of the run is still in place, untouched.
+    /// Output which has no checksum (e.g a table of a database server) can't be verified, so it's never up to date.
+    pub fn is_up_to_date(&self) -> Result<bool> {
+        let output_sha256 = match &self.output_sha256 {
+            Some(output_sha256) => output_sha256,
+            None if !self.files.is_empty() => {
+                return Ok(self.files.iter().all(|file| Path::new(file).exists()))
+            }
+            None => return Ok(false),
+        };
+        let output = Path::new(&self.output);
+        Ok(output.exists() && &checksum::sha256_file(output)? == output_sha256)
+    }
+}
+
+/// Directory where the outcomes of the complete runs are recorded, one file per idempotency key.
+pub struct StateDir {
+    path: PathBuf,
+}
+
+impl StateDir {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: PathBuf::from(path),
+        }
+    }
+
+    fn state_path(&self, key: &str) -> PathBuf {
+        self.path.join(format!("{}.json", key))
+    }
+
+    /// Outcome of the previous run with the same key, if there was any.
+    pub fn find(&self, key: &str) -> Result<Option<RunState>> {
+        let path = self.state_path(key);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = read_to_string(&path).with_context(|| {
+            format!("Couldn't read the state of the run at {}!", path.display())
+        })?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .with_context(|| format!("State of the run at {} is invalid!", path.display()))
+    }
+
+    /// Record the outcome of the run, it fails when another run is saving the state under the same key.
+    pub fn save(
+        &self,
+        key: String,
+        output: &str,
+        files: &[String],
+        output_sha256: Option<String>,
+        records: usize,
+    ) -> Result<()> {
+        create_dir_all(&self.path).with_context(|| {
+            format!(
+                "Couldn't create the state directory {}!",
+                self.path.display()
+            )
+        })?;
+        let path = self.state_path(&key);
+        let state = RunState {
+            key,
+            finished_at: Local::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            output: output.to_string(),
+            files: files.to_vec(),
+            output_sha256,
+            records,
+        };
+        // another run with the same key can't overwrite the state while it's being saved
+        let mut file = lock::lock_for_writing(&path)?;
+        file.write_all(serde_json::to_string_pretty(&state)?.as_bytes())
+            .with_context(|| format!("Couldn't save the state of the run at {}!", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn keys_of_runs() -> Result<()> {
+        let input = |sha256: Option<&str>| InputFile {
+            location: String::from("input.csv"),
+            sha256: sha256.map(String::from),
+        };
+        let key = run_key("-o output.csv", &[("input", input(Some("abc")))]);
+        assert!(key.is_some());
+        assert_eq!(
+            key,
+            run_key("-o output.csv", &[("input", input(Some("abc")))])
+        );
+        assert_ne!(
+            key,
+            run_key("-o output.csv", &[("input", input(Some("abd")))])
+        );
+        assert_ne!(
+            key,
+            run_key("-o other.csv", &[("input", input(Some("abc")))])
+        );
+        assert_eq!(run_key("-o output.csv", &[("input", input(None))]), None);
+        Ok(())
+    }
+
+    #[test]
+    fn record_runs() -> Result<()> {
+        let dir = tempdir()?;
+        let output = dir.path().join("output.csv");
+        write(&output, "room_type_meal\n")?;
+        let output_sha256 = checksum::sha256_file(&output)?;
+        let state_dir = StateDir::new(dir.path().join("state").to_str().unwrap());
+        assert_eq!(state_dir.find("abc")?, None);
+
+        state_dir.save(
+            String::from("abc"),
+            output.to_str().unwrap(),
+            &[],
+            Some(output_sha256),
+            1,
+        )?;
+        let state = state_dir.find("abc")?.expect("The run should be recorded");
+        assert_eq!(state.records, 1);
+        assert!(state.is_up_to_date()?);
+
+        // output modified after the run has to be generated again
+        write(&output, "room_type_meal\nEZ F\n")?;
+        assert!(!state.is_up_to_date()?);
+        Ok(())
+    }
+
+    #[test]
+    fn skip_saving_locked_state() -> Result<()> {
+        let dir = tempdir()?;
+        let state_dir = StateDir::new(dir.path().to_str().unwrap());
+        state_dir.save(String::from("abc"), "output.csv", &[], None, 1)?;
+
+        let _held = lock::lock_for_writing(&dir.path().join("abc.json"))?;
+        let error = state_dir
+            .save(String::from("abc"), "output.csv", &[], None, 2)
+            .expect_err("This should fail");
+        assert!(error
+            .to_string()
+            .ends_with("is locked by another running instance of axiv!"));
+        Ok(())
+    }
+}
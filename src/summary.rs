@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::backpressure::QueueStats;
+use crate::data::{CacheStats, Coverage};
+use crate::shutdown::TERMINATED_EXIT_CODE;
+use crate::timings::Timings;
+
+/// Summary of a successful run, presented to the user once all of the data is saved.
+#[derive(Debug)]
+pub struct Summary {
+    /// Where the output was saved.
+    pub output: String,
+    /// Files the output was split into, when it has the {source} or {part} placeholders.
+    pub files: Vec<String>,
+    /// Whether the run was skipped, because the output of the same inputs and settings was already in place.
+    pub skipped: bool,
+    /// Whether the run was stopped (e.g with Ctrl-C) before the whole input was processed.
+    pub terminated: bool,
+    /// Coverage of the input by the reference data, when it was checked before the input was enriched.
+    pub coverage: Option<Coverage>,
+    /// How many records were saved in the output.
+    pub records: usize,
+    /// How many messages of the queue were acknowledged, when the input was consumed from one.
+    pub acknowledged: Option<usize>,
+    /// How many records were rejected, when they were saved in the rejects file.
+    pub rejects: Option<usize>,
+    /// How many duplicates of the records of the input were found, when they were detected.
+    pub duplicates: Option<usize>,
+    /// How many records were excluded by each of the filters, e.g min_category.
+    pub excluded: BTreeMap<String, usize>,
+    /// How many records with suspicious prices were saved in the review file instead of the output.
+    pub anomalies: Option<usize>,
+    /// How many violations of the output contract there were in each of the columns, when they are just counted.
+    pub contract_violations: BTreeMap<String, usize>,
+    /// How many values of each of the columns were truncated or redacted to fit their limits.
+    pub limited: BTreeMap<String, usize>,
+    /// How many warnings of each kind there were.
+    pub warnings: BTreeMap<String, usize>,
+    /// Statistics of the caches of the data sources that are looked up remotely, e.g in Redis.
+    pub caches: BTreeMap<String, CacheStats>,
+    /// Why the merge join of the rooms fell back to all of them in memory, when it did.
+    pub merge_join_fallback: Option<String>,
+    /// How full the queue in front of the sink was, when the output was written to it on another thread.
+    pub sink_queue: Option<QueueStats>,
+    /// Time spent in each of the stages of the run, when it was measured.
+    pub timings: Option<Timings>,
+    /// SHA-256 checksum of the output file, there's none when the output is a table of a database server
+    /// or when it was split into many files.
+    pub output_sha256: Option<String>,
+}
+
+impl Summary {
+    /// Exit code of the program after the run, the terminated one isn't a success.
+    pub fn exit_code(&self) -> i32 {
+        if self.terminated {
+            TERMINATED_EXIT_CODE
+        } else {
+            0
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.skipped {
+            write!(
+                f,
+                "The inputs and the settings haven't changed since the previous run, the output at {} is up to date",
+                self.output
+            )?;
+        } else if self.terminated {
+            write!(
+                f,
+                "The run was terminated early, only a part of the data was saved at {}",
+                self.output
+            )?;
+        } else {
+            write!(
+                f,
+                "The data was successfully parsed and saved at {}",
+                self.output
+            )?;
+        }
+        if !self.files.is_empty() {
+            write!(f, "\nFiles written: {}", self.files.join(", "))?;
+        }
+        if let Some(coverage) = &self.coverage {
+            write!(f, "\nCoverage of the input: {}", coverage)?;
+        }
+        write!(f, "\nRecords written: {}", self.records)?;
+        if let Some(acknowledged) = self.acknowledged {
+            write!(f, "\nMessages acknowledged: {}", acknowledged)?;
+        }
+        if let Some(rejects) = self.rejects {
+            write!(f, "\nRecords rejected: {}", rejects)?;
+        }
+        if let Some(duplicates) = self.duplicates {
+            write!(f, "\nDuplicate records: {}", duplicates)?;
+        }
+        if !self.excluded.is_empty() {
+            write!(f, "\nRecords excluded: {}", counts(&self.excluded))?;
+        }
+        if let Some(anomalies) = self.anomalies {
+            write!(f, "\nRecords flagged for review: {}", anomalies)?;
+        }
+        if !self.contract_violations.is_empty() {
+            write!(
+                f,
+                "\nContract violations: {}",
+                counts(&self.contract_violations)
+            )?;
+        }
+        if !self.limited.is_empty() {
+            write!(f, "\nValues cut to the limits: {}", counts(&self.limited))?;
+        }
+        if !self.warnings.is_empty() {
+            write!(f, "\nWarnings: {}", counts(&self.warnings))?;
+        }
+        if let Some(reason) = &self.merge_join_fallback {
+            write!(
+                f,
+                "\nMerge join fell back to the rooms in memory: {}",
+                reason
+            )?;
+        }
+        for (name, stats) in &self.caches {
+            write!(
+                f,
+                "\nCache of the {}: {} hits, {} misses ({:.1}% hit rate)",
+                name,
+                stats.hits,
+                stats.misses,
+                stats.hit_rate()
+            )?;
+        }
+        if let Some(sink_queue) = &self.sink_queue {
+            write!(f, "\nSink queue: {}", sink_queue)?;
+        }
+        if let Some(timings) = &self.timings {
+            write!(f, "\nTimings: {}", timings)?;
+        }
+        if let Some(output_sha256) = &self.output_sha256 {
+            write!(f, "\nOutput SHA-256: {}", output_sha256)?;
+        }
+        Ok(())
+    }
+}
+
+/// Total of the counts followed by the count of each kind, e.g `3 (exclude_sources: 2, min_category: 1)`.
+fn counts(counts: &BTreeMap<String, usize>) -> String {
+    let kinds = counts
+        .iter()
+        .map(|(kind, count)| format!("{}: {}", kind, count))
+        .collect::<Vec<_>>();
+    format!("{} ({})", counts.values().sum::<usize>(), kinds.join(", "))
+}
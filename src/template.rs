@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Local};
+use csv::Writer;
+
+use crate::compression::{finish_writer, OutputFile};
+use crate::data::{Output, OutputFormat, OutputRecord};
+
+/// Location of the output with placeholders, e.g `output_{date}_{source}_{part}.csv`.
+/// Date ({date}, e.g 20200917) and time ({time}, e.g 235400) of the run are the same for every file,
+/// while the source ({source}) and the number of the part ({part}) split the output into many files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputTemplate {
+    template: String,
+}
+
+impl OutputTemplate {
+    pub fn new(template: &str, now: DateTime<Local>) -> Self {
+        Self {
+            template: template
+                .replace("{date}", &now.format("%Y%m%d").to_string())
+                .replace("{time}", &now.format("%H%M%S").to_string()),
+        }
+    }
+
+    /// Whether the output is split into many files.
+    pub fn is_partitioned(&self) -> bool {
+        self.template.contains("{source}") || self.template.contains("{part}")
+    }
+
+    /// Location of the output once the placeholders of the run are resolved.
+    pub fn as_str(&self) -> &str {
+        &self.template
+    }
+
+    /// Location of the given part of the output of the given source.
+    pub fn path(&self, source: &str, part: usize) -> String {
+        self.template
+            .replace("{source}", source)
+            .replace("{part}", &part.to_string())
+    }
+}
+
+/// Function creating the writer of a file of the output.
+pub type OpenFile = Box<dyn Fn(&Path) -> Result<Writer<OutputFile>>>;
+
+/// Writer of the output split into files by the source of the records, or into parts of at most `rows_per_file` rows.
+pub struct PartitionedWriter {
+    template: OutputTemplate,
+    rows_per_file: Option<usize>,
+    open: OpenFile,
+    partitions: HashMap<String, Partition>,
+    files: Vec<String>,
+}
+
+struct Partition {
+    part: usize,
+    rows: usize,
+    writer: Writer<OutputFile>,
+}
+
+impl PartitionedWriter {
+    /// Files are created with the given function as soon as the first record of each of them is known.
+    pub fn new(
+        template: OutputTemplate,
+        rows_per_file: Option<usize>,
+        open: OpenFile,
+    ) -> Result<Self> {
+        if rows_per_file.is_some() && !template.as_str().contains("{part}") {
+            return Err(anyhow!(
+                "The output has to have the {} placeholder to be split into parts!",
+                "{part}"
+            ));
+        }
+        Ok(Self {
+            template,
+            rows_per_file,
+            open,
+            partitions: HashMap::new(),
+            files: Vec::new(),
+        })
+    }
+
+    fn open_part(&mut self, source: &str, part: usize) -> Result<Partition> {
+        let path = self.template.path(source, part);
+        let writer = (self.open)(Path::new(&path))?;
+        self.files.push(path);
+        Ok(Partition {
+            part,
+            rows: 0,
+            writer,
+        })
+    }
+
+    pub fn write(&mut self, output: &Output, format: &OutputFormat) -> Result<()> {
+        let source = if self.template.as_str().contains("{source}") {
+            output.source.as_str()
+        } else {
+            ""
+        };
+        let next_part = match self.partitions.get(source) {
+            None => Some(1),
+            Some(partition) if Some(partition.rows) == self.rows_per_file => {
+                Some(partition.part + 1)
+            }
+            Some(_) => None,
+        };
+        if let Some(part) = next_part {
+            let partition = self.open_part(source, part)?;
+            if let Some(full) = self.partitions.insert(source.to_string(), partition) {
+                finish_writer(full.writer)
+                    .with_context(|| "Couldn't save the part of the output!")?;
+            }
+        }
+        let partition = self
+            .partitions
+            .get_mut(source)
+            .ok_or_else(|| anyhow!("There's no part of the output for the source {}!", source))?;
+        partition
+            .writer
+            .serialize(OutputRecord::new(output, format))
+            .with_context(|| format!("Couldn't serialize {:#?}", output))?;
+        partition.rows += 1;
+        Ok(())
+    }
+
+    /// Save whatever is still buffered and return the files the output was split into.
+    pub fn finish(mut self) -> Result<Vec<String>> {
+        for (_, partition) in self.partitions.drain() {
+            finish_writer(partition.writer)
+                .with_context(|| "Couldn't save the part of the output!")?;
+        }
+        self.files.sort();
+        Ok(self.files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{read_to_string, File};
+
+    use chrono::TimeZone;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::compression::Compression;
+    use crate::data::{CategoryFormat, NumberFormat};
+    use crate::testing;
+
+    #[test]
+    fn resolve_placeholders() {
+        let now = Local.ymd(2020, 9, 17).and_hms(23, 54, 0);
+        let template = OutputTemplate::new("out/{date}_{time}_{source}_{part}.csv", now);
+        assert_eq!(template.as_str(), "out/20200917_235400_{source}_{part}.csv");
+        assert!(template.is_partitioned());
+        assert_eq!(template.path("IHG", 2), "out/20200917_235400_IHG_2.csv");
+        assert!(!OutputTemplate::new("output_{date}.csv", now).is_partitioned());
+    }
+
+    #[test]
+    fn split_output() -> Result<()> {
+        let dir = tempdir()?;
+        let location = dir.path().join("output_{source}_{part}.csv");
+        let template = OutputTemplate::new(location.to_str().unwrap(), Local::now());
+        let format = OutputFormat {
+            number: NumberFormat::from_locale("plain").unwrap(),
+            category: CategoryFormat::Raw,
+            coordinates: false,
+            chains: false,
+            ranking: false,
+            run_date: None,
+            seasons: None,
+            match_status: false,
+            match_confidence: false,
+            candidate_room_names: None,
+            derived_metrics: false,
+            stamp: None,
+            dropped: Vec::new(),
+        };
+        let open: OpenFile = Box::new(|path| {
+            let file = OutputFile::new(File::create(path)?, Compression::None, None)?;
+            Ok(Writer::from_writer(file))
+        });
+
+        let mut writer = PartitionedWriter::new(template, Some(2), open)?;
+        for source in ["IHG", "MARR", "IHG", "IHG"].iter() {
+            writer.write(&testing::output().source(source).build(), &format)?;
+        }
+        let files = writer.finish()?;
+
+        let names: Vec<String> = files
+            .iter()
+            .map(|file| {
+                Path::new(file)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec!["output_IHG_1.csv", "output_IHG_2.csv", "output_MARR_1.csv"]
+        );
+        // every part has its own header
+        assert_eq!(read_to_string(&files[0])?.lines().count(), 3);
+        assert_eq!(read_to_string(&files[1])?.lines().count(), 2);
+        Ok(())
+    }
+}
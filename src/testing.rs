@@ -0,0 +1,450 @@
+//! Builders of the entities and in-memory data sources, so the scenarios can be set up in the tests
+//! without any fixture files. It's only available with the `testing` feature, and in the unit tests of axiv itself.
+//!
+//! ```
+//! use axiv::testing::{hotel, hotels, input, integrator, room, rooms};
+//!
+//! let outputs = integrator(
+//!     rooms(vec![room().build()]),
+//!     hotels(vec![hotel().build()]),
+//!     vec![input().price(120.0).adults(2).build()],
+//! )
+//! .collect::<anyhow::Result<Vec<_>>>()
+//! .unwrap();
+//! assert_eq!(outputs[0].price, 60.0);
+//! ```
+
+use std::num::NonZeroU16;
+
+use chrono::{Duration, NaiveDate};
+use csv::StringRecord;
+
+use crate::data::custom_date::INPUT_FORMAT;
+use crate::data::RawInput;
+pub use crate::data::{
+    csv_input, hotels_reader_for, rooms_reader, DataIntegrator, DataSource, Hotel, HotelDataSource,
+    Input, Output, Room, RoomDataSource,
+};
+
+/// Builder of the Room, by default it's the room BER898 of the hotel BER00002 from IHG.
+pub struct RoomBuilder {
+    room: Room,
+}
+
+pub fn room() -> RoomBuilder {
+    RoomBuilder {
+        room: Room {
+            hotel_code: String::from("BER00002"),
+            source: String::from("IHG"),
+            room_name: String::from("Standard"),
+            room_code: String::from("BER898"),
+            valid_from: None,
+            valid_to: None,
+        },
+    }
+}
+
+impl RoomBuilder {
+    pub fn hotel_code(mut self, hotel_code: &str) -> Self {
+        self.room.hotel_code = hotel_code.to_string();
+        self
+    }
+
+    pub fn source(mut self, source: &str) -> Self {
+        self.room.source = source.to_string();
+        self
+    }
+
+    pub fn room_name(mut self, room_name: &str) -> Self {
+        self.room.room_name = room_name.to_string();
+        self
+    }
+
+    pub fn room_code(mut self, room_code: &str) -> Self {
+        self.room.room_code = room_code.to_string();
+        self
+    }
+
+    pub fn build(self) -> Room {
+        self.room
+    }
+}
+
+/// Builder of the Hotel, by default it's the hotel BER00002 in Berlin with the category 4.
+pub struct HotelBuilder {
+    hotel: Hotel,
+}
+
+pub fn hotel() -> HotelBuilder {
+    HotelBuilder {
+        hotel: Hotel {
+            id: String::from("BER00002"),
+            city_code: String::from("BER"),
+            name: String::from("Crowne Plaza Berlin City Centre"),
+            category: 4.0,
+            country_code: String::from("DE"),
+            city: String::from("Berlin"),
+            valid_from: None,
+            valid_to: None,
+        },
+    }
+}
+
+impl HotelBuilder {
+    pub fn id(mut self, id: &str) -> Self {
+        self.hotel.id = id.to_string();
+        self
+    }
+
+    pub fn city_code(mut self, city_code: &str) -> Self {
+        self.hotel.city_code = city_code.to_string();
+        self
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.hotel.name = name.to_string();
+        self
+    }
+
+    pub fn category(mut self, category: f32) -> Self {
+        self.hotel.category = category;
+        self
+    }
+
+    pub fn country_code(mut self, country_code: &str) -> Self {
+        self.hotel.country_code = country_code.to_string();
+        self
+    }
+
+    pub fn city(mut self, city: &str) -> Self {
+        self.hotel.city = city.to_string();
+        self
+    }
+
+    pub fn build(self) -> Hotel {
+        self.hotel
+    }
+}
+
+/// Builder of the Input, by default it's an offer of a single night in the room built by [room] for a single adult.
+pub struct InputBuilder {
+    input: Input,
+}
+
+pub fn input() -> InputBuilder {
+    InputBuilder {
+        input: Input {
+            city_code: String::from("BER"),
+            hotel_code: String::from("BER00002"),
+            room_type: String::from("EZ"),
+            room_code: String::from("BER898"),
+            meal: String::from("F"),
+            checkin: NaiveDate::from_ymd(2018, 7, 21),
+            adults: 1,
+            children: 0,
+            price: 85.5,
+            source: String::from("IHG"),
+            nights: None,
+            checkout: None,
+        },
+    }
+}
+
+impl InputBuilder {
+    pub fn city_code(mut self, city_code: &str) -> Self {
+        self.input.city_code = city_code.to_string();
+        self
+    }
+
+    pub fn hotel_code(mut self, hotel_code: &str) -> Self {
+        self.input.hotel_code = hotel_code.to_string();
+        self
+    }
+
+    pub fn room_type(mut self, room_type: &str) -> Self {
+        self.input.room_type = room_type.to_string();
+        self
+    }
+
+    pub fn room_code(mut self, room_code: &str) -> Self {
+        self.input.room_code = room_code.to_string();
+        self
+    }
+
+    pub fn meal(mut self, meal: &str) -> Self {
+        self.input.meal = meal.to_string();
+        self
+    }
+
+    pub fn checkin(mut self, checkin: NaiveDate) -> Self {
+        self.input.checkin = checkin;
+        self
+    }
+
+    pub fn adults(mut self, adults: u8) -> Self {
+        self.input.adults = adults;
+        self
+    }
+
+    pub fn children(mut self, children: u8) -> Self {
+        self.input.children = children;
+        self
+    }
+
+    pub fn price(mut self, price: f64) -> Self {
+        self.input.price = price;
+        self
+    }
+
+    pub fn source(mut self, source: &str) -> Self {
+        self.input.source = source.to_string();
+        self
+    }
+
+    pub fn nights(mut self, nights: u16) -> Self {
+        self.input.nights = NonZeroU16::new(nights);
+        self
+    }
+
+    pub fn checkout(mut self, checkout: NaiveDate) -> Self {
+        self.input.checkout = Some(checkout);
+        self
+    }
+
+    pub fn build(self) -> Input {
+        self.input
+    }
+}
+
+/// Builder of the Output, by default it's the offer built by [input] enriched with the room Einzelzimmer
+/// of the hotel Crowne Plaza, the short names most of the tests of the output use.
+pub struct OutputBuilder {
+    output: Output,
+}
+
+pub fn output() -> OutputBuilder {
+    OutputBuilder {
+        output: Output {
+            room_type_meal: String::from("EZ F"),
+            room_code: String::from("BER898"),
+            source: String::from("IHG"),
+            hotel_name: String::from("Crowne Plaza"),
+            city_name: String::from("Berlin"),
+            city_code: String::from("BER"),
+            hotel_category: 4.0,
+            pax: 1,
+            adults: 1,
+            children: 0,
+            room_name: String::from("Einzelzimmer"),
+            checkin: NaiveDate::from_ymd(2018, 7, 21),
+            checkout: NaiveDate::from_ymd(2018, 7, 22),
+            price: 85.5,
+            latitude: None,
+            longitude: None,
+            hotel_code: String::from("BER00002"),
+            meal: String::from("F"),
+            ranking: None,
+            match_status: None,
+            match_confidence: None,
+            candidate_room_names: Vec::new(),
+            chain_code: None,
+            brand_name: None,
+            warnings: Vec::new(),
+        },
+    }
+}
+
+impl OutputBuilder {
+    /// Room type of the offer, it's joined with the meal.
+    pub fn room_type(mut self, room_type: &str) -> Self {
+        self.output.room_type_meal = format!("{} {}", room_type, self.output.meal);
+        self
+    }
+
+    pub fn room_code(mut self, room_code: &str) -> Self {
+        self.output.room_code = room_code.to_string();
+        self
+    }
+
+    pub fn source(mut self, source: &str) -> Self {
+        self.output.source = source.to_string();
+        self
+    }
+
+    pub fn hotel_name(mut self, hotel_name: &str) -> Self {
+        self.output.hotel_name = hotel_name.to_string();
+        self
+    }
+
+    pub fn city_code(mut self, city_code: &str) -> Self {
+        self.output.city_code = city_code.to_string();
+        self
+    }
+
+    pub fn hotel_category(mut self, hotel_category: f32) -> Self {
+        self.output.hotel_category = hotel_category;
+        self
+    }
+
+    /// Adults of the offer, they are counted into the pax.
+    pub fn adults(mut self, adults: u8) -> Self {
+        self.output.adults = adults;
+        self.output.pax = adults + self.output.children;
+        self
+    }
+
+    /// Children of the offer, they are counted into the pax.
+    pub fn children(mut self, children: u8) -> Self {
+        self.output.children = children;
+        self.output.pax = self.output.adults + children;
+        self
+    }
+
+    pub fn room_name(mut self, room_name: &str) -> Self {
+        self.output.room_name = room_name.to_string();
+        self
+    }
+
+    /// Checkin of the offer, it's a single night unless the checkout is given as well.
+    pub fn checkin(mut self, checkin: NaiveDate) -> Self {
+        self.output.checkin = checkin;
+        self.output.checkout = checkin + Duration::days(1);
+        self
+    }
+
+    pub fn checkout(mut self, checkout: NaiveDate) -> Self {
+        self.output.checkout = checkout;
+        self
+    }
+
+    pub fn price(mut self, price: f64) -> Self {
+        self.output.price = price;
+        self
+    }
+
+    pub fn latitude(mut self, latitude: f64) -> Self {
+        self.output.latitude = Some(latitude);
+        self
+    }
+
+    pub fn hotel_code(mut self, hotel_code: &str) -> Self {
+        self.output.hotel_code = hotel_code.to_string();
+        self
+    }
+
+    pub fn build(self) -> Output {
+        self.output
+    }
+}
+
+/// Rooms data source with the given rooms in memory.
+pub fn rooms(rooms: Vec<Room>) -> RoomDataSource {
+    let mut data_source: RoomDataSource = DataSource::new();
+    for room in rooms {
+        data_source.insert(room.key(), room);
+    }
+    data_source
+}
+
+/// Hotels data source with the given hotels in memory.
+pub fn hotels(hotels: Vec<Hotel>) -> HotelDataSource {
+    let mut data_source: HotelDataSource = DataSource::new();
+    for hotel in hotels {
+        data_source.insert(hotel.id.clone(), hotel);
+    }
+    data_source
+}
+
+/// Columns of the input the records are given in.
+const COLUMNS: [&str; 12] = [
+    "city_code",
+    "hotel_code",
+    "room_type",
+    "room_code",
+    "meal",
+    "checkin",
+    "adults",
+    "children",
+    "price",
+    "source",
+    "nights",
+    "checkout",
+];
+
+/// Raw record of the input, as it would be read from the input file.
+fn record(input: &Input) -> StringRecord {
+    StringRecord::from(vec![
+        input.city_code.clone(),
+        input.hotel_code.clone(),
+        input.room_type.clone(),
+        input.room_code.clone(),
+        input.meal.clone(),
+        input.checkin.format(INPUT_FORMAT).to_string(),
+        input.adults.to_string(),
+        input.children.to_string(),
+        input.price.to_string(),
+        input.source.clone(),
+        input
+            .nights
+            .map_or_else(String::new, |nights| nights.to_string()),
+        input.checkout.map_or_else(String::new, |checkout| {
+            checkout.format(INPUT_FORMAT).to_string()
+        }),
+    ])
+}
+
+/// Integrator of the given records of the input, they are on the lines starting at 2, as if there was a header.
+pub fn integrator(
+    rooms: RoomDataSource,
+    hotels: HotelDataSource,
+    inputs: Vec<Input>,
+) -> DataIntegrator {
+    let records: Vec<_> = inputs
+        .iter()
+        .enumerate()
+        .map(|(index, input)| {
+            Ok(RawInput {
+                line: index as u64 + 2,
+                record: record(input),
+            })
+        })
+        .collect();
+    DataIntegrator::new(
+        rooms,
+        hotels,
+        StringRecord::from(COLUMNS.to_vec()),
+        Box::new(records.into_iter()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn integrate_built_scenario() -> Result<()> {
+        let outputs: Vec<Result<Output>> = integrator(
+            rooms(vec![room().build(), room().source("GTA").build()]),
+            hotels(vec![hotel().category(5.0).build()]),
+            vec![
+                input().nights(3).build(),
+                input().source("GTA").adults(2).price(100.0).build(),
+                input().room_code("BER899").build(),
+                input().checkout(NaiveDate::from_ymd(2018, 7, 23)).build(),
+            ],
+        )
+        .collect();
+        assert_eq!(outputs.len(), 4);
+        let first = outputs[0].as_ref().expect("It should be enriched");
+        assert_eq!(first.hotel_category, 5.0);
+        assert_eq!(first.checkout, NaiveDate::from_ymd(2018, 7, 24));
+        let second = outputs[1].as_ref().expect("It should be enriched");
+        assert_eq!((second.source.as_str(), second.price), ("GTA", 50.0));
+        assert!(outputs[2].is_err());
+        let fourth = outputs[3].as_ref().expect("It should be enriched");
+        assert_eq!(fourth.checkout, NaiveDate::from_ymd(2018, 7, 23));
+        Ok(())
+    }
+}
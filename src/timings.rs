@@ -0,0 +1,176 @@
+use std::fmt;
+use std::ops::AddAssign;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "allocations")]
+mod counting {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    pub static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+    /// System allocator that counts the allocations, so they can be broken down by the stages of the run.
+    struct CountingAllocator;
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+}
+
+/// How many allocations there were so far, they are counted only with the allocations feature.
+fn allocations() -> Option<u64> {
+    #[cfg(feature = "allocations")]
+    return Some(counting::ALLOCATIONS.load(std::sync::atomic::Ordering::Relaxed));
+    #[cfg(not(feature = "allocations"))]
+    None
+}
+
+/// Time (and allocations) spent in a stage of the run.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Usage {
+    pub time: Duration,
+    pub allocations: Option<u64>,
+}
+
+impl Usage {
+    /// What's left of the usage once the other one is taken away from it.
+    pub fn without(self, other: Usage) -> Usage {
+        Usage {
+            time: self.time.saturating_sub(other.time),
+            allocations: self
+                .allocations
+                .map(|allocations| allocations.saturating_sub(other.allocations.unwrap_or(0))),
+        }
+    }
+}
+
+impl AddAssign for Usage {
+    fn add_assign(&mut self, other: Usage) {
+        self.time += other.time;
+        self.allocations = match (self.allocations, other.allocations) {
+            (Some(allocations), Some(other)) => Some(allocations + other),
+            (allocations, other) => allocations.or(other),
+        };
+    }
+}
+
+/// Moment from which the usage is measured.
+#[derive(Debug, Clone, Copy)]
+pub struct Snapshot {
+    instant: Instant,
+    allocations: Option<u64>,
+}
+
+impl Snapshot {
+    pub fn now() -> Self {
+        Self {
+            instant: Instant::now(),
+            allocations: allocations(),
+        }
+    }
+
+    pub fn elapsed(&self) -> Usage {
+        Usage {
+            time: self.instant.elapsed(),
+            allocations: match (allocations(), self.allocations) {
+                (Some(now), Some(then)) => Some(now - then),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Breakdown of the run by its stages, in the order in which they are done.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Timings {
+    pub stages: Vec<(&'static str, Usage)>,
+}
+
+impl Timings {
+    pub fn with_stage(mut self, stage: &'static str, usage: Usage) -> Self {
+        self.stages.push((stage, usage));
+        self
+    }
+}
+
+impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self
+            .stages
+            .iter()
+            .map(|(_, usage)| usage.time)
+            .sum::<Duration>()
+            .as_secs_f64();
+        let stages = self
+            .stages
+            .iter()
+            .map(|(stage, usage)| {
+                let share = if total > 0.0 {
+                    usage.time.as_secs_f64() / total * 100.0
+                } else {
+                    0.0
+                };
+                let allocations = match usage.allocations {
+                    Some(allocations) => format!(", {} allocations", allocations),
+                    None => String::new(),
+                };
+                format!(
+                    "{} {:.3}s ({:.1}%{})",
+                    stage,
+                    usage.time.as_secs_f64(),
+                    share,
+                    allocations
+                )
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{}", stages.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(millis: u64, allocations: Option<u64>) -> Usage {
+        Usage {
+            time: Duration::from_millis(millis),
+            allocations,
+        }
+    }
+
+    #[test]
+    fn break_down_stages() {
+        let mut read = usage(100, Some(10));
+        read += usage(150, Some(5));
+        assert_eq!(read, usage(250, Some(15)));
+        assert_eq!(usage(1000, Some(30)).without(read), usage(750, Some(15)));
+        assert_eq!(usage(100, None).without(read), usage(0, None));
+
+        let timings = Timings::default()
+            .with_stage("read", usage(250, None))
+            .with_stage("write", usage(750, None));
+        assert_eq!(
+            timings.to_string(),
+            "read 0.250s (25.0%), write 0.750s (75.0%)"
+        );
+        let timings = Timings::default().with_stage("load", usage(500, Some(42)));
+        assert_eq!(timings.to_string(), "load 0.500s (100.0%, 42 allocations)");
+    }
+
+    #[test]
+    fn measure_since_snapshot() {
+        let snapshot = Snapshot::now();
+        let usage = snapshot.elapsed();
+        assert_eq!(usage.allocations.is_some(), cfg!(feature = "allocations"));
+    }
+}
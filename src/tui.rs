@@ -0,0 +1,297 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+
+use anyhow::{Context, Result};
+use strsim::normalized_levenshtein;
+
+use crate::data::{
+    generate_room_key, Action, Entry, HotelDataSource, Override, Reject, RejectReason, Room,
+    RoomDataSource,
+};
+
+/// How many of the nearest rooms or hotels are suggested for each of the rejects.
+const CANDIDATES: usize = 3;
+
+/// Entry of the reference data that resembles the one that's missing, together with the correction
+/// that adds the missing entry based on it.
+struct Candidate {
+    description: String,
+    correction: Override,
+}
+
+/// Nearest entries of the reference data for the one the rejected record links to, ordered by the similarity of their keys.
+fn candidates(reject: &Reject, rooms: &RoomDataSource, hotels: &HotelDataSource) -> Vec<Candidate> {
+    let hotel_code = reject.value("hotel_code");
+    match reject.reason {
+        RejectReason::MissingRoom => {
+            let key = generate_room_key(
+                hotel_code,
+                reject.value("room_code"),
+                reject.value("source"),
+            );
+            let mut nearest: Vec<(f64, &String, &Room)> = rooms
+                .iter()
+                .map(|(room_key, room)| (normalized_levenshtein(&key, room_key), room_key, room))
+                .collect();
+            nearest.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(b.1)));
+            nearest
+                .into_iter()
+                .take(CANDIDATES)
+                .map(|(similarity, room_key, room)| Candidate {
+                    description: format!(
+                        "room {} \"{}\" (similarity {:.2})",
+                        room_key, room.room_name, similarity
+                    ),
+                    correction: Override {
+                        action: Action::Add,
+                        entry: Entry::Room(Room {
+                            hotel_code: hotel_code.to_string(),
+                            source: reject.value("source").to_string(),
+                            room_name: room.room_name.clone(),
+                            room_code: reject.value("room_code").to_string(),
+                            valid_from: None,
+                            valid_to: None,
+                        }),
+                    },
+                })
+                .collect()
+        }
+        RejectReason::MissingHotel => {
+            let mut nearest: Vec<_> = hotels
+                .iter()
+                .map(|(id, hotel)| (normalized_levenshtein(hotel_code, id), id, hotel))
+                .collect();
+            nearest.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(b.1)));
+            nearest
+                .into_iter()
+                .take(CANDIDATES)
+                .map(|(similarity, id, hotel)| {
+                    let mut missing = hotel.clone();
+                    missing.id = hotel_code.to_string();
+                    Candidate {
+                        description: format!(
+                            "hotel {} \"{}\" (similarity {:.2})",
+                            id, hotel.name, similarity
+                        ),
+                        correction: Override {
+                            action: Action::Add,
+                            entry: Entry::Hotel(missing),
+                        },
+                    }
+                })
+                .collect()
+        }
+        RejectReason::Unparsable
+        | RejectReason::InvalidField
+        | RejectReason::Duplicate
+        | RejectReason::RecordTooLarge => vec![],
+    }
+}
+
+fn show<W: Write>(
+    output: &mut W,
+    rejects: &[Reject],
+    index: usize,
+    candidates: &[Candidate],
+    correction: Option<&Override>,
+) -> Result<()> {
+    let reject = &rejects[index];
+    let line = reject.line.map_or_else(
+        || String::from("unknown line"),
+        |line| format!("line {}", line),
+    );
+    writeln!(
+        output,
+        "Reject {} of {}, {}",
+        index + 1,
+        rejects.len(),
+        line
+    )?;
+    writeln!(
+        output,
+        "{}",
+        reject
+            .message
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    )?;
+    let values: Vec<String> = reject
+        .record
+        .iter()
+        .map(|(column, value)| format!("{}={}", column, value))
+        .collect();
+    writeln!(output, "  {}", values.join(" "))?;
+    if candidates.is_empty() {
+        writeln!(output, "No candidates")?;
+    } else {
+        writeln!(output, "Candidates:")?;
+        for (number, candidate) in candidates.iter().enumerate() {
+            writeln!(output, "  {}) {}", number + 1, candidate.description)?;
+        }
+    }
+    if let Some(correction) = correction {
+        writeln!(output, "Correction: {}", serde_json::to_string(correction)?)?;
+    }
+    Ok(())
+}
+
+/// Interactively page through the rejects, suggesting the nearest rooms or hotels for each of them.
+/// Accepting one of the candidates creates a correction that adds the missing room or hotel to the reference data.
+/// Returns corrections in the order of the rejects they fix.
+pub fn explore<R: BufRead, W: Write>(
+    rejects: &[Reject],
+    rooms: &RoomDataSource,
+    hotels: &HotelDataSource,
+    mut input: R,
+    mut output: W,
+) -> Result<Vec<Override>> {
+    if rejects.is_empty() {
+        writeln!(output, "There are no rejects to explore.")?;
+        return Ok(vec![]);
+    }
+
+    let mut corrections = BTreeMap::new();
+    let mut index = 0;
+    loop {
+        let nearest = candidates(&rejects[index], rooms, hotels);
+        show(
+            &mut output,
+            rejects,
+            index,
+            &nearest,
+            corrections.get(&index),
+        )?;
+        write!(
+            output,
+            "[n]ext, [p]revious, [1-{}] accept the candidate, [q]uit > ",
+            CANDIDATES
+        )?;
+        output.flush()?;
+
+        let mut command = String::new();
+        if input
+            .read_line(&mut command)
+            .with_context(|| "Couldn't read the command!")?
+            == 0
+        {
+            break;
+        }
+        match command.trim() {
+            "n" | "" => index = (index + 1).min(rejects.len() - 1),
+            "p" => index = index.saturating_sub(1),
+            "q" => break,
+            number => match number.parse::<usize>() {
+                Ok(number) if number >= 1 && number <= nearest.len() => {
+                    if let Some(candidate) = nearest.into_iter().nth(number - 1) {
+                        corrections.insert(index, candidate.correction);
+                    }
+                    index = (index + 1).min(rejects.len() - 1);
+                }
+                _ => writeln!(output, "Unknown command: {}", number)?,
+            },
+        }
+        writeln!(output)?;
+    }
+    Ok(corrections.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use csv::StringRecord;
+
+    use super::*;
+    use crate::data::{hotels_reader_for, rooms_reader, DataSource, Hotel};
+
+    fn reject(reason: RejectReason, hotel_code: &str, room_code: &str, source: &str) -> Reject {
+        Reject::new(
+            reason,
+            String::from("Input links to a non existent entity"),
+            Some(2),
+            &StringRecord::from(vec!["hotel_code", "room_code", "source"]),
+            Some(&StringRecord::from(vec![hotel_code, room_code, source])),
+        )
+    }
+
+    fn data() -> Result<(RoomDataSource, HotelDataSource)> {
+        let mut rooms: RoomDataSource = DataSource::new();
+        rooms.import_from(Path::new("test_data/room_names.csv"), rooms_reader)?;
+        let mut hotels: HotelDataSource = DataSource::new();
+        hotels.import_from(
+            Path::new("test_data/hotels.json"),
+            hotels_reader_for("hotels.json"),
+        )?;
+        Ok((rooms, hotels))
+    }
+
+    #[test]
+    fn accept_candidates() -> Result<()> {
+        let (rooms, hotels) = data()?;
+        let rejects = vec![
+            reject(RejectReason::MissingRoom, "BER00002", "BER899", "IHG"),
+            reject(RejectReason::Unparsable, "", "", ""),
+            reject(RejectReason::MissingHotel, "BER00004", "BER848", "MARR"),
+        ];
+        let mut output = vec![];
+        // accept the nearest room, skip the unparsable record, accept the nearest hotel
+        let corrections = explore(
+            &rejects,
+            &rooms,
+            &hotels,
+            "1\nn\n1\nq\n".as_bytes(),
+            &mut output,
+        )?;
+
+        assert_eq!(
+            corrections,
+            vec![
+                Override {
+                    action: Action::Add,
+                    entry: Entry::Room(Room {
+                        hotel_code: String::from("BER00002"),
+                        source: String::from("IHG"),
+                        room_name: String::from("Einzelzimmer"),
+                        room_code: String::from("BER899"),
+                        valid_from: None,
+                        valid_to: None,
+                    })
+                },
+                Override {
+                    action: Action::Add,
+                    entry: Entry::Hotel(Hotel {
+                        id: String::from("BER00004"),
+                        city_code: String::from("BER"),
+                        name: String::from("Crowne Plaza Berlin City Centre"),
+                        category: 4.0,
+                        country_code: String::from("DE"),
+                        city: String::from("Berlin"),
+                        valid_from: None,
+                        valid_to: None,
+                    })
+                }
+            ]
+        );
+        let output = String::from_utf8(output)?;
+        assert!(output.starts_with("Reject 1 of 3, line 2\n"));
+        assert!(
+            output.contains("  1) room BER00002-BER898-IHG \"Einzelzimmer\" (similarity 0.95)\n")
+        );
+        assert!(output.contains("Reject 2 of 3, line 2\n"));
+        assert!(output.contains("No candidates\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn nothing_to_explore() -> Result<()> {
+        let (rooms, hotels) = data()?;
+        let mut output = vec![];
+        assert!(explore(&[], &rooms, &hotels, "".as_bytes(), &mut output)?.is_empty());
+        assert_eq!(
+            String::from_utf8(output)?,
+            "There are no rejects to explore.\n"
+        );
+        Ok(())
+    }
+}
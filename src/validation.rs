@@ -0,0 +1,317 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+
+use crate::bundle;
+use crate::config::{Config, Profile};
+use crate::data::is_database;
+use crate::remote::is_remote;
+use crate::settings::{Command, Settings};
+
+/// Whether the location points to a file on the disk that doesn't exist, the archive is checked for its members.
+/// Files on the web and tables of the databases are not checked.
+fn is_missing(location: &str) -> bool {
+    let path = bundle::split(location, "").map_or(location, |(archive, _)| archive);
+    !is_remote(path) && !is_database(path) && !Path::new(path).exists()
+}
+
+/// Whether the value is in the range, both of the ends are included.
+fn check_range(
+    violations: &mut Vec<String>,
+    option: &str,
+    value: Option<f64>,
+    min: f64,
+    max: Option<f64>,
+) {
+    let value = match value {
+        Some(value) => value,
+        None => return,
+    };
+    match max {
+        Some(max) if !(min..=max).contains(&value) => violations.push(format!(
+            "{} has to be between {} and {}, got {}",
+            option, min, max, value
+        )),
+        None if value < min => violations.push(format!(
+            "{} has to be at least {}, got {}",
+            option, min, value
+        )),
+        _ => {}
+    }
+}
+
+/// Check the settings before any of the files is read or written, so the run doesn't fail halfway through.
+/// All of the problems are reported together, with the files of the given settings that don't exist.
+pub fn validate(settings: &Settings, locations: &[(&str, String)]) -> Result<()> {
+    let mut violations = Vec::new();
+    for (setting, location) in locations {
+        // rooms and hotels are looked up in Redis instead
+        if settings.redis.is_some() && (*setting == "rooms" || *setting == "hotels") {
+            continue;
+        }
+        if is_missing(location) {
+            violations.push(format!("{} {} doesn't exist", setting, location));
+        }
+    }
+
+    check_range(
+        &mut violations,
+        "--min-output-ratio",
+        settings.checks.min_output_ratio,
+        0.0,
+        None,
+    );
+    check_range(
+        &mut violations,
+        "--max-output-ratio",
+        settings.checks.max_output_ratio,
+        0.0,
+        None,
+    );
+    if let (Some(min), Some(max)) = (
+        settings.checks.min_output_ratio,
+        settings.checks.max_output_ratio,
+    ) {
+        if min > max {
+            violations.push(format!(
+                "--min-output-ratio {} is higher than --max-output-ratio {}",
+                min, max
+            ));
+        }
+    }
+    check_range(
+        &mut violations,
+        "--min-coverage",
+        settings.checks.min_coverage,
+        0.0,
+        Some(1.0),
+    );
+    check_range(
+        &mut violations,
+        "--fuzzy-rooms",
+        settings.fuzzy_rooms,
+        0.0,
+        Some(1.0),
+    );
+    check_range(
+        &mut violations,
+        "--anomaly-deviations",
+        settings.anomaly_deviations,
+        0.0,
+        None,
+    );
+    check_range(
+        &mut violations,
+        "--anomaly-factor",
+        settings.anomaly_factor,
+        1.0,
+        None,
+    );
+    check_range(
+        &mut violations,
+        "--min-category",
+        settings.min_category.map(f64::from),
+        0.0,
+        None,
+    );
+    check_range(
+        &mut violations,
+        "--warn-price-above",
+        Some(settings.warn_price_above),
+        0.0,
+        None,
+    );
+    if let Err(e) = settings
+        .sink
+        .output_compression
+        .level(settings.sink.output_compression_level)
+    {
+        violations.push(e.to_string().trim_end_matches('!').to_string());
+    }
+    let delimiter = match &settings.profile {
+        Some(name) if !is_missing(&settings.config) => {
+            match Config::from_path(Path::new(&settings.config))
+                .and_then(|config| config.profile(name).and_then(Profile::delimiter))
+            {
+                Ok(delimiter) => Some(delimiter),
+                Err(e) => {
+                    violations.push(e.to_string().trim_end_matches('!').to_string());
+                    None
+                }
+            }
+        }
+        // the missing config file is reported with the other files
+        Some(_) => None,
+        None => Profile::default().delimiter().ok(),
+    };
+    for (option, quote, delimiter) in [
+        ("--input-quote", Some(settings.input_quote), delimiter),
+        ("--input-escape", settings.input_escape, delimiter),
+        (
+            "--output-quote",
+            Some(settings.sink.output_quote),
+            Some(b';'),
+        ),
+        ("--output-escape", settings.sink.output_escape, Some(b';')),
+    ] {
+        match quote {
+            Some(quote) if !quote.is_ascii() => violations.push(format!(
+                "{} has to be a single ASCII character, got the byte {:#04x}",
+                option, quote
+            )),
+            Some(quote) if Some(quote) == delimiter => violations.push(format!(
+                "{} can't be the delimiter '{}' of the fields",
+                option, quote as char
+            )),
+            _ => {}
+        }
+    }
+    if let Err(e) = crate::field_rules(settings) {
+        violations.push(e.to_string().trim_end_matches('!').to_string());
+    }
+    if settings.skip_if_unchanged && settings.state_dir.is_none() {
+        violations.push(String::from("--skip-if-unchanged needs the --state-dir"));
+    }
+    if settings.queue.sqs.is_some() && settings.queue.rabbitmq.is_some() {
+        violations.push(String::from("--sqs and --rabbitmq can't be used together"));
+    }
+    if settings.queue.sqs.is_some() || settings.queue.rabbitmq.is_some() {
+        for (option, given) in [
+            ("--min-coverage", settings.checks.min_coverage.is_some()),
+            ("--input-sha256", settings.checksums.input_sha256.is_some()),
+            ("--bundle", settings.bundle.is_some()),
+        ] {
+            if given {
+                violations.push(format!("{} can't be used with the queue input", option));
+            }
+        }
+    }
+    if settings.merge_join {
+        // the rooms that are streamed can't be gone through, shared or looked up twice
+        for (option, given) in [
+            ("--fuzzy-rooms", settings.fuzzy_rooms.is_some()),
+            ("--candidate-room-names", settings.candidate_room_names),
+            ("--overrides", settings.overrides.is_some()),
+            ("--redis", settings.redis.is_some()),
+            ("--min-coverage", settings.checks.min_coverage.is_some()),
+            (
+                "the batch or the daemon",
+                matches!(
+                    settings.command,
+                    Some(Command::Batch(_)) | Some(Command::Daemon(_))
+                ),
+            ),
+        ] {
+            if given {
+                violations.push(format!("--merge-join can't be used with {}", option));
+            }
+        }
+        if is_database(&settings.rooms) {
+            violations.push(String::from("--merge-join needs the rooms in a file"));
+        }
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("Settings are invalid: {}!", violations.join("; ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::{CheckSettings, SinkSettings};
+
+    fn locations(settings: &Settings) -> Vec<(&'static str, String)> {
+        vec![
+            ("input", settings.input.clone()),
+            ("rooms", settings.rooms.clone()),
+            ("hotels", settings.hotels.clone()),
+        ]
+    }
+
+    #[test]
+    fn pass_valid_settings() -> Result<()> {
+        let settings = Settings {
+            input: String::from("test_data/input.csv"),
+            rooms: String::from("test_data/room_names.csv"),
+            hotels: String::from("https://example.com/hotels.json"),
+            fuzzy_rooms: Some(0.8),
+            ..Settings::default()
+        };
+        validate(&settings, &locations(&settings))
+    }
+
+    #[test]
+    fn report_all_violations() {
+        let settings = Settings {
+            input: String::from("test_data/missing.csv"),
+            rooms: String::from("test_data/missing.zip/room_names.csv"),
+            hotels: String::from("test_data/hotels.json"),
+            checks: CheckSettings {
+                min_output_ratio: Some(1.0),
+                max_output_ratio: Some(0.9),
+                ..Settings::default().checks
+            },
+            fuzzy_rooms: Some(1.5),
+            skip_if_unchanged: true,
+            ..Settings::default()
+        };
+        assert_eq!(
+            validate(&settings, &locations(&settings))
+                .expect_err("This should fail")
+                .to_string(),
+            "Settings are invalid: input test_data/missing.csv doesn't exist; \
+             rooms test_data/missing.zip/room_names.csv doesn't exist; \
+             --min-output-ratio 1 is higher than --max-output-ratio 0.9; \
+             --fuzzy-rooms has to be between 0 and 1, got 1.5; \
+             --skip-if-unchanged needs the --state-dir!"
+        );
+    }
+
+    #[test]
+    fn report_invalid_delimiters_and_quotes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = dir.path().join("axiv.toml");
+        std::fs::write(&config, "[profiles.ROUGH]\ndelimiter = \"ä\"\n")?;
+        let settings = Settings {
+            input: String::from("test_data/input.csv"),
+            rooms: String::from("test_data/room_names.csv"),
+            hotels: String::from("test_data/hotels.json"),
+            config: config.display().to_string(),
+            profile: Some(String::from("ROUGH")),
+            sink: SinkSettings {
+                output_quote: 0xe4,
+                ..Settings::default().sink
+            },
+            ..Settings::default()
+        };
+        assert_eq!(
+            validate(&settings, &locations(&settings))
+                .expect_err("This should fail")
+                .to_string(),
+            "Settings are invalid: Delimiter of the input has to be an ASCII character, got 'ä'; \
+             --output-quote has to be a single ASCII character, got the byte 0xe4!"
+        );
+
+        // without the profile the fields of the input are separated by the default delimiter
+        let settings = Settings {
+            profile: None,
+            input_quote: b'|',
+            sink: SinkSettings {
+                output_escape: Some(b';'),
+                ..Settings::default().sink
+            },
+            ..settings
+        };
+        assert_eq!(
+            validate(&settings, &locations(&settings))
+                .expect_err("This should fail")
+                .to_string(),
+            "Settings are invalid: --input-quote can't be the delimiter '|' of the fields; \
+             --output-escape can't be the delimiter ';' of the fields!"
+        );
+        Ok(())
+    }
+}
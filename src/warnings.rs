@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::data::Output;
+use crate::errors::ErrorReporter;
+use crate::lock;
+
+/// Destination of the warnings about the enriched records.
+/// Warnings are counted by their kind for the summary and, when the warnings file is given,
+/// saved there one JSON object per line. Records with warnings are still saved in the output.
+pub struct WarningsSink {
+    file: Option<BufWriter<File>>,
+    reporter: Option<ErrorReporter>,
+    counts: BTreeMap<String, usize>,
+}
+
+impl WarningsSink {
+    pub fn new(path: Option<&str>) -> Result<Self> {
+        let file = match path {
+            Some(path) => Some(BufWriter::new(lock::lock_for_writing(Path::new(path))?)),
+            None => None,
+        };
+        Ok(Self {
+            file,
+            reporter: None,
+            counts: BTreeMap::new(),
+        })
+    }
+
+    /// Report every warning to the given reporter as well.
+    pub fn with_reporter(mut self, reporter: ErrorReporter) -> Self {
+        self.reporter = Some(reporter);
+        self
+    }
+
+    /// Take the warnings of the enriched record and pass it through.
+    pub fn handle(&mut self, mut output: Output) -> Result<Output> {
+        for warning in output.warnings.drain(..) {
+            *self
+                .counts
+                .entry(warning.kind.code().to_string())
+                .or_insert(0) += 1;
+            if let Some(reporter) = &mut self.reporter {
+                reporter.warned(&warning)?;
+            }
+            if let Some(file) = &mut self.file {
+                serde_json::to_writer(&mut *file, &warning)
+                    .with_context(|| "Couldn't save the warning!")?;
+                writeln!(file).with_context(|| "Couldn't save the warning!")?;
+            }
+        }
+        Ok(output)
+    }
+
+    /// Save whatever is still buffered and return how many warnings of each kind there were.
+    pub fn finish(self) -> Result<BTreeMap<String, usize>> {
+        if let Some(mut file) = self.file {
+            file.flush()
+                .with_context(|| "Couldn't save the warnings!")?;
+        }
+        Ok(self.counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::data::{Warning, WarningKind};
+    use crate::errors::ErrorFormat;
+    use crate::testing;
+
+    #[test]
+    fn report_warnings_as_json() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("errors.jsonl");
+        let mut sink = WarningsSink::new(None)?
+            .with_reporter(ErrorReporter::new(ErrorFormat::Json, path.to_str())?);
+        let mut output = testing::output().build();
+        output.warnings.push(Warning {
+            line: 2,
+            kind: WarningKind::HighPrice,
+            message: String::from("Price per person 1085.50 is above 1000.00"),
+        });
+        assert!(sink.handle(output)?.warnings.is_empty());
+        assert_eq!(sink.finish()?.get("high_price"), Some(&1));
+
+        assert_eq!(
+            read_to_string(&path)?,
+            concat!(
+                r#"{"code":"high_price","message":"Price per person 1085.50 is above 1000.00","line":2,"fields":{},"field":null,"hint":null}"#,
+                "\n"
+            )
+        );
+        Ok(())
+    }
+}
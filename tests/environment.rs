@@ -0,0 +1,60 @@
+use std::env;
+
+use clap::Clap;
+
+use axiv::{Command, Consolidation, Settings};
+
+// The environment is changed only in this test binary, the settings of the other tests aren't affected by it
+#[test]
+fn read_environment_variables() {
+    env::set_var("AXIV_OUTPUT", "env-output.csv");
+    env::set_var("AXIV_RETRIES", "7");
+    env::set_var("AXIV_CONSOLIDATE", "cheapest");
+    env::set_var("AXIV_EXPAND_NIGHTS", "true");
+    env::set_var("AXIV_NAME", "ihg");
+    let settings = Settings::parse_from(["axiv"]);
+    assert_eq!(settings.output, "env-output.csv");
+    assert_eq!(settings.remote.retries, 7);
+    assert_eq!(settings.consolidate, Some(Consolidation::Cheapest));
+    assert!(settings.expand_nights);
+    assert!(!settings.timings);
+
+    // options given explicitly take precedence
+    let settings = Settings::parse_from(["axiv", "-o", "output.csv", "--expand-nights"]);
+    assert_eq!(settings.output, "output.csv");
+    assert!(settings.expand_nights);
+
+    let settings = Settings::parse_from(["axiv", "submit", "--input", "ihg.csv"]);
+    match settings.command {
+        Some(Command::Submit(submit)) => {
+            assert_eq!(submit.name.as_deref(), Some("ihg"));
+            assert_eq!(submit.input, "ihg.csv");
+            assert_eq!(submit.output, "env-output.csv");
+        }
+        command => panic!("Expected the submit command, got {:?}", command),
+    }
+
+    // unless the settings are parsed from the arguments alone
+    let settings = Settings::from_arguments([
+        "axiv",
+        "submit",
+        "--input",
+        "ihg.csv",
+        "-o",
+        "ihg_enriched.csv",
+    ]);
+    assert_eq!(settings.output, "output.csv");
+    assert_eq!(settings.remote.retries, 3);
+    assert_eq!(settings.consolidate, None);
+    assert!(!settings.expand_nights);
+    match settings.command {
+        Some(Command::Submit(submit)) => {
+            assert_eq!(submit.name, None);
+            assert_eq!(submit.output, "ihg_enriched.csv");
+        }
+        command => panic!("Expected the submit command, got {:?}", command),
+    }
+    let settings = Settings::from_arguments(["axiv", "--expand-nights"]);
+    assert!(settings.expand_nights);
+    assert_eq!(Settings::default().output, "output.csv");
+}
@@ -1,5 +1,10 @@
+#[cfg(feature = "bundle")]
+use std::path::Path;
+
 use file_diff::diff;
 
+#[cfg(feature = "bundle")]
+use axiv::compare::compare;
 use axiv::{run, Settings};
 
 #[test]
@@ -10,8 +15,27 @@ fn integrate_data() {
         output: String::from("test-output.csv"),
         hotels: String::from("hotels.json"),
         rooms: String::from("room_names.csv"),
+        ..Settings::default()
     };
     run(&settings).expect("This shouldn't fail");
     // Ensure that our integration tool produces expected output
     assert!(diff("expected.csv", "test-output.csv"));
 }
+
+#[test]
+#[cfg(feature = "bundle")]
+fn integrate_bundled_data() {
+    // Input, rooms and hotels are members of the same archive
+    let settings = Settings {
+        output: String::from("test-bundle-output.csv"),
+        bundle: Some(String::from("test_data/feed.zip")),
+        ..Settings::default()
+    };
+    run(&settings).expect("This shouldn't fail");
+    let differences = compare(
+        Path::new("expected.csv"),
+        Path::new("test-bundle-output.csv"),
+    )
+    .expect("This shouldn't fail");
+    assert!(differences.is_empty(), "{}", differences);
+}
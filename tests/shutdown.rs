@@ -0,0 +1,62 @@
+use std::fs::{read_dir, read_to_string, write};
+
+use tempfile::tempdir;
+
+use axiv::{
+    request_shutdown, run_with_events, CheckSettings, EventHandler, Settings, TERMINATED_EXIT_CODE,
+};
+
+/// Asks the run to stop once the given number of records is read, as if it got Ctrl-C.
+struct Interrupt {
+    after: usize,
+}
+
+impl EventHandler for Interrupt {
+    fn progress(&mut self, records: usize) {
+        if records == self.after {
+            request_shutdown();
+        }
+    }
+}
+
+// The request to stop is global, so it's raised only in this test binary, the runs of the other tests aren't affected by it
+#[test]
+fn stop_gracefully() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let input = dir.path().join("input.csv");
+    let mut content = String::from(
+        "city_code|hotel_code|room_type|room_code|meal|checkin|adults|children|price|source\n",
+    );
+    for _ in 0..2500 {
+        content.push_str("BER|BER00002|EZ|BER898|F|20180721|1|0|85.50|IHG\n");
+    }
+    write(&input, content)?;
+    let state_dir = dir.path().join("state");
+    let output = dir.path().join("output.csv");
+    let settings = Settings {
+        input: input.display().to_string(),
+        output: output.display().to_string(),
+        rooms: String::from("test_data/room_names.csv"),
+        hotels: String::from("test_data/hotels.json"),
+        state_dir: Some(state_dir.display().to_string()),
+        // the whole input would fall short of it, but the terminated run isn't checked
+        checks: CheckSettings {
+            min_records: Some(5000),
+            ..Settings::default().checks
+        },
+        ..Settings::default()
+    };
+    let summary = run_with_events(&settings, &mut Interrupt { after: 1000 })?;
+
+    assert!(summary.terminated);
+    assert_eq!(summary.exit_code(), TERMINATED_EXIT_CODE);
+    // the records enriched before the run was stopped are saved
+    assert_eq!(summary.records, 1000);
+    assert_eq!(read_to_string(&output)?.lines().count(), 1001);
+    assert!(summary
+        .to_string()
+        .starts_with("The run was terminated early, only a part of the data was saved at"));
+    // the partial output isn't recorded as the outcome of the run
+    assert!(!state_dir.exists() || read_dir(&state_dir)?.next().is_none());
+    Ok(())
+}